@@ -0,0 +1,161 @@
+use brainfuck::{
+    arena::{create_arena, run_arena, Arena},
+    create_ast, interpreter_init, lexical_analysis_str,
+    optimizer::optimizer_pipeline,
+    run_program, syntax_analysis,
+    vm::{flatten_commands, FlatInterpreter},
+    InputSource, Interpreter, Node, OverflowMode, MEMORY_SIZE,
+};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use std::{cell::Cell, fs, io, rc::Rc};
+
+fn read_program(name: &str) -> String {
+    fs::read_to_string(format!("test_files/{}", name)).unwrap()
+}
+
+fn parse(source: &str) -> Node {
+    syntax_analysis(lexical_analysis_str(source), source).unwrap()
+}
+
+// An interpreter reading nothing and writing nowhere, like `--input-string ""`
+// with `--output /dev/null`
+fn quiet_interpreter() -> Interpreter {
+    let mut interpreter = interpreter_init(MEMORY_SIZE);
+    interpreter.input = InputSource::Buffer(Vec::new(), 0);
+    interpreter.output = Box::new(io::sink());
+    interpreter
+}
+
+fn bench_lexical_analysis(c: &mut Criterion) {
+    // the first 10 KB of mandelbrot, lexing does not need matched brackets
+    let source: String = read_program("mandelbrot.txt")
+        .chars()
+        .take(10 * 1024)
+        .collect();
+    let mut group = c.benchmark_group("lexical_analysis");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("10KB", |b| b.iter(|| lexical_analysis_str(&source)));
+    group.finish();
+}
+
+fn bench_syntax_analysis(c: &mut Criterion) {
+    let depth = 1000;
+    let source = format!("{}+{}", "[".repeat(depth), "]".repeat(depth));
+    let commands = lexical_analysis_str(&source);
+    let mut group = c.benchmark_group("syntax_analysis");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("nested 1000 deep", |b| {
+        b.iter_batched(
+            || commands.clone(),
+            |commands| syntax_analysis(commands, &source).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn bench_run_program(c: &mut Criterion) {
+    let mut group = c.benchmark_group("run_program");
+    group.throughput(Throughput::Elements(1));
+    for name in ["hello-world", "fibonacci"].iter() {
+        let ast = parse(&read_program(&format!("{}.txt", name)));
+        group.bench_function(*name, |b| {
+            b.iter_batched(
+                quiet_interpreter,
+                |mut interpreter| run_program(&mut interpreter, &ast).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+
+    // a single run takes seconds even optimized, criterion's fewest samples will do
+    let mut group = c.benchmark_group("run_program");
+    group.throughput(Throughput::Elements(1)).sample_size(10);
+    let eliminated = Rc::new(Cell::new(0));
+    let ast = optimizer_pipeline(&eliminated, OverflowMode::Wrap)
+        .run(parse(&read_program("mandelbrot.txt")));
+    group.bench_function("mandelbrot", |b| {
+        b.iter_batched(
+            quiet_interpreter,
+            |mut interpreter| run_program(&mut interpreter, &ast).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+// Building the tree of mandelbrot, a Vec per node with children, against the
+// arena holding every node in one Vec
+fn bench_arena(c: &mut Criterion) {
+    let source = read_program("mandelbrot.txt");
+    let commands = lexical_analysis_str(&source);
+    let mut group = c.benchmark_group("arena");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("create_ast mandelbrot", |b| {
+        b.iter(|| {
+            let mut program = parse("");
+            create_ast(&mut program, &commands, &mut 0);
+            program
+        })
+    });
+    group.bench_function("create_arena mandelbrot", |b| {
+        b.iter(|| create_arena(&commands))
+    });
+    let ast = parse(&read_program("fibonacci.txt"));
+    let (arena, root) = Arena::from_node(&ast);
+    group.bench_function("run_arena fibonacci", |b| {
+        b.iter_batched(
+            quiet_interpreter,
+            |mut interpreter| run_arena(&mut interpreter, &arena, root).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+// The match of FlatInterpreter::run against the table of handlers of run_dispatch,
+// on mandelbrot after the optimizer
+fn bench_dispatch(c: &mut Criterion) {
+    let eliminated = Rc::new(Cell::new(0));
+    let ast = optimizer_pipeline(&eliminated, OverflowMode::Wrap)
+        .run(parse(&read_program("mandelbrot.txt")));
+    let commands = flatten_commands(&ast);
+    let mut group = c.benchmark_group("dispatch");
+    group.throughput(Throughput::Elements(1)).sample_size(10);
+    group.bench_function("match mandelbrot", |b| {
+        b.iter_batched(
+            quiet_interpreter,
+            |mut interpreter| {
+                FlatInterpreter::new(commands.clone(), &mut interpreter)
+                    .unwrap()
+                    .run()
+                    .unwrap()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("table mandelbrot", |b| {
+        b.iter_batched(
+            quiet_interpreter,
+            |mut interpreter| {
+                FlatInterpreter::new(commands.clone(), &mut interpreter)
+                    .unwrap()
+                    .run_dispatch()
+                    .unwrap()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_lexical_analysis,
+    bench_syntax_analysis,
+    bench_run_program,
+    bench_arena,
+    bench_dispatch
+);
+criterion_main!(benches);
@@ -0,0 +1,82 @@
+#ifndef BRAINFUCK_H
+#define BRAINFUCK_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef struct Interpreter Interpreter;
+
+/**
+ * The program ran to the end, or halted with '@'
+ */
+#define BF_OK 0
+
+/**
+ * The source was null or not UTF-8
+ */
+#define BF_INVALID_SOURCE 1
+
+/**
+ * The source has an unmatched bracket
+ */
+#define BF_SYNTAX_ERROR 2
+
+/**
+ * The program failed while running, the pointer left the tape for example
+ */
+#define BF_RUNTIME_ERROR 3
+
+/**
+ * The interpreter panicked, its tape may be left in any state
+ */
+#define BF_PANIC 4
+
+/**
+ * A new interpreter with `tape_size` zero cells, freed with `bf_interpreter_destroy`.
+ * Null for a `tape_size` of 0
+ */
+Interpreter *bf_interpreter_create(uintptr_t tape_size);
+
+/**
+ * Run the `len` bytes of source at `source` on the interpreter, returns one of
+ * the `BF_` codes
+ *
+ * # Safety
+ *
+ * `interp` comes from `bf_interpreter_create` and was not destroyed, `source`
+ * points to `len` readable bytes
+ */
+int bf_run_source(Interpreter *interp, const char *source, uintptr_t len);
+
+/**
+ * Value of the cell at `index` in memory, 0 past the end of the tape
+ *
+ * # Safety
+ *
+ * `interp` comes from `bf_interpreter_create` and was not destroyed
+ */
+uint8_t bf_get_cell(const Interpreter *interp, uintptr_t index);
+
+/**
+ * The data pointer, cells left of the start of a bidirectional tape are negative
+ *
+ * # Safety
+ *
+ * `interp` comes from `bf_interpreter_create` and was not destroyed
+ */
+int32_t bf_get_pointer(const Interpreter *interp);
+
+/**
+ * Free an interpreter, a null pointer is ignored
+ *
+ * # Safety
+ *
+ * `interp` comes from `bf_interpreter_create` and is not used afterwards
+ */
+void bf_interpreter_destroy(Interpreter *interp);
+
+#endif  /* BRAINFUCK_H */
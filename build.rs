@@ -0,0 +1,19 @@
+// Writes brainfuck.h, the C declarations of the functions in src/ffi.rs, to OUT_DIR.
+// Set BRAINFUCK_UPDATE_HEADER to also update the copy at the root of the crate
+fn main() {
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=BRAINFUCK_UPDATE_HEADER");
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir))
+        .expect("cbindgen.toml could not be read");
+    let header = cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(format!("{}/src/ffi.rs", crate_dir))
+        .generate()
+        .expect("brainfuck.h could not be generated");
+    header.write_to_file(format!("{}/brainfuck.h", std::env::var("OUT_DIR").unwrap()));
+    if std::env::var_os("BRAINFUCK_UPDATE_HEADER").is_some() {
+        header.write_to_file(format!("{}/brainfuck.h", crate_dir));
+    }
+}
@@ -0,0 +1,10 @@
+//! Lex and parse arbitrary bytes, errors are fine but panics are not.
+#![no_main]
+
+use brainfuck::{lexical_analysis_str, syntax_analysis};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let source = String::from_utf8_lossy(data).into_owned();
+    let _ = syntax_analysis(lexical_analysis_str(&source), &source);
+});
@@ -0,0 +1,25 @@
+//! Run arbitrary bytes through the parser, the optimizer and the interpreter for
+//! up to 1000 steps, errors are fine but panics are not.
+#![no_main]
+
+use brainfuck::{
+    interpreter_init, lexical_analysis_str, optimizer::optimizer_pipeline, run_program,
+    syntax_analysis, InputSource, OverflowMode, MEMORY_SIZE,
+};
+use libfuzzer_sys::fuzz_target;
+use std::{cell::Cell, io, rc::Rc};
+
+fuzz_target!(|data: &[u8]| {
+    let source = String::from_utf8_lossy(data).into_owned();
+    let ast = match syntax_analysis(lexical_analysis_str(&source), &source) {
+        Ok(ast) => ast,
+        Err(_) => return,
+    };
+    let eliminated = Rc::new(Cell::new(0));
+    let mut interpreter = interpreter_init(MEMORY_SIZE);
+    interpreter.max_steps = Some(1000);
+    interpreter.input = InputSource::Buffer(Vec::new(), 0);
+    interpreter.output = Box::new(io::sink());
+    let ast = optimizer_pipeline(&eliminated, OverflowMode::Wrap).run(ast);
+    let _ = run_program(&mut interpreter, &ast);
+});
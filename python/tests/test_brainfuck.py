@@ -0,0 +1,38 @@
+from pathlib import Path
+
+import pytest
+
+import brainfuck
+
+TEST_FILES = Path(__file__).resolve().parents[2] / "test_files"
+
+
+def test_hello_world():
+    interpreter = brainfuck.Interpreter()
+    source = (TEST_FILES / "hello-world.txt").read_text()
+    assert interpreter.run(source) == b"Hello World!\n"
+
+
+def test_tape_stays_between_runs():
+    interpreter = brainfuck.Interpreter(tape_size=4)
+    assert interpreter.run("+++>++") == b""
+    interpreter.run("[<+>-]")
+    assert interpreter.get_tape() == [5, 0, 0, 0]
+    assert interpreter.get_pointer() == 1
+
+
+def test_input_is_read_by_comma():
+    interpreter = brainfuck.Interpreter(8)
+    interpreter.set_input(b"ab")
+    assert interpreter.run(",+.,+.,.") == b"bc\x00"
+
+
+def test_errors_are_raised():
+    interpreter = brainfuck.Interpreter()
+    with pytest.raises(ValueError, match="unmatched"):
+        interpreter.run("[")
+
+
+def test_empty_tape_is_refused():
+    with pytest.raises(ValueError, match="tape_size"):
+        brainfuck.Interpreter(tape_size=0)
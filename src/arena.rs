@@ -0,0 +1,172 @@
+//! The syntax tree stored flat in one vector. Every node holds the index of its
+//! first child and of its next sibling instead of a `Vec` of children, so a
+//! program of n commands takes one allocation that grows, not one `Vec` per loop.
+
+use crate::cell::CellArithmetic;
+use crate::tape::Tape;
+use crate::Command::{self, *};
+use crate::NodeType::{self, *};
+use crate::{
+    check_interrupt, check_timeout, count_step, execute_instruction, memory_index, Interpreter,
+    InterpreterError, Node,
+};
+use std::time::Instant;
+
+/// `first_child` and `next_sibling` of a node without one
+pub const NONE: u32 = u32::MAX;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ArenaNode {
+    pub node_type: NodeType,
+    pub instruction: Command,
+    pub source_pos: usize, // byte offset of the command in the source
+    pub first_child: u32,
+    pub next_sibling: u32,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Arena {
+    pub nodes: Vec<ArenaNode>,
+}
+
+impl Arena {
+    /// The tree in an arena with the index of its root, the root is always 0
+    pub fn from_node(ast: &Node) -> (Arena, u32) {
+        let mut arena = Arena::default();
+        let root = arena.push(ast.node_type, ast.instruction, ast.source_pos);
+        // nodes whose children are being added with the last one added so far
+        let mut stack = vec![(ast.childrens.iter(), root, NONE)];
+        while let Some(top) = stack.last_mut() {
+            let (parent, last) = (top.1, top.2);
+            match top.0.next() {
+                Some(child) => {
+                    let index = arena.push(child.node_type, child.instruction, child.source_pos);
+                    arena.link(parent, last, index);
+                    stack.last_mut().unwrap().2 = index;
+                    if !child.childrens.is_empty() {
+                        stack.push((child.childrens.iter(), index, NONE));
+                    }
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+        (arena, root)
+    }
+
+    /// The node at `index`
+    pub fn node(&self, index: u32) -> &ArenaNode {
+        &self.nodes[index as usize]
+    }
+
+    /// Indices of the children of the node at `index`, in order
+    pub fn children(&self, index: u32) -> impl Iterator<Item = u32> + '_ {
+        let first = self.node(index).first_child;
+        std::iter::successors(Some(first).filter(|&child| child != NONE), move |&child| {
+            Some(self.node(child).next_sibling).filter(|&next| next != NONE)
+        })
+    }
+
+    // Add a node without children or siblings and return its index
+    fn push(&mut self, node_type: NodeType, instruction: Command, source_pos: usize) -> u32 {
+        self.nodes.push(ArenaNode {
+            node_type,
+            instruction,
+            source_pos,
+            first_child: NONE,
+            next_sibling: NONE,
+        });
+        (self.nodes.len() - 1) as u32
+    }
+
+    // Make `index` the child of `parent` following `last`, its first child when
+    // `last` is NONE
+    fn link(&mut self, parent: u32, last: u32, index: u32) {
+        match last {
+            NONE => self.nodes[parent as usize].first_child = index,
+            _ => self.nodes[last as usize].next_sibling = index,
+        }
+    }
+}
+
+/// `create_ast` building an arena, returned with the index of the `Program` root.
+/// The brackets must match, as `syntax_analysis` checks
+pub fn create_arena(commands: &[(Command, usize)]) -> (Arena, u32) {
+    let mut arena = Arena::default();
+    let root = arena.push(Program, Command::Default, 0);
+    let mut open = vec![(root, NONE)];
+    for &(command, pos) in commands.iter() {
+        if let JumpBackward | EndSubroutine = command {
+            if open.len() > 1 {
+                open.pop();
+            }
+            continue;
+        }
+        let node_type = if command == JumpForward {
+            Loop
+        } else {
+            Operator
+        };
+        let index = arena.push(node_type, command, pos);
+        let (parent, last) = *open.last().unwrap();
+        arena.link(parent, last, index);
+        open.last_mut().unwrap().1 = index;
+        if let JumpForward | DefineSubroutine = command {
+            open.push((index, NONE));
+        }
+    }
+    (arena, root)
+}
+
+/// `run_program` walking the arena from `root`, subroutines are not run
+pub fn run_arena<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &mut Interpreter<T, M>,
+    arena: &Arena,
+    root: u32,
+) -> Result<(), InterpreterError> {
+    // nodes being run with their next child and the iterations so far, the way
+    // run_program keeps them
+    let start = Instant::now();
+    let mut stack: Vec<(u32, u32, u64)> = vec![(root, arena.node(root).first_child, 0)];
+    while let Some(&(node, child, iterations)) = stack.last() {
+        let top = stack.len() - 1;
+        let current = memory_index(interpreter);
+        if child != NONE {
+            let next = arena.node(child);
+            stack[top].1 = next.next_sibling;
+            match next.node_type {
+                Loop if !interpreter.memory.get(current).is_zero() => {
+                    check_interrupt(interpreter)?;
+                    check_timeout(interpreter, start)?;
+                    check_loop_iterations(interpreter, next, 1)?;
+                    stack.push((child, next.first_child, 1));
+                }
+                Operator => execute_instruction(interpreter, &next.instruction, current)?,
+                _ => {}
+            }
+        } else if top > 0 && !interpreter.memory.get(current).is_zero() {
+            count_step(interpreter)?;
+            check_interrupt(interpreter)?;
+            check_timeout(interpreter, start)?;
+            check_loop_iterations(interpreter, arena.node(node), iterations + 1)?;
+            stack[top] = (node, arena.node(node).first_child, iterations + 1);
+        } else {
+            stack.pop();
+        }
+    }
+    Ok(())
+}
+
+fn check_loop_iterations<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &Interpreter<T, M>,
+    node: &ArenaNode,
+    iterations: u64,
+) -> Result<(), InterpreterError> {
+    match interpreter.max_loop_iters {
+        Some(limit) if iterations > limit => Err(InterpreterError::LoopIterationLimitExceeded {
+            offset: node.source_pos,
+        }),
+        _ => Ok(()),
+    }
+}
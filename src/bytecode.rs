@@ -0,0 +1,304 @@
+//! Flat bytecode with precomputed jump targets and its compact binary format.
+//!
+//! A serialized program starts with the magic `BF\x01` and a format version byte,
+//! followed by the instruction count and the instructions. Every instruction is an
+//! opcode byte followed by its operand, all integers are LEB128 varints and signed
+//! operands are zigzag encoded first.
+
+use crate::vm::{build_jump_table, JumpTableError};
+use crate::Command::*;
+use crate::NodeType::*;
+use crate::{Command, Node};
+use std::{convert::TryFrom, fmt};
+
+pub const MAGIC: &[u8] = b"BF\x01";
+pub const FORMAT_VERSION: u8 = 1;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Bytecode {
+    MoveBy(i32),           // move the data pointer
+    AddBy(i8),             // add to the byte at the data pointer
+    SetZero,               // set the byte at the data pointer to zero
+    AddTo { offset: i32 }, // add the byte to the byte `offset` cells away and clear it
+    ScanRight,             // move right to the closest zero byte
+    ScanLeft,              // move left to the closest zero byte
+    Output,                // output the byte at the data pointer
+    Input,                 // read one byte of input into the data pointer
+    JumpForward(u32),      // jump to the target when the byte is zero
+    JumpBackward(u32),     // jump to the target when the byte is nonzero
+    DebugPrint,            // print the cells around the pointer to stderr
+    Halt,                  // stop the program
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BytecodeError {
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    UnexpectedEnd,
+    UnknownOpcode(u8),
+    InvalidOperand { position: usize },
+    InvalidJumpTarget { position: usize },
+}
+
+impl fmt::Display for BytecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BytecodeError::InvalidMagic => write!(f, "not a bytecode file"),
+            BytecodeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported bytecode version {}", version)
+            }
+            BytecodeError::UnexpectedEnd => write!(f, "bytecode ends unexpectedly"),
+            BytecodeError::UnknownOpcode(opcode) => write!(f, "unknown opcode {}", opcode),
+            BytecodeError::InvalidOperand { position } => {
+                write!(f, "invalid operand of instruction {}", position)
+            }
+            BytecodeError::InvalidJumpTarget { position } => {
+                write!(f, "invalid jump target of instruction {}", position)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BytecodeError {}
+
+// Append the bytecode of all children of the node
+fn compile_nodes(node: &Node, program: &mut Vec<Bytecode>) {
+    for child in node.childrens.iter() {
+        if child.node_type == Loop {
+            let start = program.len();
+            program.push(Bytecode::JumpForward(0));
+            compile_nodes(child, program);
+            program.push(Bytecode::JumpBackward(start as u32 + 1));
+            program[start] = Bytecode::JumpForward(program.len() as u32);
+            continue;
+        }
+        let instruction = match child.instruction {
+            IncDP => Bytecode::MoveBy(1),
+            DecDP => Bytecode::MoveBy(-1),
+            MoveBy(delta) => Bytecode::MoveBy(delta),
+            IncByte => Bytecode::AddBy(1),
+            DecByte => Bytecode::AddBy(-1),
+            AddByte(delta) => Bytecode::AddBy(delta),
+            SetZero => Bytecode::SetZero,
+            AddTo { offset } => Bytecode::AddTo { offset },
+            ScanRight => Bytecode::ScanRight,
+            ScanLeft => Bytecode::ScanLeft,
+            OutByte => Bytecode::Output,
+            InByte => Bytecode::Input,
+            DebugPrint => Bytecode::DebugPrint,
+            Halt => Bytecode::Halt,
+            _ => continue,
+        };
+        program.push(instruction);
+    }
+}
+
+/// Flatten the tree to bytecode, loops become jumps with precomputed targets
+pub fn compile_bytecode(ast: &Node) -> Vec<Bytecode> {
+    let mut program = Vec::new();
+    compile_nodes(ast, &mut program);
+    program
+}
+
+impl Bytecode {
+    /// Interpreter command performing the instruction, jumps map to the brackets
+    pub fn command(self) -> Command {
+        match self {
+            Bytecode::MoveBy(delta) => MoveBy(delta),
+            Bytecode::AddBy(delta) => AddByte(delta),
+            Bytecode::SetZero => SetZero,
+            Bytecode::AddTo { offset } => AddTo { offset },
+            Bytecode::ScanRight => ScanRight,
+            Bytecode::ScanLeft => ScanLeft,
+            Bytecode::Output => OutByte,
+            Bytecode::Input => InByte,
+            Bytecode::DebugPrint => DebugPrint,
+            Bytecode::Halt => Halt,
+            Bytecode::JumpForward(_) => JumpForward,
+            Bytecode::JumpBackward(_) => JumpBackward,
+        }
+    }
+}
+
+/// Check that every jump targets the instruction right after its matching
+/// bracket, as paired by `build_jump_table`
+pub fn check_jump_targets(program: &[Bytecode]) -> Result<(), BytecodeError> {
+    let commands: Vec<Command> = program.iter().map(|bytecode| bytecode.command()).collect();
+    let table = build_jump_table(&commands).map_err(|error| match error {
+        JumpTableError::UnmatchedOpen { offset } | JumpTableError::UnmatchedClose { offset } => {
+            BytecodeError::InvalidJumpTarget { position: offset }
+        }
+    })?;
+    for (position, instruction) in program.iter().enumerate() {
+        match *instruction {
+            Bytecode::JumpForward(target) | Bytecode::JumpBackward(target)
+                if target as usize != table[position] + 1 =>
+            {
+                return Err(BytecodeError::InvalidJumpTarget { position });
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Rebuild the tree from bytecode with matching jumps
+pub fn bytecode_to_ast(program: &[Bytecode]) -> Node {
+    let mut stack = vec![Node {
+        node_type: Program,
+        instruction: Default,
+        source_pos: 0,
+        childrens: Vec::new(),
+    }];
+    for instruction in program.iter() {
+        match *instruction {
+            Bytecode::JumpForward(_) => stack.push(Node {
+                node_type: Loop,
+                instruction: JumpForward,
+                source_pos: 0,
+                childrens: Vec::new(),
+            }),
+            Bytecode::JumpBackward(_) => {
+                let finished = stack.pop().unwrap();
+                stack.last_mut().unwrap().childrens.push(finished);
+            }
+            _ => stack.last_mut().unwrap().childrens.push(Node {
+                node_type: Operator,
+                instruction: instruction.command(),
+                source_pos: 0,
+                childrens: Vec::new(),
+            }),
+        }
+    }
+    stack.pop().unwrap()
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+/// Encode the program in the binary bytecode format
+pub fn serialize_bytecode(program: &[Bytecode]) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    out.push(FORMAT_VERSION);
+    write_varint(&mut out, program.len() as u64);
+    for instruction in program.iter() {
+        let (opcode, operand) = match *instruction {
+            Bytecode::MoveBy(delta) => (0, Some(zigzag(delta as i64))),
+            Bytecode::AddBy(delta) => (1, Some(zigzag(delta as i64))),
+            Bytecode::SetZero => (2, None),
+            Bytecode::AddTo { offset } => (3, Some(zigzag(offset as i64))),
+            Bytecode::ScanRight => (4, None),
+            Bytecode::ScanLeft => (5, None),
+            Bytecode::Output => (6, None),
+            Bytecode::Input => (7, None),
+            Bytecode::JumpForward(target) => (8, Some(target as u64)),
+            Bytecode::JumpBackward(target) => (9, Some(target as u64)),
+            Bytecode::DebugPrint => (10, None),
+            Bytecode::Halt => (11, None),
+        };
+        out.push(opcode);
+        if let Some(operand) = operand {
+            write_varint(&mut out, operand);
+        }
+    }
+    out
+}
+
+// Reads varints and opcodes from the serialized bytes
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, BytecodeError> {
+        let byte = *self
+            .bytes
+            .get(self.position)
+            .ok_or(BytecodeError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<Option<u64>, BytecodeError> {
+        let mut value: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Decode a program in the binary bytecode format, checking that jumps match
+pub fn deserialize_bytecode(bytes: &[u8]) -> Result<Vec<Bytecode>, BytecodeError> {
+    if !bytes.starts_with(MAGIC) {
+        return Err(BytecodeError::InvalidMagic);
+    }
+    let mut reader = Reader {
+        bytes,
+        position: MAGIC.len(),
+    };
+    let version = reader.byte()?;
+    if version != FORMAT_VERSION {
+        return Err(BytecodeError::UnsupportedVersion(version));
+    }
+    let count = reader
+        .varint()?
+        .ok_or(BytecodeError::InvalidOperand { position: 0 })?;
+
+    let mut program = Vec::new();
+    for position in 0..count as usize {
+        let opcode = reader.byte()?;
+        let invalid = BytecodeError::InvalidOperand { position };
+        let signed = |reader: &mut Reader| -> Result<i32, BytecodeError> {
+            let value = unzigzag(reader.varint()?.ok_or(BytecodeError::UnexpectedEnd)?);
+            i32::try_from(value).map_err(|_| BytecodeError::InvalidOperand { position })
+        };
+        let instruction = match opcode {
+            0 => Bytecode::MoveBy(signed(&mut reader)?),
+            1 => Bytecode::AddBy(i8::try_from(signed(&mut reader)?).map_err(|_| invalid)?),
+            2 => Bytecode::SetZero,
+            3 => Bytecode::AddTo {
+                offset: signed(&mut reader)?,
+            },
+            4 => Bytecode::ScanRight,
+            5 => Bytecode::ScanLeft,
+            6 => Bytecode::Output,
+            7 => Bytecode::Input,
+            8 | 9 => {
+                let target = reader
+                    .varint()?
+                    .and_then(|target| u32::try_from(target).ok())
+                    .ok_or(invalid)?;
+                if opcode == 8 {
+                    Bytecode::JumpForward(target)
+                } else {
+                    Bytecode::JumpBackward(target)
+                }
+            }
+            10 => Bytecode::DebugPrint,
+            11 => Bytecode::Halt,
+            _ => return Err(BytecodeError::UnknownOpcode(opcode)),
+        };
+        program.push(instruction);
+    }
+    check_jump_targets(&program)?;
+    Ok(program)
+}
@@ -0,0 +1,256 @@
+//! Types of the tape cells. The classic tape holds bytes wrapping at 256,
+//! `--cell-size` picks wider cells for programs that expect them and
+//! `--saturate` bytes that stop at their limits.
+
+use core::fmt;
+use memchr::{memchr, memrchr};
+
+/// Arithmetic of one tape cell, `Interpreter<T>` runs programs on cells of type `T`
+pub trait CellArithmetic: Copy + PartialEq + Default + fmt::Display + fmt::Debug {
+    fn inc(self) -> Self;
+    fn dec(self) -> Self;
+    fn is_zero(self) -> bool;
+    /// The byte written by `.`
+    fn to_output(self) -> u8;
+    /// The cell read by `,`
+    fn from_input(b: u8) -> Self;
+
+    /// The cell after `delta` times `+`, or `-` when it is negative
+    fn add(self, delta: i8) -> Self {
+        let mut cell = self;
+        for _ in 0..delta.unsigned_abs() {
+            cell = if delta > 0 { cell.inc() } else { cell.dec() };
+        }
+        cell
+    }
+
+    /// What `[->+<]` leaves in this cell when `counter` is the cell it counts down
+    fn add_cell(self, counter: Self) -> Self {
+        let (mut cell, mut counter) = (self, counter);
+        while !counter.is_zero() {
+            counter = counter.dec();
+            cell = cell.inc();
+        }
+        cell
+    }
+
+    /// `add` when the cell stays in its range, None when it would wrap
+    fn checked_add(self, delta: i8) -> Option<Self> {
+        Some(self.add(delta))
+    }
+
+    /// `add_cell` when no step of the loop wraps a cell, None when one would
+    fn checked_add_cell(self, counter: Self) -> Option<Self> {
+        let (mut cell, mut counter) = (self, counter);
+        while !counter.is_zero() {
+            counter = counter.checked_add(-1)?;
+            cell = cell.checked_add(1)?;
+        }
+        Some(cell)
+    }
+
+    /// Index of the first zero cell
+    fn find_zero(cells: &[Self]) -> Option<usize> {
+        cells.iter().position(|cell| cell.is_zero())
+    }
+
+    /// Index of the last zero cell
+    fn rfind_zero(cells: &[Self]) -> Option<usize> {
+        cells.iter().rposition(|cell| cell.is_zero())
+    }
+}
+
+impl CellArithmetic for u8 {
+    fn inc(self) -> Self {
+        self.wrapping_add(1)
+    }
+
+    fn dec(self) -> Self {
+        self.wrapping_sub(1)
+    }
+
+    fn is_zero(self) -> bool {
+        self == 0
+    }
+
+    fn to_output(self) -> u8 {
+        self
+    }
+
+    fn from_input(b: u8) -> Self {
+        b
+    }
+
+    fn add(self, delta: i8) -> Self {
+        self.wrapping_add(delta as u8)
+    }
+
+    fn add_cell(self, counter: Self) -> Self {
+        self.wrapping_add(counter)
+    }
+
+    fn checked_add(self, delta: i8) -> Option<Self> {
+        if delta < 0 {
+            self.checked_sub(delta.unsigned_abs())
+        } else {
+            self.checked_add(delta as u8)
+        }
+    }
+
+    // the counter goes down to zero without wrapping, only this cell can
+    fn checked_add_cell(self, counter: Self) -> Option<Self> {
+        self.checked_add(counter)
+    }
+
+    // bytes are searched with memchr, much faster than a loop over the cells
+    fn find_zero(cells: &[Self]) -> Option<usize> {
+        memchr(0, cells)
+    }
+
+    fn rfind_zero(cells: &[Self]) -> Option<usize> {
+        memrchr(0, cells)
+    }
+}
+
+// Cells wider than a byte wrap at their own size, `.` writes their low byte
+macro_rules! wide_cell {
+    ($($cell:ty),*) => {
+        $(
+            impl CellArithmetic for $cell {
+                fn inc(self) -> Self {
+                    self.wrapping_add(1)
+                }
+
+                fn dec(self) -> Self {
+                    self.wrapping_sub(1)
+                }
+
+                fn is_zero(self) -> bool {
+                    self == 0
+                }
+
+                fn to_output(self) -> u8 {
+                    self as u8
+                }
+
+                fn from_input(b: u8) -> Self {
+                    b.into()
+                }
+
+                fn add(self, delta: i8) -> Self {
+                    self.wrapping_add(delta as Self)
+                }
+
+                fn add_cell(self, counter: Self) -> Self {
+                    self.wrapping_add(counter)
+                }
+
+                fn checked_add(self, delta: i8) -> Option<Self> {
+                    if delta < 0 {
+                        self.checked_sub(delta.unsigned_abs().into())
+                    } else {
+                        self.checked_add(delta as Self)
+                    }
+                }
+
+                fn checked_add_cell(self, counter: Self) -> Option<Self> {
+                    self.checked_add(counter)
+                }
+            }
+        )*
+    };
+}
+
+wide_cell!(u16, u32);
+
+// Signed cells wrap from 127 to -128 where bytes go from 127 to 128: the bits
+// are the same, so a program prints exactly what it prints on bytes and only
+// the values shown by `#` and the progress line differ
+impl CellArithmetic for i8 {
+    fn inc(self) -> Self {
+        self.wrapping_add(1)
+    }
+
+    fn dec(self) -> Self {
+        self.wrapping_sub(1)
+    }
+
+    fn is_zero(self) -> bool {
+        self == 0
+    }
+
+    fn to_output(self) -> u8 {
+        self as u8
+    }
+
+    fn from_input(b: u8) -> Self {
+        b as i8
+    }
+
+    fn add(self, delta: i8) -> Self {
+        self.wrapping_add(delta)
+    }
+
+    fn add_cell(self, counter: Self) -> Self {
+        self.wrapping_add(counter)
+    }
+
+    fn checked_add(self, delta: i8) -> Option<Self> {
+        self.checked_add(delta)
+    }
+}
+
+/// A byte stopping at 0 and 255 instead of wrapping, for `--saturate`. `[-]`
+/// still clears a cell but `[+]` never ends, and `-` on 0 no longer gives 255
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct SaturatingU8(pub u8);
+
+impl fmt::Display for SaturatingU8 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl CellArithmetic for SaturatingU8 {
+    fn inc(self) -> Self {
+        SaturatingU8(self.0.saturating_add(1))
+    }
+
+    fn dec(self) -> Self {
+        SaturatingU8(self.0.saturating_sub(1))
+    }
+
+    fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    fn to_output(self) -> u8 {
+        self.0
+    }
+
+    fn from_input(b: u8) -> Self {
+        SaturatingU8(b)
+    }
+
+    fn add(self, delta: i8) -> Self {
+        if delta < 0 {
+            SaturatingU8(self.0.saturating_sub(delta.unsigned_abs()))
+        } else {
+            SaturatingU8(self.0.saturating_add(delta as u8))
+        }
+    }
+
+    fn add_cell(self, counter: Self) -> Self {
+        SaturatingU8(self.0.saturating_add(counter.0))
+    }
+}
+
+/// Cell types `--cell-size` and `--saturate` can choose
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CellSize {
+    Bits8,
+    Signed8,
+    Saturating8,
+    Bits16,
+    Bits32,
+}
@@ -0,0 +1,75 @@
+//! C source backend, the emitted file only depends on the C standard library.
+
+use super::sourcemap::SourceMap;
+use crate::Command::*;
+use crate::Node;
+use crate::NodeType::*;
+use std::fmt::Write;
+
+const PROLOGUE: &str = "
+static unsigned char tape[TAPE_SIZE] = {0};
+
+/* move the pointer wrapping around the tape like the interpreter does */
+static inline unsigned char *move_pointer(unsigned char *p, long delta)
+{
+    long index = ((p - tape) + delta) % TAPE_SIZE;
+    return tape + (index < 0 ? index + TAPE_SIZE : index);
+}
+
+int main(void)
+{
+    unsigned char *p = tape;
+";
+
+const EPILOGUE: &str = "    return 0;
+}
+";
+
+/// Translate the program to a self-contained C source file
+pub fn emit_c(program: &Node, tape_size: usize) -> String {
+    emit_c_with_source_map(program, tape_size).0
+}
+
+/// `emit_c` with the source map of the statements
+pub fn emit_c_with_source_map(program: &Node, tape_size: usize) -> (String, SourceMap) {
+    let mut out = format!("#include <stdio.h>\n\n#define TAPE_SIZE {}L\n", tape_size);
+    out.push_str(PROLOGUE);
+    let mut map = SourceMap::default();
+    emit_nodes(&mut out, &mut map, program, 1);
+    out.push_str(EPILOGUE);
+    (out, map)
+}
+
+// Write C statements for all children of the node
+fn emit_nodes(out: &mut String, map: &mut SourceMap, node: &Node, depth: usize) {
+    for child in node.childrens.iter() {
+        let indent = "    ".repeat(depth);
+        if child.node_type == Loop {
+            map.mark(out, indent.len(), child.source_pos);
+            writeln!(out, "{}while (*p) {{", indent).unwrap();
+            emit_nodes(out, map, child, depth + 1);
+            writeln!(out, "{}}}", indent).unwrap();
+            continue;
+        }
+        let statement = match child.instruction {
+            IncDP => "p = move_pointer(p, 1);".to_string(),
+            DecDP => "p = move_pointer(p, -1);".to_string(),
+            MoveBy(delta) => format!("p = move_pointer(p, {});", delta),
+            IncByte => "++*p;".to_string(),
+            DecByte => "--*p;".to_string(),
+            AddByte(delta) => format!("*p += {};", delta),
+            SetZero => "*p = 0;".to_string(),
+            AddTo { offset } => {
+                format!("if (*p) {{ *move_pointer(p, {}) += *p; *p = 0; }}", offset)
+            }
+            ScanRight => "while (*p) p = move_pointer(p, 1);".to_string(),
+            ScanLeft => "while (*p) p = move_pointer(p, -1);".to_string(),
+            OutByte => "putchar(*p);".to_string(),
+            InByte => "{ int c = getchar(); *p = c == EOF ? 0 : c; }".to_string(),
+            Halt => "return 0;".to_string(),
+            _ => continue,
+        };
+        map.mark(out, indent.len(), child.source_pos);
+        writeln!(out, "{}{}", indent, statement).unwrap();
+    }
+}
@@ -0,0 +1,319 @@
+//! x86-64 Linux executable backend, the machine code is encoded here and put in
+//! an ELF file with the `object` crate, so no assembler or linker is needed. The
+//! code follows the NASM backend instruction for instruction.
+//!
+//! ```sh
+//! brainfuck_interpreter --file hello.b --emit=elf --output hello && ./hello
+//! ```
+
+use super::nasm::cleared_after;
+use crate::Command::*;
+use crate::Node;
+use crate::NodeType::*;
+use object::elf::{
+    ELFOSABI_NONE, EM_X86_64, ET_EXEC, PF_R, PF_W, PF_X, PT_LOAD, SHF_ALLOC, SHF_EXECINSTR,
+    SHF_WRITE, SHT_NOBITS, SHT_PROGBITS,
+};
+use object::write::elf::{FileHeader, ProgramHeader, SectionHeader, Writer};
+use object::Endianness;
+
+const BASE_ADDRESS: u64 = 0x40_0000;
+const PAGE_SIZE: u64 = 0x1000;
+
+// register numbers in the ModRM byte
+const RDX: u8 = 2;
+const RBX: u8 = 3;
+
+const CMP_CELL_ZERO: [u8; 3] = [0x80, 0x3b, 0x00]; // cmp byte [rbx], 0
+const JE: [u8; 2] = [0x0f, 0x84];
+const JNE: [u8; 2] = [0x0f, 0x85];
+const JMP: [u8; 1] = [0xe9];
+const CALL: [u8; 1] = [0xe8];
+
+// write the byte at the pointer to stdout
+const OUTPUT: [u8; 21] = [
+    0xb8, 0x01, 0x00, 0x00, 0x00, // mov eax, 1
+    0xbf, 0x01, 0x00, 0x00, 0x00, // mov edi, 1
+    0x48, 0x89, 0xde, // mov rsi, rbx
+    0xba, 0x01, 0x00, 0x00, 0x00, // mov edx, 1
+    0x0f, 0x05, // syscall
+    0xc3, // ret
+];
+
+// read a byte from stdin into the cell at the pointer, 0 at end of input
+const INPUT: [u8; 23] = [
+    0x31, 0xc0, // xor eax, eax
+    0x31, 0xff, // xor edi, edi
+    0x48, 0x89, 0xde, // mov rsi, rbx
+    0xba, 0x01, 0x00, 0x00, 0x00, // mov edx, 1
+    0x0f, 0x05, // syscall
+    0x48, 0x85, 0xc0, // test rax, rax
+    0x7f, 0x03, // jg over the next instruction
+    0xc6, 0x03, 0x00, // mov byte [rbx], 0
+    0xc3, // ret
+];
+
+const EXIT: [u8; 9] = [
+    0xb8, 0x3c, 0x00, 0x00, 0x00, // mov eax, 60
+    0x31, 0xff, // xor edi, edi
+    0x0f, 0x05, // syscall
+];
+
+/// Translate the program to an executable for x86-64 Linux, the tape lives in
+/// the `.bss` section
+pub fn emit_elf(program: &Node, tape_size: usize) -> Vec<u8> {
+    let mut assembler = Assembler::new(tape_size as i64);
+    assembler.emit_nodes(program);
+    let exit = assembler.exit;
+    assembler.jump_to(&JMP, exit);
+    let mut code = assembler.code;
+
+    let mut buffer = Vec::new();
+    let mut writer = Writer::new(Endianness::Little, true, &mut buffer);
+    writer.reserve_file_header();
+    writer.reserve_program_headers(2);
+    let text_offset = writer.reserve(code.len(), PAGE_SIZE as usize) as u64;
+    writer.reserve_null_section_index();
+    let text_name = writer.add_section_name(b".text");
+    writer.reserve_section_index();
+    let bss_name = writer.add_section_name(b".bss");
+    writer.reserve_section_index();
+    writer.reserve_shstrtab_section_index();
+    writer.reserve_shstrtab();
+    writer.reserve_section_headers();
+
+    let text_address = BASE_ADDRESS + text_offset;
+    let tape_address = (text_address + code.len() as u64 + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+    // the prologue loads the start and the end of the tape
+    code[2..10].copy_from_slice(&tape_address.to_le_bytes());
+    code[12..20].copy_from_slice(&(tape_address + tape_size as u64).to_le_bytes());
+
+    writer
+        .write_file_header(&FileHeader {
+            os_abi: ELFOSABI_NONE,
+            abi_version: 0,
+            e_type: ET_EXEC,
+            e_machine: EM_X86_64,
+            e_entry: text_address,
+            e_flags: 0,
+        })
+        .expect("an x86-64 ELF header is always valid");
+    writer.write_align_program_headers();
+    writer.write_program_header(&ProgramHeader {
+        p_type: PT_LOAD,
+        p_flags: PF_R | PF_X,
+        p_offset: text_offset,
+        p_vaddr: text_address,
+        p_paddr: text_address,
+        p_filesz: code.len() as u64,
+        p_memsz: code.len() as u64,
+        p_align: PAGE_SIZE,
+    });
+    writer.write_program_header(&ProgramHeader {
+        p_type: PT_LOAD,
+        p_flags: PF_R | PF_W,
+        p_offset: 0,
+        p_vaddr: tape_address,
+        p_paddr: tape_address,
+        p_filesz: 0,
+        p_memsz: tape_size as u64,
+        p_align: PAGE_SIZE,
+    });
+    writer.write_align(PAGE_SIZE as usize);
+    writer.write(&code);
+    writer.write_shstrtab();
+    writer.write_null_section_header();
+    writer.write_section_header(&SectionHeader {
+        name: Some(text_name),
+        sh_type: SHT_PROGBITS,
+        sh_flags: (SHF_ALLOC | SHF_EXECINSTR).into(),
+        sh_addr: text_address,
+        sh_offset: text_offset,
+        sh_size: code.len() as u64,
+        sh_link: 0,
+        sh_info: 0,
+        sh_addralign: 16,
+        sh_entsize: 0,
+    });
+    writer.write_section_header(&SectionHeader {
+        name: Some(bss_name),
+        sh_type: SHT_NOBITS,
+        sh_flags: (SHF_ALLOC | SHF_WRITE).into(),
+        sh_addr: tape_address,
+        sh_offset: text_offset + code.len() as u64,
+        sh_size: tape_size as u64,
+        sh_link: 0,
+        sh_info: 0,
+        sh_addralign: PAGE_SIZE,
+        sh_entsize: 0,
+    });
+    writer.write_shstrtab_section_header();
+    buffer
+}
+
+// Machine code being written, the routines for I/O and exit sit between the
+// prologue and the program
+struct Assembler {
+    code: Vec<u8>,
+    tape_size: i64,
+    output: usize,
+    input: usize,
+    exit: usize,
+}
+
+impl Assembler {
+    fn new(tape_size: i64) -> Self {
+        let mut assembler = Assembler {
+            code: Vec::new(),
+            tape_size,
+            output: 0,
+            input: 0,
+            exit: 0,
+        };
+        // the tape addresses are filled in once the length of the code is known
+        assembler.emit(&[0x49, 0xbc]); // mov r12, start of the tape
+        assembler.emit(&[0; 8]);
+        assembler.emit(&[0x49, 0xbd]); // mov r13, end of the tape
+        assembler.emit(&[0; 8]);
+        assembler.emit(&[0x4c, 0x89, 0xe3]); // mov rbx, r12
+        let start = assembler.jump(&JMP);
+        assembler.output = assembler.code.len();
+        assembler.emit(&OUTPUT);
+        assembler.input = assembler.code.len();
+        assembler.emit(&INPUT);
+        assembler.exit = assembler.code.len();
+        assembler.emit(&EXIT);
+        let here = assembler.code.len();
+        assembler.patch(start, here);
+        assembler
+    }
+
+    fn emit(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+
+    fn emit_i32(&mut self, value: i64) {
+        self.emit(&(value as i32).to_le_bytes());
+    }
+
+    // Write a jump with its target left to `patch`, returns where the offset goes
+    fn jump(&mut self, opcode: &[u8]) -> usize {
+        self.emit(opcode);
+        let at = self.code.len();
+        self.emit(&[0; 4]);
+        at
+    }
+
+    fn jump_to(&mut self, opcode: &[u8], target: usize) {
+        let at = self.jump(opcode);
+        self.patch(at, target);
+    }
+
+    fn patch(&mut self, at: usize, target: usize) {
+        let offset = target as i64 - (at as i64 + 4);
+        self.code[at..at + 4].copy_from_slice(&(offset as i32).to_le_bytes());
+    }
+
+    // Write the instructions of all children of the node
+    fn emit_nodes(&mut self, node: &Node) {
+        let mut children = 0;
+        while children < node.childrens.len() {
+            let child = &node.childrens[children];
+            children += 1;
+            if child.node_type == Loop {
+                self.emit(&CMP_CELL_ZERO);
+                let end = self.jump(&JE);
+                let body = self.code.len();
+                self.emit_nodes(child);
+                self.emit(&CMP_CELL_ZERO);
+                self.jump_to(&JNE, body);
+                let here = self.code.len();
+                self.patch(end, here);
+                continue;
+            }
+            match child.instruction {
+                IncDP => self.move_pointer(1),
+                DecDP => self.move_pointer(-1),
+                MoveBy(delta) => self.move_pointer(delta.into()),
+                IncByte => self.emit(&[0xfe, 0x03]), // inc byte [rbx]
+                DecByte => self.emit(&[0xfe, 0x0b]), // dec byte [rbx]
+                AddByte(delta) => self.emit(&[0x80, 0x03, delta as u8]), // add byte [rbx], delta
+                SetZero => {
+                    // `[-]>[-]>[-]` clears the cells in one go
+                    let cells = 1 + cleared_after(&node.childrens[children..]);
+                    if cells == 1 {
+                        self.emit(&[0xc6, 0x03, 0x00]); // mov byte [rbx], 0
+                    } else {
+                        self.clear_cells(cells);
+                        self.move_pointer(cells as i64 - 1);
+                        children += 2 * (cells - 1);
+                    }
+                }
+                AddTo { offset } => {
+                    self.emit(&[0x0f, 0xb6, 0x03]); // movzx eax, byte [rbx]
+                    self.wrapped_address(RDX, offset.into());
+                    self.emit(&[0x00, 0x02]); // add byte [rdx], al
+                    self.emit(&[0xc6, 0x03, 0x00]); // mov byte [rbx], 0
+                }
+                ScanRight | ScanLeft => {
+                    let scan = self.code.len();
+                    self.emit(&CMP_CELL_ZERO);
+                    let end = self.jump(&JE);
+                    self.move_pointer(if child.instruction == ScanRight {
+                        1
+                    } else {
+                        -1
+                    });
+                    self.jump_to(&JMP, scan);
+                    let here = self.code.len();
+                    self.patch(end, here);
+                }
+                OutByte => self.jump_to(&CALL, self.output),
+                InByte => self.jump_to(&CALL, self.input),
+                Halt => self.jump_to(&JMP, self.exit),
+                _ => {}
+            }
+        }
+    }
+
+    // Zero `cells` cells from the pointer with `rep stosb`, the ones past the end
+    // of the tape at its start
+    fn clear_cells(&mut self, cells: usize) {
+        self.emit(&[0x48, 0x89, 0xdf]); // mov rdi, rbx
+        self.emit(&[0x4c, 0x89, 0xe9]); // mov rcx, r13
+        self.emit(&[0x48, 0x29, 0xd9]); // sub rcx, rbx
+        self.emit(&[0xba]); // mov edx, cells
+        self.emit_i32(cells as i64);
+        self.emit(&[0x48, 0x39, 0xd1]); // cmp rcx, rdx
+        self.emit(&[0x48, 0x0f, 0x47, 0xca]); // cmova rcx, rdx
+        self.emit(&[0x48, 0x29, 0xca]); // sub rdx, rcx
+        self.emit(&[0x31, 0xc0]); // xor eax, eax
+        self.emit(&[0xf3, 0xaa]); // rep stosb
+        self.emit(&[0x4c, 0x89, 0xe7]); // mov rdi, r12
+        self.emit(&[0x48, 0x89, 0xd1]); // mov rcx, rdx
+        self.emit(&[0xf3, 0xaa]); // rep stosb
+    }
+
+    // Move the pointer wrapping around the tape like the interpreter does
+    fn move_pointer(&mut self, delta: i64) {
+        self.wrapped_address(RBX, delta);
+    }
+
+    // Set `register` to the address `delta` cells away from the pointer, wrapped
+    // around the tape
+    fn wrapped_address(&mut self, register: u8, delta: i64) {
+        let delta = delta.rem_euclid(self.tape_size);
+        if delta == 0 {
+            if register != RBX {
+                self.emit(&[0x48, 0x89, 0xd8 | register]); // mov register, rbx
+            }
+            return;
+        }
+        self.emit(&[0x48, 0x8d, 0x8b]); // lea rcx, [rbx + back]
+        self.emit_i32(delta - self.tape_size);
+        self.emit(&[0x48, 0x8d, 0x83 | register << 3]); // lea register, [rbx + delta]
+        self.emit_i32(delta);
+        self.emit(&[0x4c, 0x39, 0xe8 | register]); // cmp register, r13
+        self.emit(&[0x48, 0x0f, 0x43, 0xc1 | register << 3]); // cmovae register, rcx
+    }
+}
@@ -0,0 +1,207 @@
+//! LLVM IR backend emitting the textual `.ll` format, the module only depends on
+//! `putchar` and `getchar` from the C library.
+
+use crate::Command::*;
+use crate::Node;
+use crate::NodeType::*;
+use std::fmt::Write;
+
+// Wraps the moved pointer around the tape like the interpreter does
+const MOVE_POINTER: &str = "define internal ptr @move_pointer(ptr %tape, ptr %p, i64 %delta) {
+entry:
+  %base = ptrtoint ptr %tape to i64
+  %address = ptrtoint ptr %p to i64
+  %offset = sub i64 %address, %base
+  %moved = add i64 %offset, %delta
+  %remainder = srem i64 %moved, TAPE_SIZE
+  %negative = icmp slt i64 %remainder, 0
+  %wrapped = add i64 %remainder, TAPE_SIZE
+  %index = select i1 %negative, i64 %wrapped, i64 %remainder
+  %cell = getelementptr inbounds i8, ptr %tape, i64 %index
+  ret ptr %cell
+}
+";
+
+struct LlvmEmitter {
+    out: String,
+    temporaries: usize,
+    loops: usize,
+    halts: usize,
+}
+
+impl LlvmEmitter {
+    // Fresh SSA register name
+    fn temporary(&mut self) -> String {
+        self.temporaries += 1;
+        format!("%t{}", self.temporaries)
+    }
+
+    fn line(&mut self, instruction: &str) {
+        writeln!(self.out, "  {}", instruction).unwrap();
+    }
+
+    // Load the data pointer, returning the register holding it
+    fn load_pointer(&mut self) -> String {
+        let pointer = self.temporary();
+        self.line(&format!("{} = load ptr, ptr %p", pointer));
+        pointer
+    }
+
+    fn move_pointer(&mut self, delta: i32) {
+        let pointer = self.load_pointer();
+        let moved = self.temporary();
+        self.line(&format!(
+            "{} = call ptr @move_pointer(ptr %tape, ptr {}, i64 {})",
+            moved, pointer, delta
+        ));
+        self.line(&format!("store ptr {}, ptr %p", moved));
+    }
+
+    fn add_to_cell(&mut self, delta: i8) {
+        let pointer = self.load_pointer();
+        let value = self.temporary();
+        let sum = self.temporary();
+        self.line(&format!("{} = load i8, ptr {}", value, pointer));
+        self.line(&format!("{} = add i8 {}, {}", sum, value, delta));
+        self.line(&format!("store i8 {}, ptr {}", sum, pointer));
+    }
+
+    fn add_to(&mut self, offset: i32) {
+        let pointer = self.load_pointer();
+        let value = self.temporary();
+        let target = self.temporary();
+        let old = self.temporary();
+        let sum = self.temporary();
+        self.line(&format!("{} = load i8, ptr {}", value, pointer));
+        self.line(&format!(
+            "{} = call ptr @move_pointer(ptr %tape, ptr {}, i64 {})",
+            target, pointer, offset
+        ));
+        self.line(&format!("{} = load i8, ptr {}", old, target));
+        self.line(&format!("{} = add i8 {}, {}", sum, old, value));
+        self.line(&format!("store i8 {}, ptr {}", sum, target));
+        self.line(&format!("store i8 0, ptr {}", pointer));
+    }
+
+    fn output(&mut self) {
+        let pointer = self.load_pointer();
+        let value = self.temporary();
+        let extended = self.temporary();
+        let ignored = self.temporary();
+        self.line(&format!("{} = load i8, ptr {}", value, pointer));
+        self.line(&format!("{} = zext i8 {} to i32", extended, value));
+        self.line(&format!(
+            "{} = call i32 @putchar(i32 {})",
+            ignored, extended
+        ));
+    }
+
+    fn input(&mut self) {
+        let pointer = self.load_pointer();
+        let read = self.temporary();
+        let eof = self.temporary();
+        let byte = self.temporary();
+        let value = self.temporary();
+        self.line(&format!("{} = call i32 @getchar()", read));
+        self.line(&format!("{} = icmp eq i32 {}, -1", eof, read));
+        self.line(&format!("{} = trunc i32 {} to i8", byte, read));
+        self.line(&format!("{} = select i1 {}, i8 0, i8 {}", value, eof, byte));
+        self.line(&format!("store i8 {}, ptr {}", value, pointer));
+    }
+
+    // Loop header testing the current cell, the body is emitted by the caller
+    fn begin_loop(&mut self) -> usize {
+        self.loops += 1;
+        let id = self.loops;
+        self.line(&format!("br label %loop{}.cond", id));
+        writeln!(self.out, "loop{}.cond:", id).unwrap();
+        let pointer = self.load_pointer();
+        let value = self.temporary();
+        let nonzero = self.temporary();
+        self.line(&format!("{} = load i8, ptr {}", value, pointer));
+        self.line(&format!("{} = icmp ne i8 {}, 0", nonzero, value));
+        self.line(&format!(
+            "br i1 {}, label %loop{}.body, label %loop{}.end",
+            nonzero, id, id
+        ));
+        writeln!(self.out, "loop{}.body:", id).unwrap();
+        id
+    }
+
+    fn end_loop(&mut self, id: usize) {
+        self.line(&format!("br label %loop{}.cond", id));
+        writeln!(self.out, "loop{}.end:", id).unwrap();
+    }
+
+    fn emit_nodes(&mut self, node: &Node) {
+        for child in node.childrens.iter() {
+            if child.node_type == Loop {
+                let id = self.begin_loop();
+                self.emit_nodes(child);
+                self.end_loop(id);
+                continue;
+            }
+            match child.instruction {
+                IncDP => self.move_pointer(1),
+                DecDP => self.move_pointer(-1),
+                MoveBy(delta) => self.move_pointer(delta),
+                IncByte => self.add_to_cell(1),
+                DecByte => self.add_to_cell(-1),
+                AddByte(delta) => self.add_to_cell(delta),
+                SetZero => {
+                    let pointer = self.load_pointer();
+                    self.line(&format!("store i8 0, ptr {}", pointer));
+                }
+                AddTo { offset } => self.add_to(offset),
+                ScanRight | ScanLeft => {
+                    let id = self.begin_loop();
+                    self.move_pointer(if child.instruction == ScanRight {
+                        1
+                    } else {
+                        -1
+                    });
+                    self.end_loop(id);
+                }
+                OutByte => self.output(),
+                InByte => self.input(),
+                Halt => {
+                    // whatever follows the return goes in a block nothing jumps to
+                    self.halts += 1;
+                    self.line("ret i32 0");
+                    writeln!(self.out, "halt{}.after:", self.halts).unwrap();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Translate the program to an LLVM IR module with a `main` function
+pub fn emit_llvm_ir(program: &Node, tape_size: usize) -> String {
+    let mut emitter = LlvmEmitter {
+        out: String::new(),
+        temporaries: 0,
+        loops: 0,
+        halts: 0,
+    };
+    emitter.out.push_str("declare i32 @putchar(i32)\n");
+    emitter.out.push_str("declare i32 @getchar()\n");
+    emitter
+        .out
+        .push_str("declare void @llvm.memset.p0.i64(ptr, i8, i64, i1)\n\n");
+    emitter
+        .out
+        .push_str(&MOVE_POINTER.replace("TAPE_SIZE", &tape_size.to_string()));
+    emitter.out.push_str("\ndefine i32 @main() {\nentry:\n");
+    emitter.line(&format!("%tape = alloca [{} x i8]", tape_size));
+    emitter.line(&format!(
+        "call void @llvm.memset.p0.i64(ptr %tape, i8 0, i64 {}, i1 false)",
+        tape_size
+    ));
+    emitter.line("%p = alloca ptr");
+    emitter.line("store ptr %tape, ptr %p");
+    emitter.emit_nodes(program);
+    emitter.line("ret i32 0");
+    emitter.out.push_str("}\n");
+    emitter.out
+}
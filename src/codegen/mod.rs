@@ -0,0 +1,11 @@
+//! Backends translating the abstract syntactic tree to other languages.
+
+pub mod c;
+pub mod elf;
+pub mod llvm;
+pub mod nasm;
+pub mod qbe;
+pub mod rust;
+pub mod sourcemap;
+pub mod wasm;
+pub mod wat;
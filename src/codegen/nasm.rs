@@ -0,0 +1,200 @@
+//! x86-64 assembly backend in NASM syntax for Linux, the emitted file needs no
+//! library: input and output are `read` and `write` system calls.
+//!
+//! ```sh
+//! nasm -f elf64 out.asm && ld out.o -o out
+//! ```
+
+use crate::Command::*;
+use crate::Node;
+use crate::NodeType::*;
+use std::fmt::Write;
+
+const PROLOGUE: &str = "
+; rbx is the data pointer, r12 the first cell of the tape and r13 the end of it
+section .text
+global _start
+_start:
+    lea r12, [rel tape]
+    lea r13, [r12 + TAPE_SIZE]
+    mov rbx, r12
+";
+
+const EPILOGUE: &str = "exit:
+    mov eax, 60
+    xor edi, edi
+    syscall
+
+; write the byte at the pointer to stdout
+output:
+    mov eax, 1
+    mov edi, 1
+    mov rsi, rbx
+    mov edx, 1
+    syscall
+    ret
+
+; read a byte from stdin into the cell at the pointer, 0 at end of input
+input:
+    xor eax, eax
+    xor edi, edi
+    mov rsi, rbx
+    mov edx, 1
+    syscall
+    test rax, rax
+    jg .read
+    mov byte [rbx], 0
+.read:
+    ret
+";
+
+/// Translate the program to an assembly file for `nasm -f elf64`
+pub fn emit_nasm(program: &Node, tape_size: usize) -> String {
+    let mut out = format!("TAPE_SIZE equ {}\n", tape_size);
+    out.push_str(PROLOGUE);
+    emit_nodes(&mut out, program, tape_size as i64, &mut 0);
+    out.push_str("    jmp exit\n\n");
+    out.push_str(EPILOGUE);
+    write!(out, "\nsection .bss\ntape: resb TAPE_SIZE\n").unwrap();
+    out
+}
+
+// Write the instructions of all children of the node, `labels` numbers the jumps
+fn emit_nodes(out: &mut String, node: &Node, tape_size: i64, labels: &mut usize) {
+    let mut children = 0;
+    while children < node.childrens.len() {
+        let child = &node.childrens[children];
+        children += 1;
+        if child.node_type == Loop {
+            let label = next_label(labels);
+            writeln!(
+                out,
+                "    cmp byte [rbx], 0\n    je end{}\nloop{}:",
+                label, label
+            )
+            .unwrap();
+            emit_nodes(out, child, tape_size, labels);
+            writeln!(
+                out,
+                "    cmp byte [rbx], 0\n    jne loop{}\nend{}:",
+                label, label
+            )
+            .unwrap();
+            continue;
+        }
+        match child.instruction {
+            IncDP => move_pointer(out, 1, tape_size),
+            DecDP => move_pointer(out, -1, tape_size),
+            MoveBy(delta) => move_pointer(out, delta.into(), tape_size),
+            IncByte => out.push_str("    inc byte [rbx]\n"),
+            DecByte => out.push_str("    dec byte [rbx]\n"),
+            AddByte(delta) => writeln!(out, "    add byte [rbx], {}", delta).unwrap(),
+            SetZero => {
+                // `[-]>[-]>[-]` clears the cells in one go
+                let cells = 1 + cleared_after(&node.childrens[children..]);
+                if cells == 1 {
+                    out.push_str("    mov byte [rbx], 0\n");
+                } else {
+                    clear_cells(out, cells);
+                    move_pointer(out, cells as i64 - 1, tape_size);
+                    children += 2 * (cells - 1);
+                }
+            }
+            AddTo { offset } => {
+                out.push_str("    movzx eax, byte [rbx]\n");
+                wrapped_address(out, "rdx", offset.into(), tape_size);
+                out.push_str("    add byte [rdx], al\n    mov byte [rbx], 0\n");
+            }
+            ScanRight | ScanLeft => {
+                let label = next_label(labels);
+                writeln!(
+                    out,
+                    "scan{}:\n    cmp byte [rbx], 0\n    je end{}",
+                    label, label
+                )
+                .unwrap();
+                move_pointer(
+                    out,
+                    if child.instruction == ScanRight {
+                        1
+                    } else {
+                        -1
+                    },
+                    tape_size,
+                );
+                writeln!(out, "    jmp scan{}\nend{}:", label, label).unwrap();
+            }
+            OutByte => out.push_str("    call output\n"),
+            InByte => out.push_str("    call input\n"),
+            Halt => out.push_str("    jmp exit\n"),
+            _ => {}
+        }
+    }
+}
+
+fn next_label(labels: &mut usize) -> usize {
+    *labels += 1;
+    *labels
+}
+
+// Number of cells cleared right after a `[-]`, each one a step right and a `[-]`
+pub(super) fn cleared_after(rest: &[Node]) -> usize {
+    rest.chunks_exact(2)
+        .take_while(|pair| {
+            pair[0].node_type == Operator
+                && matches!(pair[0].instruction, IncDP | MoveBy(1))
+                && pair[1].node_type == Operator
+                && pair[1].instruction == SetZero
+        })
+        .count()
+}
+
+// Zero `cells` cells from the pointer with `rep stosb`, the ones past the end of
+// the tape at its start
+fn clear_cells(out: &mut String, cells: usize) {
+    writeln!(
+        out,
+        "    mov rdi, rbx
+    mov rcx, r13
+    sub rcx, rbx
+    mov edx, {}
+    cmp rcx, rdx
+    cmova rcx, rdx
+    sub rdx, rcx
+    xor eax, eax
+    rep stosb
+    mov rdi, r12
+    mov rcx, rdx
+    rep stosb",
+        cells
+    )
+    .unwrap();
+}
+
+// Move the pointer wrapping around the tape like the interpreter does
+fn move_pointer(out: &mut String, delta: i64, tape_size: i64) {
+    wrapped_address(out, "rbx", delta, tape_size);
+}
+
+// Set `register` to the address `delta` cells away from the pointer, wrapped
+// around the tape
+fn wrapped_address(out: &mut String, register: &str, delta: i64, tape_size: i64) {
+    let delta = delta.rem_euclid(tape_size);
+    if delta == 0 {
+        if register != "rbx" {
+            writeln!(out, "    mov {}, rbx", register).unwrap();
+        }
+        return;
+    }
+    writeln!(
+        out,
+        "    lea rcx, [rbx + {back}]
+    lea {register}, [rbx + {delta}]
+    cmp {register}, r13
+    cmovae {register}, rcx",
+        register = register,
+        delta = delta,
+        back = delta - tape_size
+    )
+    .unwrap();
+}
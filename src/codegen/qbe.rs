@@ -0,0 +1,183 @@
+//! QBE intermediate language backend, the `main` function only depends on
+//! `putchar` and `getchar` from the C library. QBE takes temporaries assigned
+//! more than once, so `%dp` holds the index of the current cell all along.
+//!
+//! ```sh
+//! qbe -o out.s out.ssa && cc out.s -o out
+//! ```
+
+use crate::Command::*;
+use crate::Node;
+use crate::NodeType::*;
+use std::fmt::Write;
+
+// Index of the cell delta cells away, wrapping around the tape like the
+// interpreter does
+const WRAP: &str = "function w $wrap(w %dp, w %delta) {
+@start
+    %index =w add %dp, %delta
+    %index =w rem %index, TAPE_SIZE
+    %negative =w csltw %index, 0
+    jnz %negative, @negative, @done
+@negative
+    %index =w add %index, TAPE_SIZE
+@done
+    ret %index
+}
+";
+
+struct QbeEmitter {
+    out: String,
+    temporaries: usize,
+    labels: usize,
+}
+
+impl QbeEmitter {
+    // Fresh temporary name
+    fn temporary(&mut self) -> String {
+        self.temporaries += 1;
+        format!("%t{}", self.temporaries)
+    }
+
+    fn line(&mut self, instruction: &str) {
+        writeln!(self.out, "    {}", instruction).unwrap();
+    }
+
+    fn label(&mut self, name: &str, id: usize) {
+        writeln!(self.out, "@{}{}", name, id).unwrap();
+    }
+
+    // Address of the cell with the index in `index`
+    fn cell_address(&mut self, index: &str) -> String {
+        let extended = self.temporary();
+        let address = self.temporary();
+        self.line(&format!("{} =l extsw {}", extended, index));
+        self.line(&format!("{} =l add $tape, {}", address, extended));
+        address
+    }
+
+    fn move_pointer(&mut self, delta: i32) {
+        self.line(&format!("%dp =w call $wrap(w %dp, w {})", delta));
+    }
+
+    fn add_to_cell(&mut self, delta: i8) {
+        let address = self.cell_address("%dp");
+        let value = self.temporary();
+        self.line(&format!("{} =w loadub {}", value, address));
+        self.line(&format!("{} =w add {}, {}", value, value, delta));
+        self.line(&format!("storeb {}, {}", value, address));
+    }
+
+    fn add_to(&mut self, offset: i32) {
+        let address = self.cell_address("%dp");
+        let value = self.temporary();
+        let index = self.temporary();
+        self.line(&format!("{} =w loadub {}", value, address));
+        self.line(&format!("{} =w call $wrap(w %dp, w {})", index, offset));
+        let target = self.cell_address(&index);
+        let old = self.temporary();
+        self.line(&format!("{} =w loadub {}", old, target));
+        self.line(&format!("{} =w add {}, {}", old, old, value));
+        self.line(&format!("storeb {}, {}", old, target));
+        self.line(&format!("storeb 0, {}", address));
+    }
+
+    fn output(&mut self) {
+        let address = self.cell_address("%dp");
+        let value = self.temporary();
+        self.line(&format!("{} =w loadub {}", value, address));
+        self.line(&format!("call $putchar(w {})", value));
+    }
+
+    fn input(&mut self) {
+        let read = self.temporary();
+        let kept = self.temporary();
+        self.line(&format!("{} =w call $getchar()", read));
+        // end of input stores 0 like the interpreter does
+        self.line(&format!("{} =w cnew {}, -1", kept, read));
+        self.line(&format!("{} =w mul {}, {}", read, read, kept));
+        let address = self.cell_address("%dp");
+        self.line(&format!("storeb {}, {}", read, address));
+    }
+
+    // Loop header testing the current cell, the body is emitted by the caller
+    fn begin_loop(&mut self) -> usize {
+        self.labels += 1;
+        let id = self.labels;
+        self.label("loop", id);
+        let address = self.cell_address("%dp");
+        let value = self.temporary();
+        self.line(&format!("{} =w loadub {}", value, address));
+        self.line(&format!("jnz {}, @body{}, @end{}", value, id, id));
+        self.label("body", id);
+        id
+    }
+
+    fn end_loop(&mut self, id: usize) {
+        self.line(&format!("jmp @loop{}", id));
+        self.label("end", id);
+    }
+
+    fn emit_nodes(&mut self, node: &Node) {
+        for child in node.childrens.iter() {
+            if child.node_type == Loop {
+                let id = self.begin_loop();
+                self.emit_nodes(child);
+                self.end_loop(id);
+                continue;
+            }
+            match child.instruction {
+                IncDP => self.move_pointer(1),
+                DecDP => self.move_pointer(-1),
+                MoveBy(delta) => self.move_pointer(delta),
+                IncByte => self.add_to_cell(1),
+                DecByte => self.add_to_cell(-1),
+                AddByte(delta) => self.add_to_cell(delta),
+                SetZero => {
+                    let address = self.cell_address("%dp");
+                    self.line(&format!("storeb 0, {}", address));
+                }
+                AddTo { offset } => self.add_to(offset),
+                ScanRight | ScanLeft => {
+                    let id = self.begin_loop();
+                    self.move_pointer(if child.instruction == ScanRight {
+                        1
+                    } else {
+                        -1
+                    });
+                    self.end_loop(id);
+                }
+                OutByte => self.output(),
+                InByte => self.input(),
+                Halt => {
+                    // whatever follows the return goes in a block nothing jumps to
+                    self.labels += 1;
+                    self.line("ret 0");
+                    self.label("halt", self.labels);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Translate the program to a QBE module with a `main` function
+pub fn emit_qbe(program: &Node, tape_size: usize) -> String {
+    let mut emitter = QbeEmitter {
+        out: String::new(),
+        temporaries: 0,
+        labels: 0,
+    };
+    writeln!(emitter.out, "data $tape = align 1 {{ z {} }}\n", tape_size).unwrap();
+    emitter
+        .out
+        .push_str(&WRAP.replace("TAPE_SIZE", &tape_size.to_string()));
+    emitter
+        .out
+        .push_str("\nexport function w $main() {\n@start\n");
+    emitter.line("%dp =w copy 0");
+    emitter.emit_nodes(program);
+    emitter.line("ret 0");
+    emitter.out.push_str("}\n");
+    emitter.out
+}
@@ -0,0 +1,176 @@
+//! Rust source backend, the emitted file only depends on the standard library.
+
+use super::sourcemap::SourceMap;
+use crate::Command::*;
+use crate::Node;
+use crate::NodeType::*;
+use std::fmt::Write;
+
+const MOVE_POINTER: &str = "
+// move the pointer wrapping around the tape like the interpreter does
+fn move_pointer(p: usize, delta: isize) -> usize {
+    (p as isize + delta).rem_euclid(TAPE_SIZE as isize) as usize
+}
+";
+
+// Generated statements plus what the program needs declared
+#[derive(Default)]
+struct RustEmitter {
+    body: String,
+    moves: bool,
+    reads: bool,
+    writes: bool,
+    input: bool,
+    output: bool,
+    map: SourceMap, // lines counted from the start of `body`
+}
+
+impl RustEmitter {
+    fn line(&mut self, depth: usize, statement: &str) {
+        writeln!(self.body, "{}{}", "    ".repeat(depth), statement).unwrap();
+    }
+
+    fn move_pointer(&mut self, depth: usize, delta: i32) {
+        self.moves = true;
+        self.line(depth, &format!("p = move_pointer(p, {});", delta));
+    }
+
+    fn add(&mut self, depth: usize, delta: i8) {
+        self.writes = true;
+        let method = if delta < 0 {
+            "wrapping_sub"
+        } else {
+            "wrapping_add"
+        };
+        self.line(
+            depth,
+            &format!(
+                "tape[p] = tape[p].{}({});",
+                method,
+                (delta as i16).unsigned_abs()
+            ),
+        );
+    }
+
+    fn emit_nodes(&mut self, node: &Node, depth: usize) {
+        for child in node.childrens.iter() {
+            self.map.mark(&self.body, depth * 4, child.source_pos);
+            if child.node_type == Loop {
+                self.reads = true;
+                self.line(depth, "while tape[p] != 0 {");
+                self.emit_nodes(child, depth + 1);
+                self.line(depth, "}");
+                continue;
+            }
+            match child.instruction {
+                IncDP => self.move_pointer(depth, 1),
+                DecDP => self.move_pointer(depth, -1),
+                MoveBy(delta) => self.move_pointer(depth, delta),
+                IncByte => self.add(depth, 1),
+                DecByte => self.add(depth, -1),
+                AddByte(delta) => self.add(depth, delta),
+                SetZero => {
+                    self.writes = true;
+                    self.line(depth, "tape[p] = 0;");
+                }
+                AddTo { offset } => {
+                    self.moves = true;
+                    self.reads = true;
+                    self.writes = true;
+                    self.line(depth, "if tape[p] != 0 {");
+                    self.line(
+                        depth + 1,
+                        &format!("let target = move_pointer(p, {});", offset),
+                    );
+                    self.line(
+                        depth + 1,
+                        "tape[target] = tape[target].wrapping_add(tape[p]);",
+                    );
+                    self.line(depth + 1, "tape[p] = 0;");
+                    self.line(depth, "}");
+                }
+                ScanRight | ScanLeft => {
+                    self.reads = true;
+                    self.line(depth, "while tape[p] != 0 {");
+                    self.move_pointer(
+                        depth + 1,
+                        if child.instruction == ScanRight {
+                            1
+                        } else {
+                            -1
+                        },
+                    );
+                    self.line(depth, "}");
+                }
+                OutByte => {
+                    self.output = true;
+                    self.reads = true;
+                    self.line(depth, "output.write_all(&[tape[p]]).unwrap();");
+                }
+                InByte => {
+                    // end of input stores 0 like the interpreter does
+                    self.input = true;
+                    self.writes = true;
+                    self.line(depth, "let mut byte = [0u8; 1];");
+                    self.line(depth, "tape[p] = match input.read_exact(&mut byte) {");
+                    self.line(depth + 1, "Ok(()) => byte[0],");
+                    self.line(depth + 1, "Err(_) => 0,");
+                    self.line(depth, "};");
+                }
+                Halt => self.line(depth, "return;"),
+                // nothing is emitted, so nothing is mapped
+                _ => {
+                    self.map.mappings.pop();
+                }
+            }
+        }
+    }
+}
+
+/// Translate the program to a Rust source file with a `main` function
+pub fn emit_rust(program: &Node, tape_size: usize) -> String {
+    emit_rust_with_source_map(program, tape_size).0
+}
+
+/// `emit_rust` with the source map of the statements
+pub fn emit_rust_with_source_map(program: &Node, tape_size: usize) -> (String, SourceMap) {
+    let mut emitter = RustEmitter::default();
+    emitter.emit_nodes(program, 1);
+    if emitter.body.is_empty() {
+        return ("fn main() {}\n".to_string(), SourceMap::default());
+    }
+
+    let mut out = String::new();
+    match (emitter.input, emitter.output) {
+        (true, true) => out.push_str("use std::io::{Read, Write};\n\n"),
+        (true, false) => out.push_str("use std::io::Read;\n\n"),
+        (false, true) => out.push_str("use std::io::Write;\n\n"),
+        (false, false) => {}
+    }
+    writeln!(out, "const TAPE_SIZE: usize = {};", tape_size).unwrap();
+    if emitter.moves {
+        out.push_str(MOVE_POINTER);
+    }
+    out.push_str("\nfn main() {\n");
+    let mutable = |used: bool| if used { "let mut" } else { "let" };
+    writeln!(
+        out,
+        "    {} tape = [0u8; TAPE_SIZE];",
+        mutable(emitter.writes)
+    )
+    .unwrap();
+    writeln!(out, "    {} p: usize = 0;", mutable(emitter.moves)).unwrap();
+    if emitter.input {
+        out.push_str("    let mut input = std::io::stdin();\n");
+    }
+    if emitter.output {
+        out.push_str("    let mut output = std::io::stdout();\n");
+    }
+    emitter.map.shift(out.lines().count());
+    out.push_str(&emitter.body);
+    if !emitter.reads {
+        out.push_str("    // the program never reads the tape\n    let _ = (tape, p);\n");
+    }
+    out.push_str("}\n");
+    (out, emitter.map)
+}
@@ -0,0 +1,148 @@
+//! Source maps of the C and Rust backends in the Source Map v3 format, pointing
+//! every emitted statement back at the BF command it came from.
+
+use crate::line_col;
+use std::fmt::Write;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// A statement of the emitted code, line and column start at 0, with the byte
+/// offset of its command in the source
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Mapping {
+    pub generated_line: usize,
+    pub generated_column: usize,
+    pub offset: usize,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct SourceMap {
+    pub mappings: Vec<Mapping>,
+    // bytes of the output already counted into `line`
+    counted: usize,
+    line: usize,
+}
+
+impl SourceMap {
+    /// Map the statement about to be written at `column` of the next line of `out`
+    /// to the command at `offset`
+    pub fn mark(&mut self, out: &str, column: usize, offset: usize) {
+        self.line += out.as_bytes()[self.counted..]
+            .iter()
+            .filter(|&&byte| byte == b'\n')
+            .count();
+        self.counted = out.len();
+        self.mappings.push(Mapping {
+            generated_line: self.line,
+            generated_column: column,
+            offset,
+        });
+    }
+
+    /// Move every mapping `lines` down, for code written in front of the output
+    pub fn shift(&mut self, lines: usize) {
+        for mapping in self.mappings.iter_mut() {
+            mapping.generated_line += lines;
+        }
+    }
+
+    /// The map as JSON for the output `file`. The offsets point in the texts of
+    /// `sources` joined by newlines, the way `--file` joins the files
+    pub fn to_json(&self, file: &str, sources: &[(&str, &str)]) -> String {
+        // first offset of every source in the joined text
+        let mut starts = Vec::with_capacity(sources.len());
+        let mut start = 0;
+        for (_, text) in sources.iter() {
+            starts.push(start);
+            start += text.len() + 1;
+        }
+        let mut mappings = String::new();
+        let (mut line, mut column, mut previous) = (0, 0, (0, 0, 0));
+        for mapping in self.mappings.iter() {
+            if mapping.generated_line != line {
+                mappings.push_str(&";".repeat(mapping.generated_line - line));
+                line = mapping.generated_line;
+                column = 0;
+            } else if !mappings.is_empty() && !mappings.ends_with(';') {
+                mappings.push(',');
+            }
+            let source = starts
+                .iter()
+                .rposition(|&start| start <= mapping.offset)
+                .unwrap_or(0);
+            let text = sources.get(source).map_or("", |(_, text)| text);
+            let offset = (mapping.offset - starts.get(source).unwrap_or(&0)).min(text.len());
+            let (source_line, source_column) = line_col(text, offset);
+            let current = (source, source_line - 1, source_column - 1);
+            for delta in [
+                mapping.generated_column as i64 - column as i64,
+                current.0 as i64 - previous.0 as i64,
+                current.1 as i64 - previous.1 as i64,
+                current.2 as i64 - previous.2 as i64,
+            ] {
+                encode_vlq(delta, &mut mappings);
+            }
+            column = mapping.generated_column;
+            previous = current;
+        }
+
+        let strings = |strings: Vec<&str>| {
+            strings
+                .into_iter()
+                .map(json_string)
+                .collect::<Vec<String>>()
+                .join(",")
+        };
+        let mut json = String::new();
+        write!(
+            json,
+            "{{\"version\":3,\"file\":{},\"sources\":[{}],\"sourcesContent\":[{}],\"names\":[],\"mappings\":{}}}",
+            json_string(file),
+            strings(sources.iter().map(|(name, _)| *name).collect()),
+            strings(sources.iter().map(|(_, text)| *text).collect()),
+            json_string(&mappings)
+        )
+        .unwrap();
+        json
+    }
+}
+
+/// Append the base64 VLQ of `value`: 5 bits per digit, least significant first,
+/// with the sign in the lowest bit of the first one
+pub fn encode_vlq(value: i64, out: &mut String) {
+    let mut rest = if value < 0 {
+        ((-value as u64) << 1) | 1
+    } else {
+        (value as u64) << 1
+    };
+    loop {
+        let mut digit = (rest & 0b11111) as usize;
+        rest >>= 5;
+        if rest != 0 {
+            digit |= 0b100000;
+        }
+        out.push(BASE64[digit] as char);
+        if rest == 0 {
+            break;
+        }
+    }
+}
+
+// The text as a JSON string with its quotes
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
@@ -0,0 +1,304 @@
+//! WebAssembly binary backend, encoding with `wasm-encoder` the same module the
+//! WAT backend writes as text: the memory layout and the helper functions are
+//! those described in `wat`.
+
+use super::wat::{PAGE_SIZE, TAPE_START};
+use crate::Command::*;
+use crate::Node;
+use crate::NodeType::*;
+use wasm_encoder::{
+    BlockType, CodeSection, ConstExpr, EntityType, ExportKind, ExportSection, Function,
+    FunctionSection, GlobalSection, GlobalType, ImportSection, MemArg, MemorySection, MemoryType,
+    Module, TypeSection, ValType,
+};
+
+// function types
+const FD_TYPE: u32 = 0; // (i32 i32 i32 i32) -> i32
+const WRAP_TYPE: u32 = 1; // (i32) -> i32
+const DELTA_TYPE: u32 = 2; // (i32) -> ()
+const VOID_TYPE: u32 = 3; // () -> ()
+
+// functions, the imports first
+const FD_WRITE: u32 = 0;
+const FD_READ: u32 = 1;
+const WRAP: u32 = 2;
+const MOVE: u32 = 3;
+const ADD_TO: u32 = 4;
+const OUTPUT: u32 = 5;
+const INPUT: u32 = 6;
+const START: u32 = 7;
+
+const DP: u32 = 0; // the global holding the index of the current cell
+
+const CELL: MemArg = MemArg {
+    offset: TAPE_START as u64,
+    align: 0,
+    memory_index: 0,
+};
+const BYTE: MemArg = MemArg {
+    offset: 0,
+    align: 0,
+    memory_index: 0,
+};
+const WORD: MemArg = MemArg {
+    offset: 0,
+    align: 2,
+    memory_index: 0,
+};
+
+/// Translate the program to a binary WASM module exporting the WASI `_start`
+/// function
+pub fn emit_wasm(program: &Node, tape_size: usize) -> Vec<u8> {
+    let tape_size = tape_size as i32;
+    let mut types = TypeSection::new();
+    types.ty().function([ValType::I32; 4], [ValType::I32]);
+    types.ty().function([ValType::I32], [ValType::I32]);
+    types.ty().function([ValType::I32], []);
+    types.ty().function([], []);
+
+    let mut imports = ImportSection::new();
+    for name in ["fd_write", "fd_read"].iter() {
+        imports.import(
+            "wasi_snapshot_preview1",
+            name,
+            EntityType::Function(FD_TYPE),
+        );
+    }
+
+    let mut functions = FunctionSection::new();
+    functions
+        .function(WRAP_TYPE)
+        .function(DELTA_TYPE)
+        .function(DELTA_TYPE)
+        .function(VOID_TYPE)
+        .function(VOID_TYPE)
+        .function(VOID_TYPE);
+
+    let mut memories = MemorySection::new();
+    memories.memory(MemoryType {
+        minimum: (TAPE_START + tape_size as usize).div_ceil(PAGE_SIZE) as u64,
+        maximum: None,
+        memory64: false,
+        shared: false,
+        page_size_log2: None,
+    });
+
+    let mut globals = GlobalSection::new();
+    globals.global(
+        GlobalType {
+            val_type: ValType::I32,
+            mutable: true,
+            shared: false,
+        },
+        &ConstExpr::i32_const(0),
+    );
+
+    let mut exports = ExportSection::new();
+    exports.export("memory", ExportKind::Memory, 0);
+    exports.export("_start", ExportKind::Func, START);
+
+    let mut code = CodeSection::new();
+    code.function(&wrap(tape_size));
+    code.function(&move_pointer());
+    code.function(&add_to());
+    code.function(&output());
+    code.function(&input());
+    let mut start = Function::new([]);
+    emit_nodes(&mut start, program);
+    start.instructions().end();
+    code.function(&start);
+
+    let mut module = Module::new();
+    module
+        .section(&types)
+        .section(&imports)
+        .section(&functions)
+        .section(&memories)
+        .section(&globals)
+        .section(&exports)
+        .section(&code);
+    module.finish()
+}
+
+// Index of the cell `delta` cells away, wrapping around the tape like the
+// interpreter does
+fn wrap(tape_size: i32) -> Function {
+    let mut function = Function::new([]);
+    function
+        .instructions()
+        .global_get(DP)
+        .local_get(0)
+        .i32_add()
+        .i32_const(tape_size)
+        .i32_rem_s()
+        .i32_const(tape_size)
+        .i32_add()
+        .i32_const(tape_size)
+        .i32_rem_s()
+        .end();
+    function
+}
+
+fn move_pointer() -> Function {
+    let mut function = Function::new([]);
+    function
+        .instructions()
+        .local_get(0)
+        .call(WRAP)
+        .global_set(DP)
+        .end();
+    function
+}
+
+fn add_to() -> Function {
+    let mut function = Function::new([(1, ValType::I32)]);
+    function
+        .instructions()
+        .local_get(0)
+        .call(WRAP)
+        .local_tee(1)
+        .local_get(1)
+        .i32_load8_u(CELL)
+        .global_get(DP)
+        .i32_load8_u(CELL)
+        .i32_add()
+        .i32_store8(CELL)
+        .global_get(DP)
+        .i32_const(0)
+        .i32_store8(CELL)
+        .end();
+    function
+}
+
+// Point the I/O vector at 0 to the single byte at 8
+fn set_io_vector(function: &mut Function) {
+    function
+        .instructions()
+        .i32_const(0)
+        .i32_const(8)
+        .i32_store(WORD)
+        .i32_const(4)
+        .i32_const(1)
+        .i32_store(WORD);
+}
+
+fn output() -> Function {
+    let mut function = Function::new([]);
+    set_io_vector(&mut function);
+    function
+        .instructions()
+        .i32_const(8)
+        .global_get(DP)
+        .i32_load8_u(CELL)
+        .i32_store8(BYTE)
+        .i32_const(1)
+        .i32_const(0)
+        .i32_const(1)
+        .i32_const(12)
+        .call(FD_WRITE)
+        .drop()
+        .end();
+    function
+}
+
+// End of input stores 0 like the interpreter does
+fn input() -> Function {
+    let mut function = Function::new([]);
+    set_io_vector(&mut function);
+    function
+        .instructions()
+        .i32_const(12)
+        .i32_const(0)
+        .i32_store(WORD)
+        .i32_const(0)
+        .i32_const(0)
+        .i32_const(1)
+        .i32_const(12)
+        .call(FD_READ)
+        .drop()
+        .global_get(DP)
+        .i32_const(8)
+        .i32_load8_u(BYTE)
+        .i32_const(0)
+        .i32_const(12)
+        .i32_load(WORD)
+        .select()
+        .i32_store8(CELL)
+        .end();
+    function
+}
+
+// Add `delta` to the current cell
+fn add_to_cell(function: &mut Function, delta: i32) {
+    function
+        .instructions()
+        .global_get(DP)
+        .global_get(DP)
+        .i32_load8_u(CELL)
+        .i32_const(delta)
+        .i32_add()
+        .i32_store8(CELL);
+}
+
+// Write the instructions for all children of the node
+fn emit_nodes(function: &mut Function, node: &Node) {
+    for child in node.childrens.iter() {
+        let scan = match child.instruction {
+            ScanRight => Some(1),
+            ScanLeft => Some(-1),
+            _ => None,
+        };
+        if child.node_type == Loop || scan.is_some() {
+            function
+                .instructions()
+                .block(BlockType::Empty)
+                .loop_(BlockType::Empty)
+                .global_get(DP)
+                .i32_load8_u(CELL)
+                .i32_eqz()
+                .br_if(1);
+            match scan {
+                Some(delta) => {
+                    function.instructions().i32_const(delta).call(MOVE);
+                }
+                None => emit_nodes(function, child),
+            }
+            function.instructions().br(0).end().end();
+            continue;
+        }
+        match child.instruction {
+            IncDP => {
+                function.instructions().i32_const(1).call(MOVE);
+            }
+            DecDP => {
+                function.instructions().i32_const(-1).call(MOVE);
+            }
+            MoveBy(delta) => {
+                function.instructions().i32_const(delta).call(MOVE);
+            }
+            IncByte => add_to_cell(function, 1),
+            DecByte => add_to_cell(function, -1),
+            AddByte(delta) => add_to_cell(function, delta.into()),
+            SetZero => {
+                function
+                    .instructions()
+                    .global_get(DP)
+                    .i32_const(0)
+                    .i32_store8(CELL);
+            }
+            AddTo { offset } => {
+                function.instructions().i32_const(offset).call(ADD_TO);
+            }
+            OutByte => {
+                function.instructions().call(OUTPUT);
+            }
+            InByte => {
+                function.instructions().call(INPUT);
+            }
+            Halt => {
+                function.instructions().return_();
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! WebAssembly text format backend, the module runs on any WASI runtime.
+//!
+//! Memory starts with a scratch area used for the WASI calls: the I/O vector at 0,
+//! the byte being read or written at 8 and the transferred count at 12. The tape
+//! follows at `TAPE_START` and `$dp` holds the index of the current cell.
+
+use crate::Command::*;
+use crate::Node;
+use crate::NodeType::*;
+use std::fmt::Write;
+
+pub(super) const TAPE_START: usize = 16;
+pub(super) const PAGE_SIZE: usize = 65536;
+
+const PRELUDE: &str = r#"  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") PAGES)
+  (global $dp (mut i32) (i32.const 0))
+
+  ;; index of the cell delta cells away, wrapping around the tape like the interpreter does
+  (func $wrap (param $delta i32) (result i32)
+    (i32.rem_s
+      (i32.add
+        (i32.rem_s (i32.add (global.get $dp) (local.get $delta)) (i32.const TAPE_SIZE))
+        (i32.const TAPE_SIZE))
+      (i32.const TAPE_SIZE)))
+
+  (func $move (param $delta i32)
+    (global.set $dp (call $wrap (local.get $delta))))
+
+  (func $add_to (param $offset i32)
+    (local $target i32)
+    (local.set $target (call $wrap (local.get $offset)))
+    (i32.store8 offset=16 (local.get $target)
+      (i32.add
+        (i32.load8_u offset=16 (local.get $target))
+        (i32.load8_u offset=16 (global.get $dp))))
+    (i32.store8 offset=16 (global.get $dp) (i32.const 0)))
+
+  (func $output
+    (i32.store (i32.const 0) (i32.const 8))
+    (i32.store (i32.const 4) (i32.const 1))
+    (i32.store8 (i32.const 8) (i32.load8_u offset=16 (global.get $dp)))
+    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 12))))
+
+  ;; end of input stores 0 like the interpreter does
+  (func $input
+    (i32.store (i32.const 0) (i32.const 8))
+    (i32.store (i32.const 4) (i32.const 1))
+    (i32.store (i32.const 12) (i32.const 0))
+    (drop (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 12)))
+    (i32.store8 offset=16 (global.get $dp)
+      (select
+        (i32.load8_u (i32.const 8))
+        (i32.const 0)
+        (i32.load (i32.const 12)))))
+"#;
+
+const CELL: &str = "(i32.load8_u offset=16 (global.get $dp))";
+
+// Write WAT instructions for all children of the node
+fn emit_nodes(out: &mut String, node: &Node, depth: usize) {
+    for child in node.childrens.iter() {
+        let indent = "  ".repeat(depth);
+        let scan = match child.instruction {
+            ScanRight => Some(1),
+            ScanLeft => Some(-1),
+            _ => None,
+        };
+        if child.node_type == Loop || scan.is_some() {
+            writeln!(out, "{}(block", indent).unwrap();
+            writeln!(out, "{}  (loop", indent).unwrap();
+            writeln!(out, "{}    (br_if 1 (i32.eqz {}))", indent, CELL).unwrap();
+            match scan {
+                Some(delta) => {
+                    writeln!(out, "{}    (call $move (i32.const {}))", indent, delta).unwrap()
+                }
+                None => emit_nodes(out, child, depth + 2),
+            }
+            writeln!(out, "{}    (br 0)))", indent).unwrap();
+            continue;
+        }
+        let instruction = match child.instruction {
+            IncDP => "(call $move (i32.const 1))".to_string(),
+            DecDP => "(call $move (i32.const -1))".to_string(),
+            MoveBy(delta) => format!("(call $move (i32.const {}))", delta),
+            IncByte | DecByte | AddByte(_) => {
+                let delta = match child.instruction {
+                    IncByte => 1,
+                    DecByte => -1,
+                    AddByte(delta) => delta,
+                    _ => 0,
+                };
+                format!(
+                    "(i32.store8 offset=16 (global.get $dp) (i32.add {} (i32.const {})))",
+                    CELL, delta
+                )
+            }
+            SetZero => "(i32.store8 offset=16 (global.get $dp) (i32.const 0))".to_string(),
+            AddTo { offset } => format!("(call $add_to (i32.const {}))", offset),
+            OutByte => "(call $output)".to_string(),
+            InByte => "(call $input)".to_string(),
+            Halt => "(return)".to_string(),
+            _ => continue,
+        };
+        writeln!(out, "{}{}", indent, instruction).unwrap();
+    }
+}
+
+/// Translate the program to a WAT module exporting the WASI `_start` function
+pub fn emit_wat(program: &Node, tape_size: usize) -> String {
+    let pages = (TAPE_START + tape_size).div_ceil(PAGE_SIZE);
+    let mut out = String::from("(module\n");
+    out.push_str(
+        &PRELUDE
+            .replace("TAPE_SIZE", &tape_size.to_string())
+            .replace("PAGES", &pages.to_string()),
+    );
+    out.push_str("\n  (func (export \"_start\")\n");
+    emit_nodes(&mut out, program, 2);
+    out.push_str("  )\n)\n");
+    out
+}
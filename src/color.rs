@@ -0,0 +1,57 @@
+//! ANSI colors for diagnostics and tape dumps, plain text when they are disabled.
+
+use std::io::Write;
+use termcolor::{Buffer, Color, ColorSpec, WriteColor};
+
+/// When to color, `Auto` only colors a terminal
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ColorChoice {
+    Always,
+    Auto,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to color a stream, `is_terminal` tells if the stream is a terminal
+    pub fn enabled(self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Auto => is_terminal,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// Bold red, for errors
+pub fn error_style() -> ColorSpec {
+    let mut style = ColorSpec::new();
+    style.set_fg(Some(Color::Red)).set_bold(true);
+    style
+}
+
+/// Yellow, for warnings
+pub fn warning_style() -> ColorSpec {
+    let mut style = ColorSpec::new();
+    style.set_fg(Some(Color::Yellow));
+    style
+}
+
+/// Green, for the cell under the pointer in tape dumps
+pub fn pointer_style() -> ColorSpec {
+    let mut style = ColorSpec::new();
+    style.set_fg(Some(Color::Green));
+    style
+}
+
+/// The text wrapped in the escape codes of the style, unchanged when `enabled` is false
+pub fn paint(text: &str, style: &ColorSpec, enabled: bool) -> String {
+    if !enabled {
+        return text.to_string();
+    }
+    // writing to memory cannot fail
+    let mut buffer = Buffer::ansi();
+    buffer.set_color(style).unwrap();
+    buffer.write_all(text.as_bytes()).unwrap();
+    buffer.reset().unwrap();
+    String::from_utf8(buffer.into_inner()).unwrap()
+}
@@ -0,0 +1,397 @@
+//! Interactive step debugger driven by single letter commands from the
+//! controlling terminal, so the program itself can still read stdin.
+
+use crate::color::{paint, pointer_style};
+use crate::NodeType::*;
+use crate::{
+    check_interrupt, count_step, execute_instruction, memory_index, wrap_pointer, Command,
+    Interpreter, InterpreterError, Node,
+};
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+};
+
+// Cells shown on each side of the pointer by the `t` command
+const TAPE_CONTEXT: usize = 8;
+
+#[derive(Debug)]
+pub enum DebugError {
+    Interpreter {
+        error: InterpreterError,
+        offset: usize,
+    }, // offset of the failing command
+    Io(io::Error),
+    Quit {
+        step: u64,
+    },
+}
+
+impl fmt::Display for DebugError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DebugError::Interpreter { error, offset } => {
+                write!(f, "error at byte offset {}: {}", offset, error)
+            }
+            DebugError::Io(e) => write!(f, "debugger terminal: {}", e),
+            DebugError::Quit { step } => write!(f, "debugger quit at step {}", step),
+        }
+    }
+}
+
+impl std::error::Error for DebugError {}
+
+impl From<io::Error> for DebugError {
+    fn from(e: io::Error) -> Self {
+        DebugError::Io(e)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DebugMode {
+    Step,     // pause after every instruction
+    Continue, // run until the next breakpoint
+}
+
+/// One change the program made, recorded with `--reversible`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StateChange {
+    PointerMoved(i32),                              // by this many cells
+    CellChanged { index: usize, old: u8, new: u8 }, // index in memory
+    OutputByte(u8),                                 // already written, undoing it only warns
+}
+
+/// The changes of a run, newest last, for the `u` command
+#[derive(Debug, Default)]
+pub struct Journal {
+    pub changes: VecDeque<StateChange>,
+    pub limit: Option<usize>, // the oldest changes are forgotten past this many
+}
+
+impl Journal {
+    pub fn new(limit: Option<usize>) -> Self {
+        Journal {
+            changes: VecDeque::new(),
+            limit,
+        }
+    }
+
+    fn push(&mut self, change: StateChange) {
+        if self.limit == Some(self.changes.len()) {
+            self.changes.pop_front();
+        }
+        if self.limit != Some(0) {
+            self.changes.push_back(change);
+        }
+    }
+
+    // Record what `cmd` changed, given the pointer, the tape offset and the
+    // current cell before it ran
+    fn record(
+        &mut self,
+        interpreter: &Interpreter,
+        cmd: &Command,
+        pointer: i32,
+        offset: i64,
+        index: usize,
+        old: u8,
+    ) {
+        // cells added on the left of the tape move every cell already recorded
+        let shift = (offset - interpreter.offset) as usize;
+        if shift > 0 {
+            for change in self.changes.iter_mut() {
+                if let StateChange::CellChanged { index, .. } = change {
+                    *index += shift;
+                }
+            }
+        }
+        let index = index + shift;
+        if interpreter.pointer != pointer {
+            self.push(StateChange::PointerMoved(interpreter.pointer - pointer));
+        }
+        if let Command::AddTo { offset } = *cmd {
+            if old != 0 {
+                let target = wrap_pointer(interpreter, pointer + offset);
+                let new = interpreter.memory[target];
+                self.push(StateChange::CellChanged {
+                    index: target,
+                    old: new.wrapping_sub(old),
+                    new,
+                });
+            }
+        }
+        let new = interpreter.memory[index];
+        if new != old {
+            self.push(StateChange::CellChanged { index, old, new });
+        }
+        if *cmd == Command::OutByte {
+            self.push(StateChange::OutputByte(new));
+        }
+    }
+
+    /// Undo the last `count` changes, returns the output bytes among them
+    /// that could not be taken back
+    pub fn undo(&mut self, interpreter: &mut Interpreter, count: usize) -> Vec<u8> {
+        let mut written = Vec::new();
+        for _ in 0..count {
+            match self.changes.pop_back() {
+                Some(StateChange::PointerMoved(delta)) => interpreter.pointer -= delta,
+                Some(StateChange::CellChanged { index, old, .. }) => {
+                    interpreter.memory[index] = old
+                }
+                Some(StateChange::OutputByte(byte)) => written.push(byte),
+                None => break,
+            }
+        }
+        written
+    }
+}
+
+pub struct Debugger<R: BufRead, W: Write> {
+    pub input: R,
+    pub output: W,
+    pub step: u64,
+    pub mode: DebugMode,
+    pub breakpoints: Vec<usize>,
+    pub watchpoints: HashSet<usize>,
+    pub journal: Option<Journal>, // None unless the run is reversible
+}
+
+impl<R: BufRead, W: Write> Debugger<R, W> {
+    pub fn new(input: R, output: W, breakpoints: &[usize]) -> Self {
+        Debugger {
+            input,
+            output,
+            step: 0,
+            mode: DebugMode::Step,
+            breakpoints: breakpoints.to_vec(),
+            watchpoints: HashSet::new(),
+            journal: None,
+        }
+    }
+
+    /// Same as `run_program`, stopping after operators for debugger commands
+    /// and before nodes whose source offset is a breakpoint
+    pub fn run(&mut self, interpreter: &mut Interpreter, ast: &Node) -> Result<(), DebugError> {
+        for node in ast.childrens.iter() {
+            if self.mode == DebugMode::Continue && self.breakpoints.contains(&node.source_pos) {
+                self.breakpoint(interpreter, node)?;
+            }
+            let mut index = memory_index(interpreter);
+            match node.node_type {
+                Loop => {
+                    while interpreter.memory[index] != 0 {
+                        self.run(interpreter, node)?;
+                        index = memory_index(interpreter);
+                        if interpreter.memory[index] != 0 {
+                            count_step(interpreter)
+                                .and_then(|_| check_interrupt(interpreter))
+                                .map_err(|error| DebugError::Interpreter {
+                                    error,
+                                    offset: node.source_pos,
+                                })?;
+                        }
+                    }
+                }
+                Operator => {
+                    let watched: Vec<(usize, Option<u8>)> = self
+                        .watchpoints
+                        .iter()
+                        .map(|cell| (*cell, cell_value(interpreter, *cell)))
+                        .collect();
+                    let (pointer, offset) = (interpreter.pointer, interpreter.offset);
+                    let old = interpreter.memory[index];
+                    execute_instruction(interpreter, &node.instruction, index).map_err(
+                        |error| DebugError::Interpreter {
+                            error,
+                            offset: node.source_pos,
+                        },
+                    )?;
+                    self.step += 1;
+                    if let Some(journal) = self.journal.as_mut() {
+                        journal.record(interpreter, &node.instruction, pointer, offset, index, old);
+                    }
+                    let changed = self.report_watchpoints(interpreter, &watched)?;
+                    if self.mode == DebugMode::Step || changed {
+                        self.print_step(interpreter, &node.instruction)?;
+                        self.prompt(interpreter)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn print_step(&mut self, interpreter: &Interpreter, cmd: &Command) -> Result<(), DebugError> {
+        let index = memory_index(interpreter);
+        let cell = format!(
+            "cell[{}]={}",
+            interpreter.cell_address(index),
+            interpreter.memory[index]
+        );
+        writeln!(
+            self.output,
+            "[step={}] cmd={:?} pointer={} {}",
+            self.step,
+            cmd,
+            interpreter.pointer,
+            paint(&cell, &pointer_style(), interpreter.color)
+        )?;
+        Ok(())
+    }
+
+    // Print watched cells changed by the last instruction, true when there were any
+    fn report_watchpoints(
+        &mut self,
+        interpreter: &Interpreter,
+        watched: &[(usize, Option<u8>)],
+    ) -> Result<bool, DebugError> {
+        let mut changed = false;
+        for (cell, old) in watched {
+            let new = cell_value(interpreter, *cell);
+            if let (Some(old), Some(new)) = (old, new) {
+                if *old != new {
+                    writeln!(
+                        self.output,
+                        "[watchpoint] cell[{}]: {} → {}",
+                        cell, old, new
+                    )?;
+                    changed = true;
+                }
+            }
+        }
+        Ok(changed)
+    }
+
+    fn breakpoint(&mut self, interpreter: &mut Interpreter, node: &Node) -> Result<(), DebugError> {
+        writeln!(
+            self.output,
+            "[breakpoint] offset {} before {:?}",
+            node.source_pos, node.instruction
+        )?;
+        self.print_step(interpreter, &node.instruction)?;
+        self.prompt(interpreter)
+    }
+
+    // Read commands until one of them resumes the program
+    fn prompt(&mut self, interpreter: &mut Interpreter) -> Result<(), DebugError> {
+        let mut line = String::new();
+        interpreter.output.flush()?;
+        loop {
+            write!(self.output, "(bfdb) ")?;
+            self.output.flush()?;
+            line.clear();
+            if self.input.read_line(&mut line)? == 0 {
+                // terminal closed, run the rest of the program undisturbed
+                self.mode = DebugMode::Continue;
+                self.breakpoints.clear();
+                return Ok(());
+            }
+            match line.trim().chars().next() {
+                Some('s') | None => {
+                    self.mode = DebugMode::Step;
+                    return Ok(());
+                }
+                Some('c') => {
+                    self.mode = DebugMode::Continue;
+                    return Ok(());
+                }
+                Some('q') => return Err(DebugError::Quit { step: self.step }),
+                Some('t') => writeln!(self.output, "{}", tape_window(interpreter))?,
+                Some('u') => self.undo(interpreter, line.trim()[1..].trim())?,
+                Some(_) => writeln!(
+                    self.output,
+                    "commands: s step, c continue, t show tape, u [N] undo N changes, q quit"
+                )?,
+            }
+        }
+    }
+
+    // `u N` puts back the pointer and the cells as they were N changes ago,
+    // the program goes on from the command it stopped at
+    fn undo(&mut self, interpreter: &mut Interpreter, count: &str) -> Result<(), DebugError> {
+        let journal = match self.journal.as_mut() {
+            Some(journal) => journal,
+            None => {
+                writeln!(self.output, "undo needs --reversible")?;
+                return Ok(());
+            }
+        };
+        let count = match count {
+            "" => 1,
+            count => match count.parse::<usize>() {
+                Ok(count) => count,
+                Err(_) => {
+                    writeln!(self.output, "invalid count '{}'", count)?;
+                    return Ok(());
+                }
+            },
+        };
+        let count = count.min(journal.changes.len());
+        for byte in journal.undo(interpreter, count) {
+            writeln!(
+                self.output,
+                "[undo] warning: output byte {} was already written and stays",
+                byte
+            )?;
+        }
+        writeln!(self.output, "[undo] {} changes undone", count)?;
+        writeln!(self.output, "{}", tape_window(interpreter))?;
+        Ok(())
+    }
+}
+
+// Value of the cell at an address, if the tape holds it
+fn cell_value(interpreter: &Interpreter, address: usize) -> Option<u8> {
+    let index = address as i64 - interpreter.offset;
+    if index < 0 {
+        return None;
+    }
+    interpreter.memory.get(index as usize).copied()
+}
+
+/// Cells around the pointer, the current one in brackets
+pub fn tape_window(interpreter: &Interpreter) -> String {
+    let index = memory_index(interpreter);
+    let start = index.saturating_sub(TAPE_CONTEXT);
+    let end = (index + TAPE_CONTEXT + 1).min(interpreter.tape_size);
+    (start..end)
+        .map(|cell| {
+            if cell == index {
+                let current = format!(
+                    "[{}]={}",
+                    interpreter.cell_address(cell),
+                    interpreter.memory[cell]
+                );
+                paint(&current, &pointer_style(), interpreter.color)
+            } else {
+                format!(
+                    "{}={}",
+                    interpreter.cell_address(cell),
+                    interpreter.memory[cell]
+                )
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Debug the program with commands read from the controlling terminal,
+/// pausing at the given source offsets and changes of watched cells during
+/// `continue`, changes are recorded for `u` into the journal if there is one
+pub fn debug_run_program(
+    interpreter: &mut Interpreter,
+    ast: &Node,
+    breakpoints: &[usize],
+    watchpoints: &HashSet<usize>,
+    journal: Option<Journal>,
+) -> Result<(), DebugError> {
+    let terminal: File = OpenOptions::new().read(true).open("/dev/tty")?;
+    let mut debugger = Debugger::new(BufReader::new(terminal), io::stderr(), breakpoints);
+    debugger.watchpoints = watchpoints.clone();
+    debugger.journal = journal;
+    debugger.run(interpreter, ast)
+}
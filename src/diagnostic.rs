@@ -0,0 +1,107 @@
+//! Errors and warnings shown with the line of source they point at, in the
+//! style of the Rust compiler:
+//!
+//! ```text
+//! error: unmatched ']'
+//!  --> line 2, column 2
+//!   |
+//! 2 | +]
+//!   |  ^
+//! ```
+
+use crate::{line_col, InterpreterError, SyntaxError};
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+use core::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    pub message: String,
+    pub offset: usize, // byte offset in the source the caret points at
+    pub severity: Severity,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>, offset: usize) -> Self {
+        Diagnostic {
+            message: message.into(),
+            offset,
+            severity: Severity::Error,
+        }
+    }
+}
+
+// Byte offset of a line and column starting at 1, the inverse of `line_col`
+fn line_col_offset(source: &str, line: usize, col: usize) -> usize {
+    let start: usize = source
+        .split_inclusive('\n')
+        .take(line - 1)
+        .map(str::len)
+        .sum();
+    start + col - 1
+}
+
+/// The unmatched bracket or brace pointed at in `source`
+pub fn syntax_diagnostic(source: &str, error: &SyntaxError) -> Diagnostic {
+    let (bracket, line, col) = match *error {
+        SyntaxError::UnmatchedOpen { line, col } => ('[', line, col),
+        SyntaxError::UnmatchedClose { line, col } => (']', line, col),
+        SyntaxError::UnmatchedOpenBrace { line, col } => ('{', line, col),
+        SyntaxError::UnmatchedCloseBrace { line, col } => ('}', line, col),
+    };
+    Diagnostic::error(
+        format!("unmatched '{}'", bracket),
+        line_col_offset(source, line, col),
+    )
+}
+
+/// The command a run failed at, None for errors that are not about one command
+pub fn interpreter_diagnostic(error: &InterpreterError) -> Option<Diagnostic> {
+    match *error {
+        InterpreterError::LoopIterationLimitExceeded { offset }
+        | InterpreterError::UndefinedSubroutine { offset }
+        | InterpreterError::CallStackOverflow { offset } => {
+            Some(Diagnostic::error(error.to_string(), offset))
+        }
+        _ => None,
+    }
+}
+
+/// The message, the position and the source line with a caret under the column
+pub fn render_diagnostic(source: &str, diag: &Diagnostic) -> String {
+    let (line, col) = line_col(source, diag.offset.min(source.len()));
+    let text = source
+        .split('\n')
+        .nth(line - 1)
+        .unwrap_or_default()
+        .trim_end_matches('\r');
+    // tabs stay tabs so the caret lines up with the column above it
+    let indent: String = text
+        .get(..col - 1)
+        .unwrap_or(text)
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let gutter = " ".repeat(line.to_string().len());
+    format!(
+        "{}: {}\n{}--> line {}, column {}\n{} |\n{} | {}\n{} | {}^\n",
+        diag.severity, diag.message, gutter, line, col, gutter, line, text, gutter, indent
+    )
+}
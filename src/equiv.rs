@@ -0,0 +1,92 @@
+//! Checking that two programs print the same bytes for a set of sample inputs.
+//! Passing every input does not prove them equivalent, it only finds where
+//! they are not.
+
+use crate::{
+    interpreter_init, run_program, CallbackOutput, EofBehavior, InputSource, InterpreterError, Node,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Inputs tried when no others are given
+pub const DEFAULT_INPUTS: [&[u8]; 4] = [b"", b"\x00", b"\xff", b"hello\n"];
+
+/// Steps each program may run per input when no limit is given
+pub const EQUIV_STEPS: u64 = 1_000_000;
+
+/// What a program printed for one input, with the error that stopped it
+#[derive(Debug, PartialEq)]
+pub struct Outcome {
+    pub output: Vec<u8>,
+    pub error: Option<InterpreterError>, // None when it ran to the end or ran '@'
+}
+
+/// The first input the programs print different bytes for
+#[derive(Debug, PartialEq)]
+pub struct Divergence {
+    pub input: Vec<u8>,
+    pub first: Outcome,
+    pub second: Outcome,
+}
+
+/// Run the program on `input` with a fresh tape, keeping what it prints
+pub fn run_captured(
+    ast: &Node,
+    input: &[u8],
+    tape_size: usize,
+    eof_behavior: EofBehavior,
+    max_steps: u64,
+) -> Outcome {
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let captured = Rc::clone(&output);
+    let mut interpreter = interpreter_init(tape_size);
+    interpreter.eof_behavior = eof_behavior;
+    interpreter.max_steps = Some(max_steps);
+    interpreter.input = InputSource::Buffer(input.to_vec(), 0);
+    interpreter.output = Box::new(CallbackOutput(Box::new(move |byte| {
+        captured.borrow_mut().push(byte)
+    })));
+    let error = match run_program(&mut interpreter, ast) {
+        Ok(()) | Err(InterpreterError::HaltInstruction) => None,
+        Err(error) => Some(error),
+    };
+    drop(interpreter);
+    let output = output.take();
+    Outcome { output, error }
+}
+
+/// Run both programs on every input and compare their output byte for byte, an
+/// error only counts through the output it cut short
+pub fn check_equivalence(
+    first: &Node,
+    second: &Node,
+    inputs: &[Vec<u8>],
+    tape_size: usize,
+    eof_behavior: EofBehavior,
+    max_steps: u64,
+) -> Option<Divergence> {
+    inputs.iter().find_map(|input| {
+        let first = run_captured(first, input, tape_size, eof_behavior, max_steps);
+        let second = run_captured(second, input, tape_size, eof_behavior, max_steps);
+        if first.output == second.output {
+            None
+        } else {
+            Some(Divergence {
+                input: input.clone(),
+                first,
+                second,
+            })
+        }
+    })
+}
+
+/// The bytes as a quoted string with `\n`, `\xNN` and the like for the bytes that
+/// do not print
+pub fn escape_bytes(bytes: &[u8]) -> String {
+    let escaped: String = bytes
+        .iter()
+        .flat_map(|&byte| std::ascii::escape_default(byte))
+        .map(char::from)
+        .collect();
+    format!("\"{}\"", escaped)
+}
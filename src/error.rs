@@ -0,0 +1,88 @@
+//! One error type for everything from reading the source to running it, so a
+//! caller can use `?` on each step.
+
+use crate::diagnostic::{interpreter_diagnostic, syntax_diagnostic, Diagnostic};
+#[cfg(not(feature = "no_std"))]
+use crate::vm::JumpTableError;
+use crate::{InterpreterError, SyntaxError};
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+#[cfg(not(feature = "no_std"))]
+use std::io;
+
+#[derive(thiserror::Error, Debug)]
+pub enum BrainfuckError {
+    // every unmatched bracket, one per line
+    #[error("{}", lines(.0))]
+    Syntax(Vec<SyntaxError>),
+    // brackets of commands without a source
+    #[cfg(not(feature = "no_std"))]
+    #[error(transparent)]
+    JumpTable(#[from] JumpTableError),
+    #[cfg(not(feature = "no_std"))]
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+    #[error("step limit of {steps} exceeded")]
+    StepLimitExceeded { steps: u64 },
+    #[error("execution timed out")]
+    Timeout,
+    #[error("execution interrupted by user")]
+    Interrupted,
+    #[error("program halted by '@'")]
+    HaltInstruction,
+    // the errors of a run without a variant of their own
+    #[error(transparent)]
+    Interpreter(InterpreterError),
+    #[error("no program to run")]
+    NoProgram,
+}
+
+impl BrainfuckError {
+    /// The errors pointing at a command of `source`, empty for the other errors
+    pub fn diagnostics(&self, source: &str) -> Vec<Diagnostic> {
+        match self {
+            BrainfuckError::Syntax(errors) => errors
+                .iter()
+                .map(|error| syntax_diagnostic(source, error))
+                .collect(),
+            BrainfuckError::Interpreter(error) => {
+                interpreter_diagnostic(error).into_iter().collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn lines(errors: &[SyntaxError]) -> String {
+    let lines: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+    lines.join("\n")
+}
+
+impl From<Vec<SyntaxError>> for BrainfuckError {
+    fn from(errors: Vec<SyntaxError>) -> Self {
+        BrainfuckError::Syntax(errors)
+    }
+}
+
+impl From<SyntaxError> for BrainfuckError {
+    fn from(error: SyntaxError) -> Self {
+        BrainfuckError::Syntax(vec![error])
+    }
+}
+
+impl From<InterpreterError> for BrainfuckError {
+    fn from(error: InterpreterError) -> Self {
+        match error {
+            InterpreterError::StepLimitExceeded { steps } => {
+                BrainfuckError::StepLimitExceeded { steps }
+            }
+            InterpreterError::Timeout => BrainfuckError::Timeout,
+            InterpreterError::Interrupted => BrainfuckError::Interrupted,
+            InterpreterError::HaltInstruction => BrainfuckError::HaltInstruction,
+            error => BrainfuckError::Interpreter(error),
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! C interface of the interpreter, built into the cdylib. `build.rs` writes the
+//! declarations to `brainfuck.h`. A panic never unwinds into C, the functions
+//! catch it and return null or `BF_PANIC`.
+//!
+//! An interpreter is created with `bf_interpreter_create`, runs any number of
+//! programs with `bf_run_source` on the same tape and is freed with
+//! `bf_interpreter_destroy`. Programs read stdin and write stdout.
+
+use crate::{
+    interpreter_init, lexical_analysis_str, run_program, syntax_analysis, Interpreter,
+    InterpreterError,
+};
+use std::io::Write;
+use std::os::raw::{c_char, c_int};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// The program ran to the end, or halted with '@'
+pub const BF_OK: c_int = 0;
+/// The source was null or not UTF-8
+pub const BF_INVALID_SOURCE: c_int = 1;
+/// The source has an unmatched bracket
+pub const BF_SYNTAX_ERROR: c_int = 2;
+/// The program failed while running, the pointer left the tape for example
+pub const BF_RUNTIME_ERROR: c_int = 3;
+/// The interpreter panicked, its tape may be left in any state
+pub const BF_PANIC: c_int = 4;
+
+/// A new interpreter with `tape_size` zero cells, freed with `bf_interpreter_destroy`.
+/// Null for a `tape_size` of 0
+#[no_mangle]
+pub extern "C" fn bf_interpreter_create(tape_size: usize) -> *mut Interpreter {
+    if tape_size == 0 {
+        return std::ptr::null_mut();
+    }
+    catch_unwind(|| Box::into_raw(Box::new(interpreter_init(tape_size))))
+        .unwrap_or(std::ptr::null_mut())
+}
+
+/// Run the `len` bytes of source at `source` on the interpreter, returns one of
+/// the `BF_` codes
+///
+/// # Safety
+///
+/// `interp` comes from `bf_interpreter_create` and was not destroyed, `source`
+/// points to `len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn bf_run_source(
+    interp: *mut Interpreter,
+    source: *const c_char,
+    len: usize,
+) -> c_int {
+    if interp.is_null() || source.is_null() {
+        return BF_INVALID_SOURCE;
+    }
+    let interpreter = &mut *interp;
+    let bytes = std::slice::from_raw_parts(source as *const u8, len);
+    catch_unwind(AssertUnwindSafe(|| run_bytes(interpreter, bytes))).unwrap_or(BF_PANIC)
+}
+
+// `bf_run_source` once the pointers were checked
+fn run_bytes(interpreter: &mut Interpreter, bytes: &[u8]) -> c_int {
+    let source = match std::str::from_utf8(bytes) {
+        Ok(source) => source,
+        Err(_) => return BF_INVALID_SOURCE,
+    };
+    let ast = match syntax_analysis(lexical_analysis_str(source), source) {
+        Ok(ast) => ast,
+        Err(_) => return BF_SYNTAX_ERROR,
+    };
+    let result = run_program(interpreter, &ast);
+    // a C program exiting never flushes the stdout of Rust
+    let flushed = interpreter.output.flush();
+    match (result, flushed) {
+        (Ok(()), Ok(())) | (Err(InterpreterError::HaltInstruction), Ok(())) => BF_OK,
+        _ => BF_RUNTIME_ERROR,
+    }
+}
+
+/// Value of the cell at `index` in memory, 0 past the end of the tape
+///
+/// # Safety
+///
+/// `interp` comes from `bf_interpreter_create` and was not destroyed
+#[no_mangle]
+pub unsafe extern "C" fn bf_get_cell(interp: *const Interpreter, index: usize) -> u8 {
+    match interp.as_ref() {
+        Some(interpreter) => interpreter.memory.get(index).copied().unwrap_or(0),
+        None => 0,
+    }
+}
+
+/// The data pointer, cells left of the start of a bidirectional tape are negative
+///
+/// # Safety
+///
+/// `interp` comes from `bf_interpreter_create` and was not destroyed
+#[no_mangle]
+pub unsafe extern "C" fn bf_get_pointer(interp: *const Interpreter) -> i32 {
+    match interp.as_ref() {
+        Some(interpreter) => interpreter.pointer,
+        None => 0,
+    }
+}
+
+/// Free an interpreter, a null pointer is ignored
+///
+/// # Safety
+///
+/// `interp` comes from `bf_interpreter_create` and is not used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn bf_interpreter_destroy(interp: *mut Interpreter) {
+    if !interp.is_null() {
+        let interpreter = Box::from_raw(interp);
+        // the output flushed when dropped may panic
+        let _ = catch_unwind(AssertUnwindSafe(|| drop(interpreter)));
+    }
+}
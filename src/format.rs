@@ -0,0 +1,84 @@
+//! Source formatter: the program printed back one block per line, comments dropped.
+//!
+//! ```text
+//! ++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]
+//! ```
+//!
+//! is too long for a line of 40 characters and becomes
+//!
+//! ```text
+//! ++++++++
+//! [
+//!     >++++[>++>+++>+++>+<<<<-]>+>+>->>+
+//!     [<]<-
+//! ]
+//! ```
+
+use crate::Command::DefineSubroutine;
+use crate::Node;
+use crate::NodeType::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Formatter {
+    pub indent: String, // written once per level of nesting
+    pub max_line_width: usize,
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Formatter {
+            indent: "    ".to_string(),
+            max_line_width: 80,
+        }
+    }
+}
+
+// The brackets around the body of a loop or a subroutine
+fn brackets(node: &Node) -> Option<(&'static str, &'static str)> {
+    match (node.node_type, node.instruction) {
+        (Loop, _) => Some(("[", "]")),
+        (Operator, DefineSubroutine) => Some(("{", "}")),
+        _ => None,
+    }
+}
+
+impl Formatter {
+    /// The formatted source of `ast`, every line ends with a newline
+    pub fn format(&self, ast: &Node) -> String {
+        let mut lines = Vec::new();
+        self.format_block(&ast.childrens, 0, &mut lines);
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    // Commands are put on the current line while it fits, a block too long
+    // for a line of its own gets its brackets on separate lines
+    fn format_block(&self, nodes: &[Node], depth: usize, lines: &mut Vec<String>) {
+        let indent = self.indent.repeat(depth);
+        let width = self.max_line_width.saturating_sub(indent.len());
+        let mut line = String::new();
+        for node in nodes.iter() {
+            let text = node.to_string();
+            match brackets(node).filter(|_| text.len() > width) {
+                Some((open, close)) => {
+                    if !line.is_empty() {
+                        lines.push(format!("{}{}", indent, line));
+                        line.clear();
+                    }
+                    lines.push(format!("{}{}", indent, open));
+                    self.format_block(&node.childrens, depth + 1, lines);
+                    lines.push(format!("{}{}", indent, close));
+                }
+                None => {
+                    if !line.is_empty() && line.len() + text.len() > width {
+                        lines.push(format!("{}{}", indent, line));
+                        line.clear();
+                    }
+                    line.push_str(&text);
+                }
+            }
+        }
+        if !line.is_empty() {
+            lines.push(format!("{}{}", indent, line));
+        }
+    }
+}
@@ -0,0 +1,450 @@
+//! Native code for the bytecode compiled with Cranelift when the program runs.
+//! The tape is a pointer passed to the compiled function, the data pointer a
+//! Cranelift variable and every loop a pair of blocks. `,` and `.` call back into
+//! Rust, so input and output go through the interpreter as usual, and so does a
+//! check for Ctrl+C and the timeout every `POLL_INTERVAL` loop iterations.
+
+use crate::bytecode::Bytecode;
+use crate::{
+    memory_index, EofBehavior, InputSource, Interpreter, InterpreterError, OutputFormatter,
+};
+use cranelift_codegen::ir::{
+    condcodes::IntCC, types, AbiParam, Block, FuncRef, InstBuilder, MemFlagsData, UserFuncName,
+    Value,
+};
+use cranelift_codegen::isa::TargetFrontendConfig;
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, FuncId, Linkage, Module};
+use std::{
+    fmt,
+    io::Write,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+/// Loop iterations between two checks of `should_stop` and the deadline
+pub const POLL_INTERVAL: i64 = 1 << 16;
+
+/// What `,` reads and `.` writes while compiled code runs
+pub struct JitIo<'a> {
+    pub input: &'a mut InputSource,
+    pub output: &'a mut dyn Write,
+    pub eof_behavior: EofBehavior,
+    pub output_format: OutputFormatter,
+    pub should_stop: &'a AtomicBool,
+    pub deadline: Option<Instant>,
+    pub error: Option<InterpreterError>, // the failed write, interrupt or timeout that stopped the program
+}
+
+// The compiled program: the tape, the index of the data pointer, read when it starts
+// and written when it stops, and the I/O. Returns 0, or 1 when the program was
+// stopped by an error
+type Entry = unsafe extern "C" fn(*mut u8, *mut i32, *mut JitIo) -> i32;
+
+/// A program compiled to native code, freed with it
+pub struct JitFunction {
+    entry: Entry,
+    module: Option<JITModule>,
+    pub tape_size: usize, // cells the code wraps the pointer around
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum JitError {
+    Unsupported(&'static str), // an instruction the compiled code cannot run
+    UnmatchedJump { position: usize },
+    Compile(String), // Cranelift failed on the function
+}
+
+impl fmt::Display for JitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JitError::Unsupported(what) => write!(f, "the JIT does not run {}", what),
+            JitError::UnmatchedJump { position } => {
+                write!(f, "unmatched jump at instruction {}", position)
+            }
+            JitError::Compile(message) => write!(f, "JIT compilation failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for JitError {}
+
+impl JitFunction {
+    /// Run the code on the tape and the I/O of the interpreter, from the cell the
+    /// pointer is at. The pointer is left wrapped onto the tape. `should_stop` and
+    /// `timeout` are checked every `POLL_INTERVAL` loop iterations, steps, the other
+    /// limits and replay logs are not counted or checked.
+    ///
+    /// Panics when the tape is not as long as the one the code was compiled for
+    pub fn run(&self, interpreter: &mut Interpreter) -> Result<(), InterpreterError> {
+        let mut pointer = memory_index(interpreter) as i32;
+        let tape = interpreter.memory.make_contiguous();
+        assert_eq!(tape.len(), self.tape_size, "tape of another size");
+        let mut io = JitIo {
+            input: &mut interpreter.input,
+            output: &mut *interpreter.output,
+            eof_behavior: interpreter.eof_behavior,
+            output_format: interpreter.output_format,
+            should_stop: &interpreter.should_stop,
+            deadline: interpreter.timeout.map(|timeout| Instant::now() + timeout),
+            error: None,
+        };
+        // the code stays inside the tape_size cells of the tape
+        unsafe { (self.entry)(tape.as_mut_ptr(), &mut pointer, &mut io) };
+        let error = io.error;
+        interpreter.pointer = pointer;
+        match error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for JitFunction {
+    fn drop(&mut self) {
+        if let Some(module) = self.module.take() {
+            // the entry point is dropped with the code
+            unsafe { module.free_memory() };
+        }
+    }
+}
+
+extern "C" fn jit_output(io: *mut JitIo, byte: u8) -> i32 {
+    let io = unsafe { &mut *io };
+    let result = match io.output_format {
+        OutputFormatter::Char => io.output.write_all(&[byte]),
+        format => io.output.write_all(&format.format(byte)),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(error) => {
+            io.error = Some(InterpreterError::Output(error.kind()));
+            1
+        }
+    }
+}
+
+extern "C" fn jit_poll(io: *mut JitIo) -> i32 {
+    let io = unsafe { &mut *io };
+    if io.should_stop.load(Ordering::Relaxed) {
+        io.error = Some(InterpreterError::Interrupted);
+    } else if io
+        .deadline
+        .is_some_and(|deadline| Instant::now() > deadline)
+    {
+        io.error = Some(InterpreterError::Timeout);
+    }
+    io.error.is_some().into()
+}
+
+extern "C" fn jit_input(io: *mut JitIo, current: u8) -> u8 {
+    let io = unsafe { &mut *io };
+    match (io.input.read_byte(), io.eof_behavior) {
+        (Some(byte), _) => byte,
+        (None, EofBehavior::Zero) => 0,
+        (None, EofBehavior::Max) => 255,
+        (None, EofBehavior::Unchanged) => current,
+    }
+}
+
+fn compile_error(error: impl fmt::Display) -> JitError {
+    JitError::Compile(error.to_string())
+}
+
+/// Compile the bytecode for a tape of `tape_size` cells, the pointer wrapping
+/// around it. `#` is not supported
+pub fn jit_compile(program: &[Bytecode], tape_size: usize) -> Result<JitFunction, JitError> {
+    if tape_size == 0 || tape_size > i32::MAX as usize {
+        return Err(JitError::Unsupported("a tape of this size"));
+    }
+    let mut builder = JITBuilder::with_flags(&[("opt_level", "speed")], default_libcall_names())
+        .map_err(compile_error)?;
+    builder.symbol("jit_output", jit_output as *const u8);
+    builder.symbol("jit_input", jit_input as *const u8);
+    builder.symbol("jit_poll", jit_poll as *const u8);
+    let mut module = JITModule::new(builder);
+    let pointer_type = module.target_config().pointer_type();
+
+    let mut output_signature = module.make_signature();
+    output_signature.params.push(AbiParam::new(pointer_type));
+    output_signature.params.push(AbiParam::new(types::I8));
+    output_signature.returns.push(AbiParam::new(types::I32));
+    let output = module
+        .declare_function("jit_output", Linkage::Import, &output_signature)
+        .map_err(compile_error)?;
+    let mut input_signature = module.make_signature();
+    input_signature.params.push(AbiParam::new(pointer_type));
+    input_signature.params.push(AbiParam::new(types::I8));
+    input_signature.returns.push(AbiParam::new(types::I8));
+    let input = module
+        .declare_function("jit_input", Linkage::Import, &input_signature)
+        .map_err(compile_error)?;
+    let mut poll_signature = module.make_signature();
+    poll_signature.params.push(AbiParam::new(pointer_type));
+    poll_signature.returns.push(AbiParam::new(types::I32));
+    let poll = module
+        .declare_function("jit_poll", Linkage::Import, &poll_signature)
+        .map_err(compile_error)?;
+
+    let mut context = module.make_context();
+    for _ in 0..3 {
+        context
+            .func
+            .signature
+            .params
+            .push(AbiParam::new(pointer_type));
+    }
+    context
+        .func
+        .signature
+        .returns
+        .push(AbiParam::new(types::I32));
+    let entry = module
+        .declare_function("run", Linkage::Local, &context.func.signature)
+        .map_err(compile_error)?;
+    context.func.name = UserFuncName::user(0, entry.as_u32());
+
+    let mut function_context = FunctionBuilderContext::new();
+    let mut function = FunctionBuilder::new(&mut context.func, &mut function_context);
+    let start = function.create_block();
+    function.append_block_params_for_function_params(start);
+    function.switch_to_block(start);
+    let params = function.block_params(start).to_vec();
+    let pointer = function.declare_var(pointer_type);
+    let index = function
+        .ins()
+        .load(types::I32, MemFlagsData::trusted(), params[1], 0);
+    let index = function.ins().uextend(pointer_type, index);
+    function.def_var(pointer, index);
+    let countdown = function.declare_var(types::I32);
+    let interval = function.ins().iconst(types::I32, POLL_INTERVAL);
+    function.def_var(countdown, interval);
+    let output = module.declare_func_in_func(output, function.func);
+    let input = module.declare_func_in_func(input, function.func);
+    let poll = module.declare_func_in_func(poll, function.func);
+    let exit = function.create_block();
+    let failed = function.create_block();
+    let mut emitter = Emitter {
+        function,
+        tape: params[0],
+        pointer_out: params[1],
+        io: params[2],
+        pointer,
+        countdown,
+        tape_size: tape_size as i64,
+        output,
+        input,
+        poll,
+        exit,
+        failed,
+    };
+    emitter.emit(program)?;
+    emitter.finish(module.target_config());
+
+    module
+        .define_function(entry, &mut context)
+        .map_err(compile_error)?;
+    module.clear_context(&mut context);
+    module.finalize_definitions().map_err(compile_error)?;
+    Ok(JitFunction {
+        entry: finalized_entry(&module, entry),
+        module: Some(module),
+        tape_size,
+    })
+}
+
+fn finalized_entry(module: &JITModule, entry: FuncId) -> Entry {
+    let code = module.get_finalized_function(entry);
+    // the function was built with the parameters and result of Entry
+    unsafe { std::mem::transmute::<*const u8, Entry>(code) }
+}
+
+struct Emitter<'a> {
+    function: FunctionBuilder<'a>,
+    tape: Value,
+    pointer_out: Value,
+    io: Value,
+    pointer: Variable,   // index of the current cell
+    countdown: Variable, // loop iterations left until the next poll
+    tape_size: i64,
+    output: FuncRef,
+    input: FuncRef,
+    poll: FuncRef,
+    exit: Block,   // stores the pointer and returns 0
+    failed: Block, // stores the pointer and returns 1
+}
+
+impl Emitter<'_> {
+    fn emit(&mut self, program: &[Bytecode]) -> Result<(), JitError> {
+        // body and exit block of every open loop
+        let mut loops: Vec<(Block, Block)> = Vec::new();
+        for (position, instruction) in program.iter().enumerate() {
+            match *instruction {
+                Bytecode::MoveBy(delta) => {
+                    let index = self.offset_index(delta.into());
+                    self.function.def_var(self.pointer, index);
+                }
+                Bytecode::AddBy(delta) => {
+                    let address = self.cell_address();
+                    let cell = self.load(address);
+                    let sum = self.function.ins().iadd_imm_s(cell, i64::from(delta));
+                    self.store(address, sum);
+                }
+                Bytecode::SetZero => {
+                    let address = self.cell_address();
+                    let zero = self.function.ins().iconst(types::I8, 0);
+                    self.store(address, zero);
+                }
+                Bytecode::AddTo { offset } => {
+                    let address = self.cell_address();
+                    let cell = self.load(address);
+                    let index = self.offset_index(offset.into());
+                    let target = self.function.ins().iadd(self.tape, index);
+                    let value = self.load(target);
+                    let sum = self.function.ins().iadd(value, cell);
+                    self.store(target, sum);
+                    let zero = self.function.ins().iconst(types::I8, 0);
+                    self.store(address, zero);
+                }
+                Bytecode::ScanRight => self.scan(1),
+                Bytecode::ScanLeft => self.scan(-1),
+                Bytecode::Output => {
+                    let address = self.cell_address();
+                    let cell = self.load(address);
+                    let call = self.function.ins().call(self.output, &[self.io, cell]);
+                    let status = self.function.inst_results(call)[0];
+                    let next = self.function.create_block();
+                    self.function
+                        .ins()
+                        .brif(status, self.failed, &[], next, &[]);
+                    self.function.switch_to_block(next);
+                }
+                Bytecode::Input => {
+                    let address = self.cell_address();
+                    let cell = self.load(address);
+                    let call = self.function.ins().call(self.input, &[self.io, cell]);
+                    let byte = self.function.inst_results(call)[0];
+                    self.store(address, byte);
+                }
+                Bytecode::JumpForward(_) => {
+                    let body = self.function.create_block();
+                    let after = self.function.create_block();
+                    let address = self.cell_address();
+                    let cell = self.load(address);
+                    self.function.ins().brif(cell, body, &[], after, &[]);
+                    self.function.switch_to_block(body);
+                    loops.push((body, after));
+                }
+                Bytecode::JumpBackward(_) => {
+                    let (body, after) = loops.pop().ok_or(JitError::UnmatchedJump { position })?;
+                    self.poll();
+                    let address = self.cell_address();
+                    let cell = self.load(address);
+                    self.function.ins().brif(cell, body, &[], after, &[]);
+                    self.function.switch_to_block(after);
+                }
+                Bytecode::Halt => {
+                    self.function.ins().jump(self.exit, &[]);
+                    // the rest of the program is never reached
+                    let unreachable = self.function.create_block();
+                    self.function.switch_to_block(unreachable);
+                }
+                Bytecode::DebugPrint => return Err(JitError::Unsupported("'#'")),
+            }
+        }
+        if !loops.is_empty() {
+            return Err(JitError::UnmatchedJump {
+                position: program.len(),
+            });
+        }
+        Ok(())
+    }
+
+    // Store the pointer for the caller and return the status from both blocks
+    fn finish(mut self, config: TargetFrontendConfig) {
+        self.function.ins().jump(self.exit, &[]);
+        for (block, status) in [(self.exit, 0), (self.failed, 1)].iter().copied() {
+            self.function.switch_to_block(block);
+            let index = self.function.use_var(self.pointer);
+            let index = self.function.ins().ireduce(types::I32, index);
+            self.function
+                .ins()
+                .store(MemFlagsData::trusted(), index, self.pointer_out, 0);
+            let status = self.function.ins().iconst(types::I32, status);
+            self.function.ins().return_(&[status]);
+        }
+        self.function.seal_all_blocks();
+        self.function.finalize(config);
+    }
+
+    // Index of the cell `delta` away from the pointer, wrapped around the tape
+    fn offset_index(&mut self, delta: i64) -> Value {
+        let index = self.function.use_var(self.pointer);
+        let moved = self
+            .function
+            .ins()
+            .iadd_imm_s(index, delta.rem_euclid(self.tape_size));
+        let past_end =
+            self.function
+                .ins()
+                .icmp_imm_s(IntCC::SignedGreaterThanOrEqual, moved, self.tape_size);
+        let wrapped = self.function.ins().iadd_imm_s(moved, -self.tape_size);
+        self.function.ins().select(past_end, wrapped, moved)
+    }
+
+    // Move the pointer by `step` until it is on a zero cell
+    fn scan(&mut self, step: i64) {
+        let header = self.function.create_block();
+        let body = self.function.create_block();
+        let after = self.function.create_block();
+        self.function.ins().jump(header, &[]);
+        self.function.switch_to_block(header);
+        let address = self.cell_address();
+        let cell = self.load(address);
+        self.function.ins().brif(cell, body, &[], after, &[]);
+        self.function.switch_to_block(body);
+        let index = self.offset_index(step);
+        self.function.def_var(self.pointer, index);
+        self.poll();
+        self.function.ins().jump(header, &[]);
+        self.function.switch_to_block(after);
+    }
+
+    // Count down one loop iteration, calling back into Rust when the countdown ends
+    // and leaving through the failed block when the program has to stop
+    fn poll(&mut self) {
+        let left = self.function.use_var(self.countdown);
+        let left = self.function.ins().iadd_imm_s(left, -1);
+        self.function.def_var(self.countdown, left);
+        let check = self.function.create_block();
+        let next = self.function.create_block();
+        self.function.ins().brif(left, next, &[], check, &[]);
+        self.function.switch_to_block(check);
+        let interval = self.function.ins().iconst(types::I32, POLL_INTERVAL);
+        self.function.def_var(self.countdown, interval);
+        let call = self.function.ins().call(self.poll, &[self.io]);
+        let status = self.function.inst_results(call)[0];
+        self.function
+            .ins()
+            .brif(status, self.failed, &[], next, &[]);
+        self.function.switch_to_block(next);
+    }
+
+    fn cell_address(&mut self) -> Value {
+        let index = self.function.use_var(self.pointer);
+        self.function.ins().iadd(self.tape, index)
+    }
+
+    fn load(&mut self, address: Value) -> Value {
+        self.function
+            .ins()
+            .load(types::I8, MemFlagsData::trusted(), address, 0)
+    }
+
+    fn store(&mut self, address: Value, value: Value) {
+        self.function
+            .ins()
+            .store(MemFlagsData::trusted(), value, address, 0);
+    }
+}
@@ -0,0 +1,1637 @@
+//! Brainfuck interpreter library: lexical analysis, parsing into an abstract
+//! syntax tree and interpretation of the tree on a tape of cells.
+//!
+//! With the `no_std` feature only the core is built, on `alloc` alone: the
+//! commands, the tree, `lexical_analysis_str`, `syntax_analysis` and `run_program`
+//! on an `Interpreter` doing its input and output through an `IoProvider`.
+
+#![cfg_attr(feature = "no_std", no_std)]
+
+extern crate alloc;
+
+// A `tracing` event at the given level, compiled away without the logging feature
+macro_rules! log {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "logging")]
+        tracing::$level!($($arg)*);
+    };
+}
+
+#[cfg(not(feature = "no_std"))]
+pub mod arena;
+#[cfg(not(feature = "no_std"))]
+pub mod bytecode;
+pub mod cell;
+#[cfg(not(feature = "no_std"))]
+pub mod codegen;
+#[cfg(not(feature = "no_std"))]
+pub mod color;
+#[cfg(not(feature = "no_std"))]
+pub mod debugger;
+pub mod diagnostic;
+#[cfg(not(feature = "no_std"))]
+pub mod equiv;
+pub mod error;
+#[cfg(not(feature = "no_std"))]
+pub mod ffi;
+#[cfg(not(feature = "no_std"))]
+pub mod format;
+#[cfg(feature = "jit")]
+pub mod jit;
+#[cfg(not(feature = "no_std"))]
+pub mod lint;
+#[cfg(not(feature = "no_std"))]
+pub mod metrics;
+#[cfg(not(feature = "no_std"))]
+pub mod ook;
+#[cfg(not(feature = "no_std"))]
+pub mod optimizer;
+#[cfg(not(feature = "no_std"))]
+pub mod profile;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod replay;
+#[cfg(not(feature = "no_std"))]
+pub mod snapshot;
+pub mod tape;
+#[cfg(not(feature = "no_std"))]
+pub mod trace;
+#[cfg(not(feature = "no_std"))]
+pub mod visit;
+#[cfg(not(feature = "no_std"))]
+pub mod vm;
+
+use crate::cell::{CellArithmetic, CellSize, SaturatingU8};
+use crate::error::BrainfuckError;
+use crate::replay::{Replay, ReplayError};
+#[cfg(not(feature = "no_std"))]
+use crate::tape::SparseTape;
+use crate::tape::Tape;
+use crate::Command::*;
+use crate::NodeType::*;
+use alloc::{
+    boxed::Box,
+    collections::VecDeque,
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+use core::{
+    convert::TryFrom,
+    fmt,
+    marker::PhantomData,
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+#[cfg(not(feature = "no_std"))]
+use std::{
+    collections::HashMap,
+    io::{self, stdin, stdout, Read, Write},
+    time::Instant,
+};
+
+pub const MEMORY_SIZE: usize = 30000;
+pub const MAX_MEMORY_SIZE: usize = usize::MAX / 2;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Command {
+    Default,      // Nothing will happen
+    IncDP,        // '>' -> Increment the data pointer (to point to the next cell to the right).
+    DecDP,        // '<' -> Decrement the data pointer (to point to the next cell to the left).
+    IncByte,      // '+' -> Increment (increase by one) the byte at the data pointer.
+    DecByte,      // '-' -> Decrement (decrease by one) the byte at the data pointer.
+    OutByte,      // '.' -> Output the byte at the data pointer.
+    InByte, // ',' -> Accept one byte of input, storing its value in the byte at the data pointer.
+    JumpForward, // '[' -> If the byte at the data pointer is zero, then instead of moving the instruction pointer forward to the next command, jump it forward to the command after the matching ] command.
+    JumpBackward, // ']' -> If the byte at the data pointer is nonzero, then instead of moving the instruction pointer forward to the next command, jump it back to the command after the matching [ command.
+    MoveBy(i32),  // Optimized: move the data pointer by the given number of cells.
+    AddByte(i8),  // Optimized: add the given amount (modulo 256) to the byte at the data pointer.
+    SetZero,      // Optimized: '[-]' -> Set the byte at the data pointer to zero.
+    // Optimized: '[->+<]' -> Add the byte at the data pointer to the byte `offset` cells away and set it to zero.
+    AddTo { offset: i32 },
+    ScanRight,  // Optimized: '[>]' -> Move the data pointer right to the closest zero byte.
+    ScanLeft,   // Optimized: '[<]' -> Move the data pointer left to the closest zero byte.
+    DebugPrint, // Extension: '#' -> Print the pointer and the cells around it to stderr.
+    Halt,       // Extension: '@' -> Stop the program right away.
+    // Extension: '{' -> Define the subroutine up to the matching '}', with the pointer as its ID.
+    DefineSubroutine,
+    EndSubroutine,  // Extension: '}' -> End the subroutine definition.
+    CallSubroutine, // Extension: ':' -> Run the most recently defined subroutine.
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NodeType {
+    Program,
+    Loop,
+    Operator,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OverflowMode {
+    Wrap,          // pointer wraps around the tape
+    Error,         // pointer leaving the tape stops the program
+    Grow,          // tape grows to the right when the pointer leaves it, left is an error
+    Bidirectional, // tape grows to whichever side the pointer leaves it
+}
+
+/// The way a cell left its range under `overflow_check`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OverflowDirection {
+    Up,   // above the largest value, 255 + 1 on bytes
+    Down, // below the smallest value, 0 - 1 on bytes
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EofBehavior {
+    Zero,      // input at end of file stores 0
+    Max,       // input at end of file stores 255, same as -1
+    Unchanged, // input at end of file leaves the cell as it is
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OutputFormatter {
+    Char,    // the raw byte
+    Decimal, // "65\n"
+    Hex,     // "0x41\n"
+    Binary,  // "01000001\n"
+}
+
+impl OutputFormatter {
+    /// Bytes written for one output byte
+    pub fn format(self, byte: u8) -> Vec<u8> {
+        match self {
+            OutputFormatter::Char => vec![byte],
+            OutputFormatter::Decimal => format!("{}\n", byte).into_bytes(),
+            OutputFormatter::Hex => format!("0x{:02X}\n", byte).into_bytes(),
+            OutputFormatter::Binary => format!("{:08b}\n", byte).into_bytes(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SyntaxError {
+    UnmatchedOpen { line: usize, col: usize }, // '[' without its ']'
+    UnmatchedClose { line: usize, col: usize }, // ']' without its '['
+    UnmatchedOpenBrace { line: usize, col: usize }, // '{' without its '}'
+    UnmatchedCloseBrace { line: usize, col: usize }, // '}' without its '{'
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SyntaxError::UnmatchedOpen { line, col } => {
+                write!(f, "error: unmatched '[' at line {}, column {}", line, col)
+            }
+            SyntaxError::UnmatchedClose { line, col } => {
+                write!(f, "error: unmatched ']' at line {}, column {}", line, col)
+            }
+            SyntaxError::UnmatchedOpenBrace { line, col } => {
+                write!(f, "error: unmatched '{{' at line {}, column {}", line, col)
+            }
+            SyntaxError::UnmatchedCloseBrace { line, col } => {
+                write!(f, "error: unmatched '}}' at line {}, column {}", line, col)
+            }
+        }
+    }
+}
+
+impl core::error::Error for SyntaxError {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum InterpreterError {
+    PointerOutOfBounds {
+        pointer: i32,
+    },
+    TapeLimitExceeded {
+        pointer: i32,
+        limit: usize,
+    },
+    #[cfg(not(feature = "no_std"))]
+    Output(io::ErrorKind),
+    #[cfg(not(feature = "no_std"))]
+    Checkpoint(io::ErrorKind),
+    StepLimitExceeded {
+        steps: u64,
+    },
+    Timeout,
+    LoopIterationLimitExceeded {
+        offset: usize,
+    },
+    Interrupted,
+    HaltInstruction, // the program ran '@', a clean exit rather than a failure
+    UndefinedSubroutine {
+        offset: usize,
+    }, // ':' before any '{'
+    CallStackOverflow {
+        offset: usize,
+    }, // ':' nested more than MAX_CALL_DEPTH times
+    CellOverflow {
+        cell: usize,
+        direction: OverflowDirection,
+    }, // cell is the index in memory
+    Replay(ReplayError),
+    EndlessScan {
+        pointer: i32,
+    }, // '[>]' or '[<]' on a wrapping tape without any zero cell
+}
+
+impl fmt::Display for InterpreterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InterpreterError::PointerOutOfBounds { pointer } => {
+                write!(f, "pointer out of bounds: {}", pointer)
+            }
+            InterpreterError::TapeLimitExceeded { pointer, limit } => write!(
+                f,
+                "pointer {} is beyond the maximum tape size of {} cells",
+                pointer, limit
+            ),
+            #[cfg(not(feature = "no_std"))]
+            InterpreterError::Output(kind) => write!(f, "failed writing output: {}", kind),
+            #[cfg(not(feature = "no_std"))]
+            InterpreterError::Checkpoint(kind) => {
+                write!(f, "failed writing a checkpoint: {}", kind)
+            }
+            InterpreterError::StepLimitExceeded { steps } => {
+                write!(f, "step limit of {} exceeded", steps)
+            }
+            InterpreterError::Timeout => write!(f, "execution timed out"),
+            InterpreterError::LoopIterationLimitExceeded { offset } => write!(
+                f,
+                "loop at source offset {} exceeded the iteration limit",
+                offset
+            ),
+            InterpreterError::Interrupted => write!(f, "execution interrupted by user"),
+            InterpreterError::HaltInstruction => write!(f, "program halted by '@'"),
+            InterpreterError::UndefinedSubroutine { offset } => write!(
+                f,
+                "call at source offset {} before any subroutine was defined",
+                offset
+            ),
+            InterpreterError::CallStackOverflow { offset } => write!(
+                f,
+                "call at source offset {} nested more than {} subroutine calls",
+                offset, MAX_CALL_DEPTH
+            ),
+            InterpreterError::CellOverflow { cell, direction } => match direction {
+                OverflowDirection::Up => {
+                    write!(f, "cell {} overflowed past its largest value", cell)
+                }
+                OverflowDirection::Down => {
+                    write!(f, "cell {} underflowed past its smallest value", cell)
+                }
+            },
+            InterpreterError::Replay(error) => error.fmt(f),
+            InterpreterError::EndlessScan { pointer } => write!(
+                f,
+                "scan from pointer {} never ends, the tape has no zero cell",
+                pointer
+            ),
+        }
+    }
+}
+
+impl core::error::Error for InterpreterError {}
+
+pub struct Interpreter<T: CellArithmetic = u8, M: Tape<T> = VecDeque<T>> {
+    pub memory: M,   // the cells, a VecDeque unless another tape is chosen
+    pub offset: i64, // address of the first cell in memory, moves left as a bidirectional tape grows
+    pub pointer: i32,
+    pub tape_size: usize,
+    pub max_tape_size: usize, // limit of a growing tape
+    pub overflow_mode: OverflowMode,
+    pub eof_behavior: EofBehavior,
+    #[cfg(not(feature = "no_std"))]
+    pub input: InputSource,
+    #[cfg(not(feature = "no_std"))]
+    pub output: Box<dyn Write>,
+    #[cfg(feature = "no_std")]
+    pub io: Box<dyn IoProvider>, // what `,` reads and `.` writes without the standard library
+    pub output_format: OutputFormatter,
+    pub max_steps: Option<u64>, // instructions and loop iterations allowed, None is unlimited
+    pub steps: u64, // counted against max_steps, only with a limit, progress or a replay log
+    #[cfg(not(feature = "no_std"))]
+    pub timeout: Option<Duration>, // wall time a run may take, checked once per loop iteration
+    pub max_loop_iters: Option<u64>, // iterations a loop may run each time it is entered
+    pub should_stop: Arc<AtomicBool>, // set from another thread to interrupt the program
+    pub subroutines: Subroutines, // bodies defined with '{' by the pointer they were defined at
+    pub last_subroutine: Option<u32>, // ID called by ':'
+    pub call_stack: Vec<usize>, // source offsets of the ':' being run
+    pub color: bool, // highlight the current cell in tape dumps
+    #[cfg(not(feature = "no_std"))]
+    pub progress: Option<Progress>, // reports of a long run on stderr, None shows nothing
+    pub overflow_check: bool, // a cell leaving its range is an error instead of wrapping
+    pub replay: Option<Replay>, // the log `,` and `.` are recorded in or played from
+    cell_type: PhantomData<T>, // the type of the cells `memory` holds
+}
+
+/// A line printed to stderr every `interval` steps, each one overwriting the last
+#[cfg(not(feature = "no_std"))]
+pub struct Progress {
+    pub interval: u64,
+    pub start: Instant,
+    pub shown: bool, // a line was printed and the cursor is still at its end
+}
+
+/// Where `,` reads its bytes from
+#[cfg(not(feature = "no_std"))]
+pub enum InputSource {
+    Stdin(StdinInput),
+    Buffer(Vec<u8>, usize), // bytes and the position of the next one
+    Callback(Box<dyn FnMut() -> Option<u8>>), // None is the end of file
+}
+
+#[cfg(not(feature = "no_std"))]
+impl InputSource {
+    /// The next byte, None at end of file
+    pub fn read_byte(&mut self) -> Option<u8> {
+        match self {
+            InputSource::Stdin(stdin) => stdin.read_byte(),
+            InputSource::Buffer(bytes, position) => {
+                let byte = bytes.get(*position).copied();
+                if byte.is_some() {
+                    *position += 1;
+                }
+                byte
+            }
+            InputSource::Callback(input) => input(),
+        }
+    }
+}
+
+#[cfg(not(feature = "no_std"))]
+impl fmt::Debug for InputSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputSource::Stdin(stdin) => f.debug_tuple("Stdin").field(stdin).finish(),
+            InputSource::Buffer(bytes, position) => f
+                .debug_tuple("Buffer")
+                .field(bytes)
+                .field(position)
+                .finish(),
+            InputSource::Callback(_) => write!(f, "Callback"),
+        }
+    }
+}
+
+/// Output handing every byte to a callback, see `Interpreter::with_io`
+#[cfg(not(feature = "no_std"))]
+pub struct CallbackOutput(pub Box<dyn FnMut(u8)>);
+
+#[cfg(not(feature = "no_std"))]
+impl Write for CallbackOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        buf.iter().for_each(|&byte| (self.0)(byte));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Standard input remembering when it reached end of file, it is not read
+/// again afterwards
+#[cfg(not(feature = "no_std"))]
+#[derive(Debug, Default)]
+pub struct StdinInput {
+    pub eof: bool,
+}
+
+#[cfg(not(feature = "no_std"))]
+impl StdinInput {
+    pub fn read_byte(&mut self) -> Option<u8> {
+        if self.eof {
+            return None;
+        }
+        let mut buffer = [0; 1];
+        if stdin().read_exact(&mut buffer).is_ok() {
+            return Some(buffer[0]);
+        }
+        self.eof = true;
+        None
+    }
+}
+
+/// Input and output of an interpreter built without the standard library, `,`
+/// reads with `read_byte` and `.` writes with `write_byte`
+#[cfg(feature = "no_std")]
+pub trait IoProvider {
+    /// The next byte, None at end of file
+    fn read_byte(&mut self) -> Option<u8>;
+    fn write_byte(&mut self, byte: u8);
+
+    /// Text of a '#', nothing is shown unless the provider has somewhere to show it
+    fn debug_print(&mut self, _text: &str) {}
+}
+
+/// Input and output handing the bytes to callbacks, see `Interpreter::with_io`
+#[cfg(feature = "no_std")]
+pub struct CallbackIo {
+    pub input: Box<dyn FnMut() -> Option<u8>>,
+    pub output: Box<dyn FnMut(u8)>,
+}
+
+#[cfg(feature = "no_std")]
+impl IoProvider for CallbackIo {
+    fn read_byte(&mut self) -> Option<u8> {
+        (self.input)()
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        (self.output)(byte)
+    }
+}
+
+/// Subroutine bodies by the pointer they were defined at, a `BTreeMap` without the
+/// standard library
+#[cfg(not(feature = "no_std"))]
+pub type Subroutines = HashMap<u32, Node>;
+#[cfg(feature = "no_std")]
+pub type Subroutines = alloc::collections::BTreeMap<u32, Node>;
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Node {
+    pub node_type: NodeType,
+    pub instruction: Command,
+    pub source_pos: usize, // byte offset of the command in the source
+    pub childrens: Vec<Node>,
+}
+
+// Trees are equal when they run the same, wherever their commands are in the source
+impl PartialEq for Node {
+    fn eq(&self, other: &Node) -> bool {
+        self.node_type == other.node_type
+            && self.instruction == other.instruction
+            && self.childrens == other.childrens
+    }
+}
+
+impl Eq for Node {}
+
+/// A parsed program, `"++[->+<].".parse::<Program>()` lexes and parses the source.
+/// With the serde feature it is written as its source code and read back from it
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "String", try_from = "String")
+)]
+pub struct Program {
+    pub ast: Node,
+}
+
+impl FromStr for Program {
+    type Err = SyntaxError;
+
+    /// Fails with the first unmatched bracket of the source
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        parse_commands(lexical_analysis_str(source), source)
+            .map(|ast| Program { ast })
+            .map_err(|errors| errors.into_iter().next().unwrap())
+    }
+}
+
+impl TryFrom<String> for Program {
+    type Error = SyntaxError;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        source.parse()
+    }
+}
+
+impl From<Program> for String {
+    fn from(program: Program) -> String {
+        program.to_string()
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.ast)
+    }
+}
+
+// Write `count` times `positive` or `negative` depending on the sign
+fn write_repeated(
+    f: &mut fmt::Formatter,
+    count: i64,
+    positive: char,
+    negative: char,
+) -> fmt::Result {
+    let symbol = if count < 0 { negative } else { positive };
+    for _ in 0..count.unsigned_abs() {
+        write!(f, "{}", symbol)?;
+    }
+    Ok(())
+}
+
+/// BF source of the tree, optimized commands are written as the loops they replaced
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.node_type {
+            Program => self
+                .childrens
+                .iter()
+                .try_for_each(|child| write!(f, "{}", child)),
+            Loop => {
+                write!(f, "[")?;
+                self.childrens
+                    .iter()
+                    .try_for_each(|child| write!(f, "{}", child))?;
+                write!(f, "]")
+            }
+            Operator => match self.instruction {
+                Default | JumpForward | JumpBackward | EndSubroutine => Ok(()),
+                IncDP => write!(f, ">"),
+                DecDP => write!(f, "<"),
+                IncByte => write!(f, "+"),
+                DecByte => write!(f, "-"),
+                OutByte => write!(f, "."),
+                InByte => write!(f, ","),
+                MoveBy(delta) => write_repeated(f, delta.into(), '>', '<'),
+                AddByte(delta) => write_repeated(f, delta.into(), '+', '-'),
+                SetZero => write!(f, "[-]"),
+                AddTo { offset } => {
+                    write!(f, "[-")?;
+                    write_repeated(f, offset.into(), '>', '<')?;
+                    write!(f, "+")?;
+                    write_repeated(f, -i64::from(offset), '>', '<')?;
+                    write!(f, "]")
+                }
+                ScanRight => write!(f, "[>]"),
+                ScanLeft => write!(f, "[<]"),
+                DebugPrint => write!(f, "#"),
+                Halt => write!(f, "@"),
+                DefineSubroutine => {
+                    write!(f, "{{")?;
+                    self.childrens
+                        .iter()
+                        .try_for_each(|child| write!(f, "{}", child))?;
+                    write!(f, "}}")
+                }
+                CallSubroutine => write!(f, ":"),
+            },
+        }
+    }
+}
+
+/// Interpreter initialization
+pub fn interpreter_init(size: usize) -> Interpreter {
+    Interpreter::new(size)
+}
+
+impl<T: CellArithmetic> Interpreter<T> {
+    /// An interpreter with `size` cells of type `T`, `Interpreter::<u16>::new`
+    /// runs programs on 16-bit cells
+    pub fn new(size: usize) -> Self {
+        Interpreter {
+            memory: VecDeque::from(vec![T::default(); size]),
+            offset: 0,
+            pointer: 0,
+            tape_size: size,
+            max_tape_size: MAX_MEMORY_SIZE,
+            overflow_mode: OverflowMode::Wrap,
+            eof_behavior: EofBehavior::Zero,
+            #[cfg(not(feature = "no_std"))]
+            input: InputSource::Stdin(StdinInput::default()),
+            #[cfg(not(feature = "no_std"))]
+            output: Box::new(stdout()),
+            #[cfg(feature = "no_std")]
+            io: Box::new(CallbackIo {
+                input: Box::new(|| None),
+                output: Box::new(|_| {}),
+            }),
+            output_format: OutputFormatter::Char,
+            max_steps: None,
+            #[cfg(not(feature = "no_std"))]
+            progress: None,
+            steps: 0,
+            #[cfg(not(feature = "no_std"))]
+            timeout: None,
+            max_loop_iters: None,
+            should_stop: Arc::new(AtomicBool::new(false)),
+            subroutines: Subroutines::new(),
+            last_subroutine: None,
+            call_stack: Vec::new(),
+            color: false,
+            overflow_check: false,
+            replay: None,
+            cell_type: PhantomData,
+        }
+    }
+}
+
+/// Bytes read from the source at a time by `LexerIterator`
+#[cfg(not(feature = "no_std"))]
+pub const LEXER_CHUNK_SIZE: usize = 8192;
+
+/// Operators lexed lazily from a reader, paired with their byte offset in the
+/// source. The source is read a chunk at a time, so only the commands are ever
+/// held in memory; an error reading it ends the iteration
+#[cfg(not(feature = "no_std"))]
+pub struct LexerIterator<R> {
+    reader: R,
+    chunk: Vec<u8>,
+    filled: usize, // bytes of the chunk read from the source
+    index: usize,  // next byte of the chunk
+    offset: usize, // offset in the source of the first byte of the chunk
+    extensions: bool,
+    done: bool,
+}
+
+#[cfg(not(feature = "no_std"))]
+impl<R: Read> LexerIterator<R> {
+    /// Lexer reading `reader`, also recognizing the extension commands as
+    /// `lexical_analysis_with_extensions` does when `extensions` is set
+    pub fn new(reader: R, extensions: bool) -> Self {
+        LexerIterator {
+            reader,
+            chunk: vec![0; LEXER_CHUNK_SIZE],
+            filled: 0,
+            index: 0,
+            offset: 0,
+            extensions,
+            done: false,
+        }
+    }
+}
+
+#[cfg(not(feature = "no_std"))]
+impl<R: Read> Iterator for LexerIterator<R> {
+    type Item = Result<(Command, usize), BrainfuckError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            while self.index < self.filled {
+                let byte = self.chunk[self.index];
+                let pos = self.offset + self.index;
+                self.index += 1;
+                if let Some(cmd) = lex_byte(byte, self.extensions) {
+                    return Some(Ok((cmd, pos)));
+                }
+            }
+            if self.done {
+                return None;
+            }
+            self.offset += self.filled;
+            self.index = 0;
+            self.filled = 0;
+            match self.reader.read(&mut self.chunk) {
+                Ok(0) => self.done = true,
+                Ok(read) => self.filled = read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error.into()));
+                }
+            }
+        }
+    }
+}
+
+/// read the source with a buffer and transform chars to operators paired with
+/// their byte offset in it, collect the iterator for all of them at once
+#[cfg(not(feature = "no_std"))]
+pub fn lexical_analysis<R: Read>(source: R) -> LexerIterator<R> {
+    LexerIterator::new(source, false)
+}
+
+/// `lexical_analysis` of source already in memory, lexed all at once
+pub fn lexical_analysis_str(source: &str) -> Vec<(Command, usize)> {
+    lexical_analysis_with_extensions(source.to_string(), false)
+}
+
+/// `lexical_analysis` that also recognizes `#` as `DebugPrint`, `@` as `Halt` and
+/// `{`, `}` and `:` as subroutine definitions and calls when extensions are allowed
+#[cfg_attr(feature = "logging", tracing::instrument(level = "debug", skip_all))]
+pub fn lexical_analysis_with_extensions(
+    commands: String,
+    extensions: bool,
+) -> Vec<(Command, usize)> {
+    // every command is ASCII, so no byte of a longer character is taken for one
+    let result: Vec<(Command, usize)> = commands
+        .bytes()
+        .enumerate()
+        .filter_map(|(pos, byte)| lex_byte(byte, extensions).map(|cmd| (cmd, pos)))
+        .collect();
+    log!(
+        debug,
+        commands = result.len(),
+        extensions,
+        "lexed the source"
+    );
+    result
+}
+
+// The operator a byte of the source stands for, None for comments
+fn lex_byte(byte: u8, extensions: bool) -> Option<Command> {
+    match byte {
+        b'>' => Some(IncDP),
+        b'<' => Some(DecDP),
+        b'+' => Some(IncByte),
+        b'-' => Some(DecByte),
+        b'.' => Some(OutByte),
+        b',' => Some(InByte),
+        b'[' => Some(JumpForward),
+        b']' => Some(JumpBackward),
+        b'#' if extensions => Some(DebugPrint),
+        b'@' if extensions => Some(Halt),
+        b'{' if extensions => Some(DefineSubroutine),
+        b'}' if extensions => Some(EndSubroutine),
+        b':' if extensions => Some(CallSubroutine),
+        _ => None,
+    }
+}
+
+/// Generates abstract syntactic tree
+pub fn create_ast(node: &mut Node, commands: &[(Command, usize)], index: &mut usize) {
+    while *index < commands.len() {
+        log!(trace, index = *index, "create_ast");
+        match commands.get(*index) {
+            Some((cmd, pos)) => match cmd {
+                JumpForward => {
+                    let mut new_node = Node {
+                        node_type: Loop,
+                        instruction: JumpForward,
+                        source_pos: *pos,
+                        childrens: Vec::new(),
+                    };
+                    *index += 1;
+                    create_ast(&mut new_node, commands, index);
+                    node.childrens.push(new_node);
+                }
+                DefineSubroutine => {
+                    let mut new_node = Node {
+                        node_type: Operator,
+                        instruction: DefineSubroutine,
+                        source_pos: *pos,
+                        childrens: Vec::new(),
+                    };
+                    *index += 1;
+                    create_ast(&mut new_node, commands, index);
+                    node.childrens.push(new_node);
+                }
+                JumpBackward | EndSubroutine => {
+                    return;
+                }
+                _ => {
+                    node.childrens.push(Node {
+                        node_type: Operator,
+                        instruction: *cmd,
+                        source_pos: *pos,
+                        childrens: Vec::new(),
+                    });
+                }
+            },
+            None => return,
+        }
+        *index += 1;
+    }
+}
+
+/// Line and column (both starting at 1) of a byte offset in the source
+pub fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source.as_bytes()[..offset];
+    let line = before.iter().filter(|&&byte| byte == b'\n').count() + 1;
+    let col = match before.iter().rposition(|&byte| byte == b'\n') {
+        Some(newline) => offset - newline,
+        None => offset + 1,
+    };
+    (line, col)
+}
+
+/// provide syntactic analysis, `source` is the text the commands were lexed from.
+/// Every unmatched bracket and subroutine brace is reported, in source order
+#[cfg_attr(feature = "logging", tracing::instrument(level = "debug", skip_all))]
+pub fn syntax_analysis(
+    commands: Vec<(Command, usize)>,
+    source: &str,
+) -> Result<Node, BrainfuckError> {
+    Ok(parse_commands(commands, source)?)
+}
+
+// `syntax_analysis` keeping the unmatched brackets apart, the first of them is
+// the error of `Program::from_str`
+fn parse_commands(commands: Vec<(Command, usize)>, source: &str) -> Result<Node, Vec<SyntaxError>> {
+    let unmatched = unmatched_brackets(&commands);
+    if !unmatched.is_empty() {
+        log!(warn, errors = unmatched.len(), "unmatched brackets");
+        return Err(unmatched
+            .into_iter()
+            .map(|(pos, cmd)| {
+                let (line, col) = line_col(source, pos);
+                match cmd {
+                    JumpForward => SyntaxError::UnmatchedOpen { line, col },
+                    JumpBackward => SyntaxError::UnmatchedClose { line, col },
+                    DefineSubroutine => SyntaxError::UnmatchedOpenBrace { line, col },
+                    _ => SyntaxError::UnmatchedCloseBrace { line, col },
+                }
+            })
+            .collect());
+    }
+    Ok(program_tree(&commands))
+}
+
+/// `syntax_analysis` of commands whose source is not at hand, like those a
+/// `LexerIterator` streams: None when a bracket or brace is unmatched, the source
+/// is then needed for `syntax_analysis` to tell where
+pub fn try_syntax_analysis(commands: &[(Command, usize)]) -> Option<Node> {
+    if unmatched_brackets(commands).is_empty() {
+        Some(program_tree(commands))
+    } else {
+        None
+    }
+}
+
+// Offsets of the brackets and braces without their match, in source order
+fn unmatched_brackets(commands: &[(Command, usize)]) -> Vec<(usize, Command)> {
+    // opening commands with their offset, a closing command only matches the last one
+    let mut stack: Vec<(Command, usize)> = Vec::new();
+    let mut unmatched: Vec<(usize, Command)> = Vec::new();
+    for &(cmd, pos) in commands.iter() {
+        let open = match cmd {
+            JumpForward | DefineSubroutine => {
+                stack.push((cmd, pos));
+                continue;
+            }
+            JumpBackward => JumpForward,
+            EndSubroutine => DefineSubroutine,
+            _ => continue,
+        };
+        match stack.last() {
+            Some(&(last, _)) if last == open => {
+                stack.pop();
+            }
+            _ => unmatched.push((pos, cmd)),
+        }
+    }
+    unmatched.extend(stack.into_iter().map(|(cmd, pos)| (pos, cmd)));
+    unmatched.sort_unstable_by_key(|&(pos, _)| pos);
+    unmatched
+}
+
+// The tree of commands whose brackets and braces all match
+fn program_tree(commands: &[(Command, usize)]) -> Node {
+    let mut program: Node = Node {
+        node_type: Program,
+        instruction: Default,
+        source_pos: 0,
+        childrens: Vec::new(),
+    };
+
+    let mut pos: usize = 0;
+    create_ast(&mut program, commands, &mut pos);
+    log!(
+        debug,
+        children = program.childrens.len(),
+        "parsed the program"
+    );
+
+    program
+}
+
+// Read one byte from user's input, None at end of file
+pub fn read_input<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &mut Interpreter<T, M>,
+) -> Option<u8> {
+    #[cfg(not(feature = "no_std"))]
+    let input = &mut interpreter.input;
+    #[cfg(feature = "no_std")]
+    let input = &mut interpreter.io;
+    input.read_byte()
+}
+
+/// Bytes of a string with C-style escapes `\n`, `\r`, `\t`, `\0`, `\\` and `\xNN`
+pub fn parse_escapes(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut encoded = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut encoded).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('r') => bytes.push(b'\r'),
+            Some('t') => bytes.push(b'\t'),
+            Some('0') => bytes.push(0),
+            Some('\\') => bytes.push(b'\\'),
+            Some('x') => {
+                let digits: String = chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&digits, 16) {
+                    Ok(byte) if digits.len() == 2 => bytes.push(byte),
+                    _ => return Err(format!("invalid escape sequence '\\x{}'", digits)),
+                }
+            }
+            Some(other) => return Err(format!("invalid escape sequence '\\{}'", other)),
+            None => return Err("escape sequence at the end of the input".to_string()),
+        }
+    }
+    Ok(bytes)
+}
+
+// Check that the pointer is still on the tape when overflow is an error, a
+// growing tape only ends on the left
+pub fn check_pointer<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &Interpreter<T, M>,
+    pointer: i32,
+) -> Result<(), InterpreterError> {
+    let out_of_bounds = match interpreter.overflow_mode {
+        OverflowMode::Wrap => false,
+        OverflowMode::Error => pointer < 0 || pointer as usize >= interpreter.tape_size,
+        OverflowMode::Grow => pointer < 0,
+        OverflowMode::Bidirectional => false,
+    };
+    if out_of_bounds {
+        return Err(InterpreterError::PointerOutOfBounds { pointer });
+    }
+    Ok(())
+}
+
+// Extend a growing tape up to the pointer, doubling its size like `Vec` does
+pub fn grow_tape<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &mut Interpreter<T, M>,
+    pointer: i32,
+) -> Result<(), InterpreterError> {
+    let address = pointer as i64;
+    let reached = address >= interpreter.offset
+        && address < interpreter.offset + interpreter.tape_size as i64;
+    let grows = match interpreter.overflow_mode {
+        OverflowMode::Grow => pointer >= 0,
+        OverflowMode::Bidirectional => true,
+        _ => false,
+    };
+    if reached || !grows {
+        return Ok(());
+    }
+    let needed = if address < interpreter.offset {
+        (interpreter.offset + interpreter.tape_size as i64 - address) as usize
+    } else {
+        (address - interpreter.offset) as usize + 1
+    };
+    if needed > interpreter.max_tape_size {
+        return Err(InterpreterError::TapeLimitExceeded {
+            pointer,
+            limit: interpreter.max_tape_size,
+        });
+    }
+    let mut size = interpreter.tape_size.max(1);
+    while size < needed {
+        size = size.saturating_mul(2);
+    }
+    size = size.min(interpreter.max_tape_size);
+    if address < interpreter.offset {
+        let added = size - interpreter.tape_size;
+        interpreter.memory.grow_front(added);
+        interpreter.offset -= added as i64;
+    } else {
+        interpreter.memory.resize(size);
+    }
+    interpreter.tape_size = size;
+    Ok(())
+}
+
+impl<T: CellArithmetic, M: Tape<T>> Interpreter<T, M> {
+    /// Address of the cell at an index of memory
+    pub fn cell_address(&self, index: usize) -> i64 {
+        index as i64 + self.offset
+    }
+
+    /// The same interpreter with cells of type `U`, every cell keeps its low byte
+    pub fn with_cells<U: CellArithmetic>(self) -> Interpreter<U> {
+        let memory = (0..self.memory.len())
+            .map(|index| U::from_input(self.memory.get(index).to_output()))
+            .collect();
+        self.swap_memory(memory).0
+    }
+
+    /// The same interpreter running on `tape`, which gets a copy of every cell and
+    /// grows to hold them
+    pub fn with_tape<N: Tape<T>>(self, mut tape: N) -> Interpreter<T, N> {
+        if tape.len() < self.memory.len() {
+            tape.resize(self.memory.len());
+        }
+        for index in 0..self.memory.len() {
+            tape.set(index, self.memory.get(index));
+        }
+        self.swap_memory(tape).0
+    }
+
+    // Every field but the cells moved to an interpreter running on `memory`, with
+    // the cells it replaced
+    fn swap_memory<U: CellArithmetic, N: Tape<U>>(self, memory: N) -> (Interpreter<U, N>, M) {
+        let interpreter = Interpreter {
+            memory,
+            offset: self.offset,
+            pointer: self.pointer,
+            tape_size: self.tape_size,
+            max_tape_size: self.max_tape_size,
+            overflow_mode: self.overflow_mode,
+            eof_behavior: self.eof_behavior,
+            #[cfg(not(feature = "no_std"))]
+            input: self.input,
+            #[cfg(not(feature = "no_std"))]
+            output: self.output,
+            #[cfg(feature = "no_std")]
+            io: self.io,
+            output_format: self.output_format,
+            max_steps: self.max_steps,
+            steps: self.steps,
+            #[cfg(not(feature = "no_std"))]
+            timeout: self.timeout,
+            max_loop_iters: self.max_loop_iters,
+            should_stop: self.should_stop,
+            subroutines: self.subroutines,
+            last_subroutine: self.last_subroutine,
+            call_stack: self.call_stack,
+            color: self.color,
+            #[cfg(not(feature = "no_std"))]
+            progress: self.progress,
+            overflow_check: self.overflow_check,
+            replay: self.replay,
+            cell_type: PhantomData,
+        };
+        (interpreter, self.memory)
+    }
+}
+
+impl Interpreter {
+    // Index in memory of the cell the pointer would reach at the address: a growing
+    // tape grows to hold it, otherwise it wraps or is out of bounds as for the pointer
+    fn reach_cell(&mut self, address: i32) -> Result<usize, InterpreterError> {
+        move_pointer(self, address)?;
+        Ok(wrap_pointer(self, address))
+    }
+
+    /// Value of the cell at the address, the tape grows to reach it when it grows
+    pub fn get_cell(&mut self, address: i32) -> Result<u8, InterpreterError> {
+        let index = self.reach_cell(address)?;
+        Ok(self.memory[index])
+    }
+
+    /// Set the cell at the address, the tape grows to reach it when it grows
+    pub fn set_cell(&mut self, address: i32, value: u8) -> Result<(), InterpreterError> {
+        let index = self.reach_cell(address)?;
+        self.memory[index] = value;
+        Ok(())
+    }
+
+    /// The interpreter reading `,` from `input` and sending the bytes of `.` to
+    /// `output`, when `input` returns None the EOF behavior applies
+    pub fn with_io(
+        mut self,
+        input: impl FnMut() -> Option<u8> + 'static,
+        output: impl FnMut(u8) + 'static,
+    ) -> Self {
+        #[cfg(not(feature = "no_std"))]
+        {
+            self.input = InputSource::Callback(Box::new(input));
+            self.output = Box::new(CallbackOutput(Box::new(output)));
+        }
+        #[cfg(feature = "no_std")]
+        {
+            self.io = Box::new(CallbackIo {
+                input: Box::new(input),
+                output: Box::new(output),
+            });
+        }
+        self
+    }
+
+    /// Every cell of the tape with its index in memory, `cell_address` turns the
+    /// index into an address
+    pub fn cells(&self) -> impl Iterator<Item = (usize, u8)> + '_ {
+        self.memory.iter().copied().enumerate()
+    }
+
+    /// The cells of `cells` that are not zero
+    pub fn nonzero_cells(&self) -> impl Iterator<Item = (usize, u8)> + '_ {
+        self.cells().filter(|&(_, value)| value != 0)
+    }
+}
+
+// Check the pointer and grow the tape when it has to reach it
+fn move_pointer<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &mut Interpreter<T, M>,
+    pointer: i32,
+) -> Result<(), InterpreterError> {
+    check_pointer(interpreter, pointer)?;
+    grow_tape(interpreter, pointer)
+}
+
+// Wrap a pointer onto the tape to get the actual index in memory vector, a
+// growing tape already reaches the pointer
+pub fn wrap_pointer<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &Interpreter<T, M>,
+    pointer: i32,
+) -> usize {
+    match interpreter.overflow_mode {
+        OverflowMode::Grow | OverflowMode::Bidirectional => {
+            (pointer as i64 - interpreter.offset) as usize
+        }
+        _ => (pointer as isize).rem_euclid(interpreter.tape_size as isize) as usize,
+    }
+}
+
+// Actual index of the data pointer in memory vector
+pub fn memory_index<T: CellArithmetic, M: Tape<T>>(interpreter: &Interpreter<T, M>) -> usize {
+    wrap_pointer(interpreter, interpreter.pointer)
+}
+
+// Distance to the closest zero byte right of index, wrapping around the tape
+fn scan_right<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &Interpreter<T, M>,
+    index: usize,
+) -> Result<usize, InterpreterError> {
+    if let Some(position) = interpreter.memory.find_zero_from(index) {
+        return Ok(position - index);
+    }
+    let to_end = interpreter.tape_size - index;
+    if let OverflowMode::Grow | OverflowMode::Bidirectional = interpreter.overflow_mode {
+        // cells the tape has not grown to yet are zero
+        return Ok(to_end);
+    }
+    check_pointer(interpreter, interpreter.pointer + to_end as i32)?;
+    match interpreter.memory.find_zero_from(0) {
+        Some(position) => Ok(to_end + position),
+        // the loop it replaced would never end either
+        None => Err(InterpreterError::EndlessScan {
+            pointer: interpreter.pointer,
+        }),
+    }
+}
+
+// Distance to the closest zero byte left of index, wrapping around the tape
+fn scan_left<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &Interpreter<T, M>,
+    index: usize,
+) -> Result<usize, InterpreterError> {
+    if let Some(position) = interpreter.memory.rfind_zero_until(index) {
+        return Ok(index - position);
+    }
+    if interpreter.overflow_mode == OverflowMode::Bidirectional {
+        return Ok(index + 1);
+    }
+    check_pointer(interpreter, interpreter.pointer - (index + 1) as i32)?;
+    match interpreter
+        .memory
+        .rfind_zero_until(interpreter.tape_size - 1)
+    {
+        Some(position) => Ok(interpreter.tape_size - position + index),
+        None => Err(InterpreterError::EndlessScan {
+            pointer: interpreter.pointer,
+        }),
+    }
+}
+
+/// Count one step against `max_steps`, every instruction and loop iteration is a step,
+/// and print the progress line every `progress.interval` steps
+pub fn count_step<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &mut Interpreter<T, M>,
+) -> Result<(), InterpreterError> {
+    #[cfg(not(feature = "no_std"))]
+    let shows_progress = interpreter.progress.is_some();
+    #[cfg(feature = "no_std")]
+    let shows_progress = false;
+    if let Some(limit) = interpreter.max_steps {
+        if interpreter.steps >= limit {
+            return Err(InterpreterError::StepLimitExceeded { steps: limit });
+        }
+        interpreter.steps += 1;
+    } else if shows_progress || interpreter.replay.is_some() {
+        interpreter.steps += 1;
+    }
+    #[cfg(not(feature = "no_std"))]
+    if let Some(progress) = &interpreter.progress {
+        if interpreter.steps.is_multiple_of(progress.interval) {
+            eprint!("\r{}", progress_line(interpreter, progress.start.elapsed()));
+            interpreter.progress.as_mut().unwrap().shown = true;
+        }
+    }
+    Ok(())
+}
+
+// Steps in thousands or millions when they are round enough
+fn format_steps(steps: u64) -> String {
+    match steps {
+        0 => "0".to_string(),
+        _ if steps.is_multiple_of(1_000_000) => format!("{}M", steps / 1_000_000),
+        _ if steps.is_multiple_of(1_000) => format!("{}K", steps / 1_000),
+        _ => steps.to_string(),
+    }
+}
+
+/// The progress report after `elapsed` of running, such as
+/// `[step=10M | ptr=42 | cell=127 | elapsed=1.2s]`
+pub fn progress_line<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &Interpreter<T, M>,
+    elapsed: Duration,
+) -> String {
+    format!(
+        "[step={} | ptr={} | cell={} | elapsed={:.1}s]",
+        format_steps(interpreter.steps),
+        interpreter.pointer,
+        interpreter.memory.get(memory_index(interpreter)),
+        elapsed.as_secs_f64()
+    )
+}
+
+/// Fail once `should_stop` was set, checked by the engines once per loop iteration
+pub fn check_interrupt<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &Interpreter<T, M>,
+) -> Result<(), InterpreterError> {
+    if interpreter.should_stop.load(Ordering::Relaxed) {
+        return Err(InterpreterError::Interrupted);
+    }
+    Ok(())
+}
+
+/// Fail once a run started at `start` took longer than `timeout`
+#[cfg(not(feature = "no_std"))]
+pub fn check_timeout<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &Interpreter<T, M>,
+    start: Instant,
+) -> Result<(), InterpreterError> {
+    match interpreter.timeout {
+        Some(limit) if start.elapsed() > limit => Err(InterpreterError::Timeout),
+        _ => Ok(()),
+    }
+}
+
+/// Cells this far left and right of the pointer are shown by `DebugPrint`
+pub const DEBUG_PRINT_RADIUS: usize = 5;
+
+/// The pointer and the cells around it, with an arrow under the current cell
+pub fn debug_print<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &Interpreter<T, M>,
+    index: usize,
+) -> String {
+    let first = index.saturating_sub(DEBUG_PRINT_RADIUS);
+    let last = (index + DEBUG_PRINT_RADIUS).min(interpreter.memory.len() - 1);
+    let cells: Vec<String> = (first..=last)
+        .map(|cell| {
+            let value = format!("{:>4}", interpreter.memory.get(cell));
+            if cell == index {
+                highlight(&value, interpreter.color)
+            } else {
+                value
+            }
+        })
+        .collect();
+    format!(
+        "pointer {}, cells {} to {}\n{}\n{:>width$}\n",
+        interpreter.pointer,
+        interpreter.pointer - (index - first) as i32,
+        interpreter.pointer + (last - index) as i32,
+        cells.concat(),
+        "^",
+        width = (index - first + 1) * 4
+    )
+}
+
+pub fn execute_instruction<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &mut Interpreter<T, M>,
+    cmd: &Command,
+    index: usize,
+) -> Result<(), InterpreterError> {
+    count_step(interpreter)?;
+    match cmd {
+        IncDP => {
+            interpreter.pointer += 1;
+            move_pointer(interpreter, interpreter.pointer)?;
+        }
+        DecDP => {
+            interpreter.pointer -= 1;
+            move_pointer(interpreter, interpreter.pointer)?;
+        }
+        MoveBy(delta) => {
+            interpreter.pointer += delta;
+            move_pointer(interpreter, interpreter.pointer)?;
+        }
+        IncByte | DecByte | AddByte(_) if interpreter.overflow_check => {
+            let delta = match *cmd {
+                IncByte => 1,
+                DecByte => -1,
+                AddByte(delta) => delta,
+                _ => unreachable!(),
+            };
+            let direction = if delta > 0 {
+                OverflowDirection::Up
+            } else {
+                OverflowDirection::Down
+            };
+            let cell = interpreter.memory.get(index).checked_add(delta).ok_or(
+                InterpreterError::CellOverflow {
+                    cell: index,
+                    direction,
+                },
+            )?;
+            interpreter.memory.set(index, cell);
+        }
+        IncByte => interpreter
+            .memory
+            .set(index, interpreter.memory.get(index).inc()),
+        DecByte => interpreter
+            .memory
+            .set(index, interpreter.memory.get(index).dec()),
+        AddByte(delta) => interpreter
+            .memory
+            .set(index, interpreter.memory.get(index).add(*delta)),
+        SetZero => interpreter.memory.set(index, T::default()),
+        AddTo { offset } if !interpreter.memory.get(index).is_zero() => {
+            let target = interpreter.pointer + offset;
+            move_pointer(interpreter, target)?;
+            let target = wrap_pointer(interpreter, target);
+            let cell = if interpreter.overflow_check {
+                interpreter
+                    .memory
+                    .get(target)
+                    .checked_add_cell(interpreter.memory.get(index))
+                    .ok_or(InterpreterError::CellOverflow {
+                        cell: target,
+                        direction: OverflowDirection::Up,
+                    })?
+            } else {
+                interpreter
+                    .memory
+                    .get(target)
+                    .add_cell(interpreter.memory.get(index))
+            };
+            interpreter.memory.set(target, cell);
+            interpreter.memory.set(index, T::default());
+        }
+        ScanRight => {
+            interpreter.pointer += scan_right(interpreter, index)? as i32;
+            move_pointer(interpreter, interpreter.pointer)?;
+        }
+        ScanLeft => {
+            interpreter.pointer -= scan_left(interpreter, index)? as i32;
+            move_pointer(interpreter, interpreter.pointer)?;
+        }
+        InByte => {
+            let byte = match &mut interpreter.replay {
+                Some(replay) => {
+                    #[cfg(not(feature = "no_std"))]
+                    let input = &mut interpreter.input;
+                    #[cfg(feature = "no_std")]
+                    let input = &mut interpreter.io;
+                    replay
+                        .input(interpreter.steps, || input.read_byte())
+                        .map_err(InterpreterError::Replay)?
+                }
+                None => read_input(interpreter),
+            };
+            match (byte, interpreter.eof_behavior) {
+                (Some(byte), _) => interpreter.memory.set(index, T::from_input(byte)),
+                (None, EofBehavior::Zero) => interpreter.memory.set(index, T::default()),
+                (None, EofBehavior::Max) => interpreter.memory.set(index, T::from_input(255)),
+                (None, EofBehavior::Unchanged) => {}
+            }
+        }
+        OutByte => {
+            let byte = interpreter.memory.get(index).to_output();
+            if let Some(replay) = &mut interpreter.replay {
+                replay
+                    .output(interpreter.steps, byte)
+                    .map_err(InterpreterError::Replay)?;
+            }
+            #[cfg(not(feature = "no_std"))]
+            {
+                let result = match interpreter.output_format {
+                    OutputFormatter::Char => interpreter.output.write_all(&[byte]),
+                    format => interpreter.output.write_all(&format.format(byte)),
+                };
+                result.map_err(|e| InterpreterError::Output(e.kind()))?
+            }
+            #[cfg(feature = "no_std")]
+            match interpreter.output_format {
+                OutputFormatter::Char => interpreter.io.write_byte(byte),
+                format => format
+                    .format(byte)
+                    .into_iter()
+                    .for_each(|byte| interpreter.io.write_byte(byte)),
+            }
+        }
+        #[cfg(not(feature = "no_std"))]
+        DebugPrint => eprint!("{}", debug_print(interpreter, index)),
+        #[cfg(feature = "no_std")]
+        DebugPrint => {
+            let text = debug_print(interpreter, index);
+            interpreter.io.debug_print(&text)
+        }
+        Halt => return Err(InterpreterError::HaltInstruction),
+        _ => {}
+    };
+    Ok(())
+}
+
+#[cfg_attr(feature = "logging", tracing::instrument(level = "debug", skip_all))]
+pub fn run_program<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &mut Interpreter<T, M>,
+    ast: &Node,
+) -> Result<(), InterpreterError> {
+    #[cfg(not(feature = "no_std"))]
+    let result = run_nodes(interpreter, ast, Instant::now());
+    #[cfg(feature = "no_std")]
+    let result = run_nodes(interpreter, ast);
+    if result.is_ok() {
+        log!(debug, pointer = interpreter.pointer, "program ended");
+    }
+    result
+}
+
+// Run the children of `ast`, subroutine calls keep the `start` of the whole run
+// so `timeout` covers them too
+fn run_nodes<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &mut Interpreter<T, M>,
+    ast: &Node,
+    #[cfg(not(feature = "no_std"))] start: Instant,
+) -> Result<(), InterpreterError> {
+    // nodes being run with the index of their next child and the iterations so
+    // far, loops are pushed when entered so nesting does not grow the call stack
+    let mut stack: Vec<(&Node, usize, u64)> = vec![(ast, 0, 0)];
+    while let Some(&(node, child, iterations)) = stack.last() {
+        let top = stack.len() - 1;
+        match node.childrens.get(child) {
+            Some(next) => {
+                stack[top].1 += 1;
+                let index = memory_index(interpreter);
+                match next.node_type {
+                    Loop if !interpreter.memory.get(index).is_zero() => {
+                        check_interrupt(interpreter)?;
+                        #[cfg(not(feature = "no_std"))]
+                        check_timeout(interpreter, start)?;
+                        check_loop_iterations(interpreter, next, 1)?;
+                        stack.push((next, 0, 1));
+                    }
+                    Operator if next.instruction == DefineSubroutine => {
+                        let id = interpreter.pointer as u32;
+                        interpreter.subroutines.insert(id, next.clone());
+                        interpreter.last_subroutine = Some(id);
+                    }
+                    Operator if next.instruction == CallSubroutine => call_subroutine(
+                        interpreter,
+                        next.source_pos,
+                        #[cfg(not(feature = "no_std"))]
+                        start,
+                    )?,
+                    Operator => execute_instruction(interpreter, &next.instruction, index)?,
+                    _ => {}
+                }
+            }
+            // the children of `ast` run once, a loop starts over while its cell is not zero
+            None if top > 0 && !interpreter.memory.get(memory_index(interpreter)).is_zero() => {
+                count_step(interpreter)?;
+                check_interrupt(interpreter)?;
+                #[cfg(not(feature = "no_std"))]
+                check_timeout(interpreter, start)?;
+                check_loop_iterations(interpreter, node, iterations + 1)?;
+                stack[top] = (node, 0, iterations + 1);
+            }
+            None => {
+                stack.pop();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `run_program` on cells of the given size, the tape is turned back into bytes
+/// afterwards keeping the low byte of every cell
+pub fn run_with_cell_size(
+    interpreter: &mut Interpreter,
+    ast: &Node,
+    cell_size: CellSize,
+) -> Result<(), InterpreterError> {
+    match cell_size {
+        CellSize::Bits8 => run_program(interpreter, ast),
+        CellSize::Signed8 => run_with_cells::<i8>(interpreter, ast),
+        CellSize::Saturating8 => run_with_cells::<SaturatingU8>(interpreter, ast),
+        CellSize::Bits16 => run_with_cells::<u16>(interpreter, ast),
+        CellSize::Bits32 => run_with_cells::<u32>(interpreter, ast),
+    }
+}
+
+fn run_with_cells<T: CellArithmetic>(
+    interpreter: &mut Interpreter,
+    ast: &Node,
+) -> Result<(), InterpreterError> {
+    let mut wide = core::mem::replace(interpreter, interpreter_init(0)).with_cells::<T>();
+    let result = run_program(&mut wide, ast);
+    *interpreter = wide.with_cells();
+    result
+}
+
+/// `run_program` on a `SparseTape` of `tape_size` cells, which only takes memory for
+/// the cells that are not zero. The tape is given back as `run_on_tape` does
+#[cfg(not(feature = "no_std"))]
+pub fn run_with_sparse_tape(
+    interpreter: &mut Interpreter,
+    ast: &Node,
+) -> (SparseTape, Result<(), InterpreterError>) {
+    let tape = SparseTape::new(interpreter.tape_size);
+    run_on_tape(interpreter, ast, tape)
+}
+
+/// `run_program` on `tape`, which gets the cells in `memory` first and gives
+/// `tape_size` its length. The cells stay on the tape, given back with the result
+/// of the run, and `memory` is left empty so a huge tape is never copied into it
+pub fn run_on_tape<M: Tape>(
+    interpreter: &mut Interpreter,
+    ast: &Node,
+    tape: M,
+) -> (M, Result<(), InterpreterError>) {
+    let mut outside = core::mem::replace(interpreter, interpreter_init(0)).with_tape(tape);
+    outside.tape_size = outside.memory.len();
+    let result = run_program(&mut outside, ast);
+    let (inside, tape) = outside.swap_memory(VecDeque::new());
+    *interpreter = inside;
+    (tape, result)
+}
+
+/// Subroutine calls that may be nested before ':' fails, every call takes a few kilobytes
+/// of the thread stack in debug builds
+pub const MAX_CALL_DEPTH: usize = 256;
+
+// Run the most recently defined subroutine for the ':' at `offset`
+fn call_subroutine<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &mut Interpreter<T, M>,
+    offset: usize,
+    #[cfg(not(feature = "no_std"))] start: Instant,
+) -> Result<(), InterpreterError> {
+    count_step(interpreter)?;
+    if interpreter.call_stack.len() >= MAX_CALL_DEPTH {
+        return Err(InterpreterError::CallStackOverflow { offset });
+    }
+    // the body is copied out so it may define or call subroutines itself
+    let body = interpreter
+        .last_subroutine
+        .and_then(|id| interpreter.subroutines.get(&id))
+        .cloned()
+        .ok_or(InterpreterError::UndefinedSubroutine { offset })?;
+    interpreter.call_stack.push(offset);
+    #[cfg(not(feature = "no_std"))]
+    let result = run_nodes(interpreter, &body, start);
+    #[cfg(feature = "no_std")]
+    let result = run_nodes(interpreter, &body);
+    interpreter.call_stack.pop();
+    result
+}
+
+// Fail when a loop is about to start iteration number `iterations` past `max_loop_iters`
+fn check_loop_iterations<T: CellArithmetic, M: Tape<T>>(
+    interpreter: &Interpreter<T, M>,
+    node: &Node,
+    iterations: u64,
+) -> Result<(), InterpreterError> {
+    match interpreter.max_loop_iters {
+        Some(limit) if iterations > limit => Err(InterpreterError::LoopIterationLimitExceeded {
+            offset: node.source_pos,
+        }),
+        _ => Ok(()),
+    }
+}
+
+// The text in the style of the current cell when colors are enabled, plain
+// without the standard library
+fn highlight(text: &str, enabled: bool) -> String {
+    #[cfg(not(feature = "no_std"))]
+    return color::paint(text, &color::pointer_style(), enabled);
+    #[cfg(feature = "no_std")]
+    {
+        let _ = enabled;
+        text.to_string()
+    }
+}
+
+/// Describe pointer and non-zero cells of the tape
+pub fn tape_summary(interpreter: &Interpreter) -> String {
+    let cells: Vec<String> = interpreter
+        .nonzero_cells()
+        .map(|(index, value)| {
+            let cell = format!("cell[{}]={}", interpreter.cell_address(index), value);
+            if index == memory_index(interpreter) {
+                highlight(&cell, interpreter.color)
+            } else {
+                cell
+            }
+        })
+        .collect();
+    format!(
+        "pointer={} nonzero cells: {}",
+        interpreter.pointer,
+        if cells.is_empty() {
+            "none".to_string()
+        } else {
+            cells.join(" ")
+        }
+    )
+}
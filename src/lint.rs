@@ -0,0 +1,185 @@
+//! Warnings about programs that are valid but probably not what was meant.
+
+use crate::visit::{walk, walk_children, Visitor};
+use crate::Command::{self, *};
+use crate::Node;
+use std::fmt;
+
+/// Two adjacent commands that undo each other, such as `><`
+#[derive(Debug, PartialEq, Eq)]
+pub struct CancelingPair {
+    pub offset: usize, // source offset of the first command
+    pub pair: &'static str,
+}
+
+impl fmt::Display for CancelingPair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "warning at offset {}: adjacent canceling instructions '{}'",
+            self.offset, self.pair
+        )
+    }
+}
+
+fn canceling_pair(first: Command, second: Command) -> Option<&'static str> {
+    match (first, second) {
+        (IncDP, DecDP) => Some("><"),
+        (DecDP, IncDP) => Some("<>"),
+        (IncByte, DecByte) => Some("+-"),
+        (DecByte, IncByte) => Some("-+"),
+        _ => None,
+    }
+}
+
+/// Find the adjacent canceling pairs in lexed commands, comments between them are ignored
+pub fn find_canceling_pairs(commands: &[(Command, usize)]) -> Vec<CancelingPair> {
+    commands
+        .windows(2)
+        .filter_map(|window| {
+            let (first, offset) = window[0];
+            canceling_pair(first, window[1].0).map(|pair| CancelingPair { offset, pair })
+        })
+        .collect()
+}
+
+/// A loop whose body can never change the cell it tests
+#[derive(Debug, PartialEq, Eq)]
+pub struct InfiniteLoopWarning {
+    pub offset: usize, // source offset of the '['
+}
+
+impl fmt::Display for InfiniteLoopWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "warning: potentially infinite loop at source offset {}",
+            self.offset
+        )
+    }
+}
+
+// Looks for a node that can change a cell or move the pointer to another one
+#[derive(Default)]
+struct CellChanges {
+    found: bool,
+}
+
+impl Visitor for CellChanges {
+    fn visit_operator(&mut self, _node: &Node, cmd: Command) {
+        self.found |= !matches!(cmd, OutByte | Default | DebugPrint | DefineSubroutine);
+    }
+}
+
+#[derive(Default)]
+struct InfiniteLoops {
+    warnings: Vec<InfiniteLoopWarning>,
+}
+
+impl Visitor for InfiniteLoops {
+    fn visit_loop(&mut self, node: &Node) {
+        let mut changes = CellChanges::default();
+        walk_children(node, &mut changes);
+        if !changes.found {
+            self.warnings.push(InfiniteLoopWarning {
+                offset: node.source_pos,
+            });
+        }
+        walk_children(node, self);
+    }
+}
+
+/// Find the loops that run forever once entered: their body only prints, so the
+/// cell they test stays non-zero
+pub fn analyze_termination(node: &Node) -> Vec<InfiniteLoopWarning> {
+    let mut loops = InfiniteLoops::default();
+    walk(node, &mut loops);
+    loops.warnings
+}
+
+#[derive(Default)]
+struct LoopDepth {
+    depth: usize,
+    max_depth: usize,
+}
+
+impl Visitor for LoopDepth {
+    fn visit_loop(&mut self, node: &Node) {
+        self.depth += 1;
+        self.max_depth = self.max_depth.max(self.depth);
+        walk_children(node, self);
+        self.depth -= 1;
+    }
+}
+
+#[derive(Default)]
+struct Subroutines {
+    found: bool,
+}
+
+impl Visitor for Subroutines {
+    fn visit_operator(&mut self, _node: &Node, cmd: Command) {
+        self.found |= matches!(cmd, DefineSubroutine | CallSubroutine);
+    }
+}
+
+/// Whether the program defines or calls subroutines, only `run_program` runs them
+pub fn uses_subroutines(node: &Node) -> bool {
+    let mut subroutines = Subroutines::default();
+    walk(node, &mut subroutines);
+    subroutines.found
+}
+
+#[derive(Default)]
+struct Output {
+    found: bool,
+}
+
+impl Visitor for Output {
+    fn visit_operator(&mut self, node: &Node, cmd: Command) {
+        match cmd {
+            OutByte => self.found = true,
+            // a subroutine body runs wherever it is called
+            DefineSubroutine => walk_children(node, self),
+            _ => {}
+        }
+    }
+}
+
+/// Whether any `.` is in the tree, a program without one runs silently
+pub fn has_output(node: &Node) -> bool {
+    let mut output = Output::default();
+    walk(node, &mut output);
+    output.found
+}
+
+#[derive(Default)]
+struct NodeCount {
+    count: usize,
+}
+
+impl Visitor for NodeCount {
+    fn visit_loop(&mut self, node: &Node) {
+        self.count += 1;
+        walk_children(node, self);
+    }
+
+    fn visit_operator(&mut self, node: &Node, _cmd: Command) {
+        self.count += 1;
+        walk_children(node, self);
+    }
+}
+
+/// Number of loops and operators in the tree, the root left out
+pub fn count_nodes(node: &Node) -> usize {
+    let mut count = NodeCount::default();
+    walk(node, &mut count);
+    count.count
+}
+
+/// Deepest nesting of loops in the tree, 0 without loops
+pub fn max_loop_depth(node: &Node) -> usize {
+    let mut depth = LoopDepth::default();
+    walk(node, &mut depth);
+    depth.max_depth
+}
@@ -1,66 +1,715 @@
-use crate::Command::*;
-use crate::NodeType::*;
+#[cfg(feature = "jit")]
+use brainfuck::jit::jit_compile;
+use brainfuck::{
+    arena::{run_arena, Arena},
+    bytecode::{compile_bytecode, deserialize_bytecode, serialize_bytecode},
+    cell::CellSize,
+    codegen::{
+        c::{emit_c, emit_c_with_source_map},
+        elf::emit_elf,
+        llvm::emit_llvm_ir,
+        nasm::emit_nasm,
+        qbe::emit_qbe,
+        rust::{emit_rust, emit_rust_with_source_map},
+        wasm::emit_wasm,
+        wat::emit_wat,
+    },
+    color::{error_style, paint, warning_style, ColorChoice},
+    debugger::{debug_run_program, DebugError, Journal},
+    diagnostic::{interpreter_diagnostic, render_diagnostic, Diagnostic},
+    equiv::{check_equivalence, escape_bytes, DEFAULT_INPUTS, EQUIV_STEPS},
+    error::BrainfuckError,
+    format::Formatter,
+    interpreter_init, lexical_analysis_str, lexical_analysis_with_extensions,
+    lint::{
+        analyze_termination, count_nodes, find_canceling_pairs, has_output, max_loop_depth,
+        uses_subroutines,
+    },
+    metrics::compute_metrics,
+    ook::lex_ook,
+    optimizer::{minify, optimizer_pipeline},
+    parse_escapes,
+    profile::{profile_run_program, Coverage, Hotspots, InstructionProfile, TapeHeatmap},
+    replay::{Replay, ReplayLog},
+    run_on_tape, run_program, run_with_cell_size, run_with_sparse_tape,
+    snapshot::{restore_checkpoint, restore_snapshot, serialize_snapshot},
+    syntax_analysis,
+    tape::MmapTape,
+    tape_summary,
+    trace::{trace_run_program, TraceContext, TraceError},
+    try_syntax_analysis,
+    vm::{flatten_commands, Checkpoints, FlatInterpreter, FlatVm},
+    Command, EofBehavior, InputSource, Interpreter, InterpreterError, LexerIterator, Node,
+    OutputFormatter, OverflowMode, Progress, MAX_MEMORY_SIZE, MEMORY_SIZE,
+};
 use clap::{App, Arg, ArgMatches};
 use std::{
-    fs::File,
-    io::{stdin, BufReader, Error, Read},
+    cell::{Cell, OnceCell},
+    collections::HashSet,
+    ffi::OsString,
+    fmt,
+    fs::{self, File},
+    io::{self, stderr, stdin, BufRead, BufReader, BufWriter, Error, IsTerminal, Read, Write},
+    path::Path,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
 
-const MEMORY_SIZE: i32 = 30000;
+/// Name of the checkpoint saved in --checkpoint-path, each one replaces the last
+const CHECKPOINT_FILE: &str = "checkpoint.bfck";
+
+/// Default of --max-loop-depth
+const MAX_LOOP_DEPTH: usize = 500;
+
+/// Command line initialization, the options of --config fill in the ones not given
+fn cli_init() -> Result<ArgMatches, Box<dyn std::error::Error>> {
+    let cli = cli_app().get_matches();
+    let config = match cli.value_of("config") {
+        Some(path) => load_config(&read_file_to_string(path)?)
+            .map_err(|e| format!("invalid config file {}: {}", path, e))?,
+        None => return Ok(cli),
+    };
+    let args: Vec<OsString> = std::env::args_os()
+        .chain(config.args(&cli).into_iter().map(OsString::from))
+        .collect();
+    Ok(cli_app().get_matches_from(args))
+}
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum Command {
-    Default,      // Nothing will happen
-    IncDP,        // '>' -> Increment the data pointer (to point to the next cell to the right).
-    DecDP,        // '<' -> Decrement the data pointer (to point to the next cell to the left).
-    IncByte,      // '+' -> Increment (increase by one) the byte at the data pointer.
-    DecByte,      // '-' -> Decrement (decrease by one) the byte at the data pointer.
-    OutByte,      // '.' -> Output the byte at the data pointer.
-    InByte, // ',' -> Accept one byte of input, storing its value in the byte at the data pointer.
-    JumpForward, // '[' -> If the byte at the data pointer is zero, then instead of moving the instruction pointer forward to the next command, jump it forward to the command after the matching ] command.
-    JumpBackward, // ']' -> If the byte at the data pointer is nonzero, then instead of moving the instruction pointer forward to the next command, jump it back to the command after the matching [ command.
+/// Options read from --config, named like the command line options with
+/// underscores. The options choosing what to run stay on the command line
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[derive(Default)]
+struct Config {
+    memory_size: Option<usize>,
+    overflow: Option<String>,
+    eof: Option<String>,
+    input_string: Option<String>,
+    output: Option<String>,
+    output_format: Option<String>,
+    warn_nops: Option<bool>,
+    warn_infinite_loops: Option<bool>,
+    warn_no_output: Option<bool>,
+    warn: Option<bool>,
+    optimize: Option<bool>,
+    max_loop_depth: Option<usize>,
+    verbose: Option<bool>,
+    dialect: Option<String>,
+    allow_extensions: Option<bool>,
+    engine: Option<String>,
+    cell_size: Option<String>,
+    saturate: Option<bool>,
+    overflow_check: Option<bool>,
+    sparse_tape: Option<bool>,
+    mmap_tape: Option<String>,
+    persistent_tape: Option<String>,
+    checkpoint_interval: Option<u64>,
+    checkpoint_path: Option<String>,
+    dynamic_tape: Option<bool>,
+    bidirectional_tape: Option<bool>,
+    max_tape_size: Option<usize>,
+    max_steps: Option<u64>,
+    progress: Option<bool>,
+    progress_interval: Option<u64>,
+    indent: Option<String>,
+    max_line_width: Option<usize>,
+    timeout: Option<f64>,
+    max_loop_iters: Option<u64>,
+    dump_on_interrupt: Option<bool>,
+    exit_from_tape: Option<bool>,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum NodeType {
-    Program,
-    Loop,
-    Operator,
+impl Config {
+    /// Command line arguments for the options of the file missing from `cli`
+    fn args(&self, cli: &ArgMatches) -> Vec<String> {
+        let values = [
+            ("memory-size", self.memory_size.map(|size| size.to_string())),
+            ("overflow", self.overflow.clone()),
+            ("eof", self.eof.clone()),
+            ("input-string", self.input_string.clone()),
+            ("output", self.output.clone()),
+            ("output-format", self.output_format.clone()),
+            (
+                "max-loop-depth",
+                self.max_loop_depth.map(|depth| depth.to_string()),
+            ),
+            ("dialect", self.dialect.clone()),
+            ("engine", self.engine.clone()),
+            ("cell-size", self.cell_size.clone()),
+            ("mmap-tape", self.mmap_tape.clone()),
+            ("persistent-tape", self.persistent_tape.clone()),
+            (
+                "checkpoint-interval",
+                self.checkpoint_interval
+                    .map(|millions| millions.to_string()),
+            ),
+            ("checkpoint-path", self.checkpoint_path.clone()),
+            (
+                "max-tape-size",
+                self.max_tape_size.map(|size| size.to_string()),
+            ),
+            ("max-steps", self.max_steps.map(|steps| steps.to_string())),
+            (
+                "progress-interval",
+                self.progress_interval.map(|steps| steps.to_string()),
+            ),
+            ("indent", self.indent.clone()),
+            (
+                "max-line-width",
+                self.max_line_width.map(|width| width.to_string()),
+            ),
+            ("timeout", self.timeout.map(|seconds| seconds.to_string())),
+            (
+                "max-loop-iters",
+                self.max_loop_iters.map(|iters| iters.to_string()),
+            ),
+        ];
+        let flags = [
+            ("warn-nops", self.warn_nops),
+            ("warn-infinite-loops", self.warn_infinite_loops),
+            ("warn-no-output", self.warn_no_output),
+            ("warn", self.warn),
+            ("optimize", self.optimize),
+            ("verbose", self.verbose),
+            ("allow-extensions", self.allow_extensions),
+            ("dynamic-tape", self.dynamic_tape),
+            ("bidirectional-tape", self.bidirectional_tape),
+            ("progress", self.progress),
+            ("saturate", self.saturate),
+            ("overflow-check", self.overflow_check),
+            ("sparse-tape", self.sparse_tape),
+            ("dump-on-interrupt", self.dump_on_interrupt),
+            ("exit-from-tape", self.exit_from_tape),
+        ];
+        let values = values.iter().filter_map(|(name, value)| {
+            let value = value.as_ref()?;
+            Some((name, format!("--{}={}", name, value)))
+        });
+        let flags = flags
+            .iter()
+            .filter(|(_, set)| *set == Some(true))
+            .map(|(name, _)| (name, format!("--{}", name)));
+        values
+            .chain(flags)
+            .filter(|(name, _)| cli.occurrences_of(name) == 0)
+            .map(|(_, arg)| arg)
+            .collect()
+    }
 }
 
-struct Interpreter {
-    memory: Vec<u8>,
-    pointer: i32,
+#[cfg(feature = "serde")]
+fn load_config(text: &str) -> Result<Config, Box<dyn std::error::Error>> {
+    Ok(toml::from_str(text)?)
 }
-struct Node {
-    node_type: NodeType,
-    instruction: Command,
-    childrens: Vec<Node>,
+
+#[cfg(not(feature = "serde"))]
+fn load_config(_: &str) -> Result<Config, Box<dyn std::error::Error>> {
+    Err("--config needs the interpreter built with the serde feature".into())
 }
 
-/// Command line initialization
-fn cli_init() -> ArgMatches {
+fn cli_app() -> App<'static> {
     App::new("BrainF*ck Interpreter")
         .version("1.0")
         .author("Samuel G.")
         .about("Does awesome things")
         .arg(
             Arg::new("file")
-                .about("sets the file to use")
+                .help("sets the file to use, several files are joined with newlines into one program")
                 .takes_value(true)
                 .short('f')
                 .long("file")
-                .required(true),
+                .multiple_values(true)
+                .multiple_occurrences(true)
+                .required_unless_present_any(["repl", "run-bytecode", "load-ast"]),
+        )
+        .arg(
+            Arg::new("config")
+                .help("reads default options from a TOML file, options on the command line win")
+                .takes_value(true)
+                .long("config"),
+        )
+        .arg(
+            Arg::new("repl")
+                .help("starts an interactive session keeping the tape between lines")
+                .long("repl")
+                .conflicts_with("file"),
+        )
+        .arg(
+            Arg::new("overflow")
+                .help("what happens when the pointer leaves the tape")
+                .takes_value(true)
+                .long("overflow")
+                .possible_values(["wrap", "error"])
+                .default_value("wrap"),
+        )
+        .arg(
+            Arg::new("eof")
+                .help("what input stores in the cell at end of file")
+                .takes_value(true)
+                .long("eof")
+                .possible_values(["0", "255", "-1", "unchanged"])
+                .allow_hyphen_values(true)
+                .default_value("0"),
+        )
+        .arg(
+            Arg::new("input-string")
+                .help("reads input from this string instead of stdin, escapes like \\n and \\x41 work")
+                .takes_value(true)
+                .long("input-string")
+                .allow_hyphen_values(true),
+        )
+        .arg(
+            Arg::new("output")
+                .help("writes the program output to this file instead of stdout")
+                .takes_value(true)
+                .short('o')
+                .long("output"),
+        )
+        .arg(
+            Arg::new("output-format")
+                .help("prints output bytes as they are or as decimal, hex or binary numbers")
+                .takes_value(true)
+                .long("output-format")
+                .possible_values(["char", "decimal", "hex", "binary"])
+                .default_value("char"),
+        )
+        .arg(
+            Arg::new("warn-nops")
+                .help("warns about adjacent instructions that cancel each other, such as '><'")
+                .long("warn-nops"),
+        )
+        .arg(
+            Arg::new("warn-infinite-loops")
+                .help("warns about loops whose body can never change the cell they test")
+                .long("warn-infinite-loops"),
+        )
+        .arg(
+            Arg::new("warn-no-output")
+                .help("warns about programs without any '.'")
+                .long("warn-no-output"),
+        )
+        .arg(
+            Arg::new("warn")
+                .help("turns on every warning")
+                .long("warn"),
+        )
+        .arg(
+            Arg::new("optimize")
+                .help("optimizes the program before running it")
+                .long("optimize"),
+        )
+        .arg(
+            Arg::new("max-loop-depth")
+                .help("deepest loop nesting a program may have [default: 500]")
+                .takes_value(true)
+                .long("max-loop-depth"),
+        )
+        .arg(
+            Arg::new("color")
+                .help("colors errors, warnings and the current cell in tape dumps")
+                .takes_value(true)
+                .long("color")
+                .possible_values(["always", "auto", "never"])
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::new("verbose")
+                .help("prints the loop nesting depth and what the optimizer changed to stderr")
+                .short('v')
+                .long("verbose"),
+        )
+        .arg(
+            Arg::new("emit")
+                .help("prints the program translated to another language instead of running it, jit compiles it to native code and runs that")
+                .takes_value(true)
+                .long("emit")
+                .possible_values(["c", "llvm-ir", "qbe", "wat", "wasm", "rust", "asm-nasm", "elf", "bytecode", "bf", "jit"])
+                .conflicts_with("repl"),
+        )
+        .arg(
+            Arg::new("source-map")
+                .help("writes a source map from the lines of --emit=c or --emit=rust back to the BF source")
+                .takes_value(true)
+                .value_name("PATH")
+                .long("source-map")
+                .requires("emit"),
+        )
+        .arg(
+            Arg::new("metrics")
+                .help("prints instruction counts, loops, nesting depth and I/O of the program, after --optimize, to stderr")
+                .long("metrics")
+                .conflicts_with("repl"),
+        )
+        .arg(
+            Arg::new("check-equiv")
+                .help("runs the program and this one on sample inputs and reports the first input they print different bytes for")
+                .takes_value(true)
+                .value_name("PATH")
+                .long("check-equiv")
+                .conflicts_with_all(&["repl", "emit", "minify", "format", "check-format", "dump-ast", "dry-run"]),
+        )
+        .arg(
+            Arg::new("equiv-input")
+                .help("an input for --check-equiv instead of the defaults \"\", \\x00, \\xff and hello\\n, escapes like \\n and \\x41 work")
+                .takes_value(true)
+                .long("equiv-input")
+                .multiple_occurrences(true)
+                .requires("check-equiv"),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .help("parses, lints and optimizes the program and prints what it found instead of running it")
+                .long("dry-run")
+                .conflicts_with_all(&["repl", "emit", "minify", "format", "check-format", "dump-ast"]),
+        )
+        .arg(
+            Arg::new("optimize-only")
+                .help("prints the program after the optimizer as BF instead of running it")
+                .long("optimize-only")
+                .conflicts_with_all(&["repl", "emit", "dump-ast", "minify", "format", "check-format", "dry-run", "check-equiv"]),
+        )
+        .arg(
+            Arg::new("minify")
+                .help("prints the program without canceling instructions and dead loops instead of running it")
+                .long("minify")
+                .conflicts_with_all(&["repl", "emit", "dump-ast", "optimize"]),
+        )
+        .arg(
+            Arg::new("format")
+                .help("prints the program formatted with one block per line instead of running it")
+                .long("format")
+                .conflicts_with_all(&["repl", "emit", "dump-ast", "optimize", "minify"]),
+        )
+        .arg(
+            Arg::new("check-format")
+                .help("exits with an error when the program is not formatted like --format prints it")
+                .long("check-format")
+                .conflicts_with_all(&["repl", "format"]),
+        )
+        .arg(
+            Arg::new("indent")
+                .help("indentation of --format, a number of spaces or 'tab'")
+                .takes_value(true)
+                .long("indent")
+                .default_value("4"),
+        )
+        .arg(
+            Arg::new("max-line-width")
+                .help("longest line of --format, longer blocks are split over several lines")
+                .takes_value(true)
+                .long("max-line-width")
+                .default_value("80"),
+        )
+        .arg(
+            Arg::new("dump-ast")
+                .help("prints the syntax tree, optimized with --optimize, instead of running it")
+                .takes_value(true)
+                .long("dump-ast")
+                .possible_values(["json"])
+                .conflicts_with_all(&["repl", "emit"]),
+        )
+        .arg(
+            Arg::new("run-bytecode")
+                .help("runs a program previously saved with --emit=bytecode")
+                .takes_value(true)
+                .long("run-bytecode")
+                .conflicts_with_all(&["file", "repl"]),
+        )
+        .arg(
+            Arg::new("load-ast")
+                .help("runs a syntax tree previously saved with --dump-ast=json")
+                .takes_value(true)
+                .long("load-ast")
+                .conflicts_with_all(&["file", "repl", "run-bytecode"]),
+        )
+        .arg(
+            Arg::new("dialect")
+                .help("reads the file as plain Brainfuck or as Ook!")
+                .takes_value(true)
+                .long("dialect")
+                .possible_values(["bf", "ook"])
+                .default_value("bf"),
+        )
+        .arg(
+            Arg::new("allow-extensions")
+                .help("reads '#' in the file as a command printing the cells around the pointer to stderr '@' as a command stopping the program, '{' and '}' around a subroutine and ':' calling it")
+                .long("allow-extensions"),
+        )
+        .arg(
+            Arg::new("cell-size")
+                .help("bits of a tape cell, wider cells wrap at their own size and 8-signed cells hold -128 to 127")
+                .takes_value(true)
+                .long("cell-size")
+                .possible_values(["8", "8-signed", "16", "32"])
+                .default_value("8"),
+        )
+        .arg(
+            Arg::new("overflow-check")
+                .help("stops the program with an error when a cell would wrap, tree engine only")
+                .long("overflow-check")
+                .conflicts_with("saturate"),
+        )
+        .arg(
+            Arg::new("saturate")
+                .help("stops cells at 0 and 255 instead of wrapping")
+                .long("saturate")
+                .conflicts_with("cell-size"),
+        )
+        .arg(
+            Arg::new("sparse-tape")
+                .help("keeps only the cells that are not zero in memory, tree engine only")
+                .long("sparse-tape")
+                .conflicts_with_all(&[
+                    "cell-size",
+                    "saturate",
+                    "load-snapshot",
+                    "save-snapshot",
+                    "dump-on-interrupt",
+                    "exit-from-tape",
+                ]),
+        )
+        .arg(
+            Arg::new("mmap-tape")
+                .help("maps a file as the tape, created full of zeros when it does not exist, tree engine only")
+                .takes_value(true)
+                .value_name("path")
+                .long("mmap-tape")
+                .conflicts_with_all(&[
+                    "sparse-tape",
+                    "cell-size",
+                    "saturate",
+                    "load-snapshot",
+                    "save-snapshot",
+                    "dynamic-tape",
+                    "bidirectional-tape",
+                    "dump-on-interrupt",
+                    "exit-from-tape",
+                ]),
+        )
+        .arg(
+            Arg::new("persistent-tape")
+                .help("maps the tape a previous run left in a file, created full of zeros when it does not exist, tree engine only")
+                .takes_value(true)
+                .value_name("path")
+                .long("persistent-tape")
+                .conflicts_with_all(&[
+                    "sparse-tape",
+                    "mmap-tape",
+                    "cell-size",
+                    "saturate",
+                    "load-snapshot",
+                    "save-snapshot",
+                    "dynamic-tape",
+                    "bidirectional-tape",
+                    "dump-on-interrupt",
+                    "exit-from-tape",
+                ]),
+        )
+        .arg(
+            Arg::new("engine")
+                .help("selects the tree walker, the bytecode virtual machine, the flat command interpreter, the flat interpreter dispatching through a table of handlers or the walker of the tree in an arena")
+                .takes_value(true)
+                .long("engine")
+                .possible_values(["tree", "vm", "flat", "dispatch", "arena"])
+                .default_value("tree"),
+        )
+        .arg(
+            Arg::new("profile")
+                .help("counts executed instructions and prints a report to stderr")
+                .long("profile")
+                .conflicts_with_all(&["repl", "emit"]),
+        )
+        .arg(
+            Arg::new("heatmap")
+                .help("prints the most accessed cells of the tape to stderr")
+                .long("heatmap")
+                .conflicts_with_all(&["repl", "emit"]),
+        )
+        .arg(
+            Arg::new("hotspot")
+                .help("prints the 10 instructions of the source run most often to stderr")
+                .long("hotspot")
+                .conflicts_with_all(&["repl", "emit"]),
+        )
+        .arg(
+            Arg::new("coverage")
+                .help("prints how many instructions of the source ran and how many never did to stderr")
+                .long("coverage")
+                .conflicts_with_all(&["repl", "emit", "optimize"]),
+        )
+        .arg(
+            Arg::new("coverage-annotate")
+                .help("writes the source to a file with a ^ under every instruction that never ran")
+                .takes_value(true)
+                .value_name("PATH")
+                .long("coverage-annotate")
+                .requires("coverage"),
+        )
+        .arg(
+            Arg::new("debug")
+                .help("steps through the program with commands read from the terminal")
+                .long("debug")
+                .conflicts_with_all(&["repl", "emit", "profile", "heatmap", "coverage", "hotspot"]),
+        )
+        .arg(
+            Arg::new("break")
+                .help("pauses the debugger at the command at this source offset")
+                .takes_value(true)
+                .long("break")
+                .multiple_occurrences(true)
+                .conflicts_with_all(&["repl", "emit", "profile", "heatmap", "coverage", "hotspot"]),
+        )
+        .arg(
+            Arg::new("watch")
+                .help("pauses the debugger whenever the cell at this index changes")
+                .takes_value(true)
+                .long("watch")
+                .multiple_occurrences(true)
+                .conflicts_with_all(&["repl", "emit", "profile", "heatmap", "coverage", "hotspot"]),
+        )
+        .arg(
+            Arg::new("reversible")
+                .help("records the changes of the program so the debugger command u can undo them")
+                .long("reversible")
+                .conflicts_with_all(&["repl", "emit", "profile", "heatmap", "coverage", "hotspot"]),
+        )
+        .arg(
+            Arg::new("undo-limit")
+                .help("number of changes --reversible keeps, the oldest are forgotten first")
+                .takes_value(true)
+                .long("undo-limit")
+                .default_value("1000000"),
+        )
+        .arg(
+            Arg::new("trace")
+                .help("prints a trace record for every executed instruction to stderr")
+                .long("trace")
+                .conflicts_with_all(&["repl", "emit", "profile", "heatmap", "coverage", "hotspot", "debug"]),
+        )
+        .arg(
+            Arg::new("load-snapshot")
+                .help("starts from the tape and pointer saved in a snapshot")
+                .takes_value(true)
+                .long("load-snapshot")
+                .conflicts_with("emit"),
+        )
+        .arg(
+            Arg::new("save-snapshot")
+                .help("saves the final tape and pointer to a snapshot")
+                .takes_value(true)
+                .long("save-snapshot")
+                .conflicts_with("emit"),
+        )
+        .arg(
+            Arg::new("record")
+                .help("logs every byte read and written with the step it happened at")
+                .takes_value(true)
+                .value_name("path")
+                .long("record")
+                .conflicts_with("replay"),
+        )
+        .arg(
+            Arg::new("replay")
+                .help("reads the input from a log of --record and checks the output against it")
+                .takes_value(true)
+                .value_name("path")
+                .long("replay")
+                .conflicts_with("input-string"),
+        )
+        .arg(
+            Arg::new("checkpoint-interval")
+                .help("saves a checkpoint every N million commands, flat engine only")
+                .takes_value(true)
+                .value_name("N")
+                .long("checkpoint-interval"),
+        )
+        .arg(
+            Arg::new("checkpoint-path")
+                .help("directory the checkpoints are saved in, the current one by default")
+                .takes_value(true)
+                .value_name("dir")
+                .long("checkpoint-path")
+                .requires("checkpoint-interval"),
+        )
+        .arg(
+            Arg::new("restart-from-checkpoint")
+                .help("continues a run of the same program from a checkpoint, flat engine only")
+                .takes_value(true)
+                .value_name("path")
+                .long("restart-from-checkpoint")
+                .conflicts_with("load-snapshot"),
+        )
+        .arg(
+            Arg::new("dynamic-tape")
+                .help("grows the tape when the pointer moves right of it instead of wrapping")
+                .long("dynamic-tape")
+                .conflicts_with("emit"),
+        )
+        .arg(
+            Arg::new("bidirectional-tape")
+                .help("grows the tape on whichever side the pointer leaves it")
+                .long("bidirectional-tape")
+                .conflicts_with_all(&["emit", "dynamic-tape"]),
+        )
+        .arg(
+            Arg::new("max-tape-size")
+                .help("number of cells a dynamic or bidirectional tape may grow to")
+                .takes_value(true)
+                .long("max-tape-size"),
+        )
+        .arg(
+            Arg::new("max-steps")
+                .help("stops the program after this many instructions and loop iterations")
+                .takes_value(true)
+                .long("max-steps"),
+        )
+        .arg(
+            Arg::new("progress")
+                .help("prints the step, pointer, current cell and elapsed time every 10 million steps when stderr is a terminal")
+                .long("progress"),
+        )
+        .arg(
+            Arg::new("progress-interval")
+                .help("number of steps between two lines of --progress")
+                .takes_value(true)
+                .long("progress-interval")
+                .default_value("10000000"),
+        )
+        .arg(
+            Arg::new("timeout")
+                .help("stops the program after this many seconds")
+                .takes_value(true)
+                .long("timeout")
+                .conflicts_with("debug"),
+        )
+        .arg(
+            Arg::new("max-loop-iters")
+                .help("stops the program when a loop runs this many times in a row, tree and arena engines only")
+                .takes_value(true)
+                .long("max-loop-iters")
+                .conflicts_with_all(&["run-bytecode", "debug", "trace", "profile", "heatmap", "coverage", "hotspot"]),
+        )
+        .arg(
+            Arg::new("dump-on-interrupt")
+                .help("prints the tape to stderr when Ctrl+C interrupts the program")
+                .long("dump-on-interrupt"),
+        )
+        .arg(
+            Arg::new("exit-from-tape")
+                .help("exits with the value of cell 0 once the program ended, up to 125")
+                .long("exit-from-tape")
+                .conflicts_with_all(&["emit", "dump-ast"]),
+        )
+        .arg(
+            Arg::new("memory-size")
+                .help("sets the number of cells on the tape [default: 30000]")
+                .takes_value(true)
+                .long("memory-size"),
         )
-        .get_matches()
-}
-
-/// Interpreter initialization
-fn interpreter_init() -> Interpreter {
-    Interpreter {
-        memory: vec![0; MEMORY_SIZE as usize],
-        pointer: 0,
-    }
 }
 
 fn read_file_to_string(path: &str) -> Result<String, Error> {
@@ -73,161 +722,963 @@ fn read_file_to_string(path: &str) -> Result<String, Error> {
     Ok(result_string)
 }
 
-/// read file with buffer and transform chars to operators
-fn lexical_analysis(commands: String) -> Result<Vec<Command>, String> {
-    let mut result: Vec<Command> = Vec::new();
-    commands.chars().for_each(|c| match c {
-        '>' => result.push(IncDP),
-        '<' => result.push(DecDP),
-        '+' => result.push(IncByte),
-        '-' => result.push(DecByte),
-        '.' => result.push(OutByte),
-        ',' => result.push(InByte),
-        '[' => result.push(JumpForward),
-        ']' => result.push(JumpBackward),
-        _ => {}
-    });
-    Ok(result)
-}
-
-/// Generates abstract syntactic tree
-fn create_ast(node: &mut Node, commands: &[Command], index: &mut usize) {
-    while *index < commands.len() {
-        // println!("index - while: {}", index);
-        match commands.get(*index) {
-            Some(cmd) => match cmd {
-                JumpForward => {
-                    let mut new_node = Node {
-                        node_type: Loop,
-                        instruction: JumpForward,
-                        childrens: Vec::new(),
-                    };
-                    *index += 1;
-                    create_ast(&mut new_node, commands, index);
-                    node.childrens.push(new_node);
-                }
-                JumpBackward => {
-                    return;
-                }
-                _ => {
-                    node.childrens.push(Node {
-                        node_type: Operator,
-                        instruction: *cmd,
-                        childrens: Vec::new(),
-                    });
-                }
-            },
-            None => return,
-        }
-        *index += 1;
-    }
-}
-
-/// print tree just to make sure
-// fn print_ast(program: &Node, depth: i32) {
-//     if depth == 0 {
-//         println!("\nPrinting ast\n");
-//     }
-//     program.childrens.iter().for_each(|node: &Node| {
-//         println!(
-//             "{}{:?}  ---  {:?}",
-//             " ".repeat((depth * 5) as usize),
-//             node.node_type,
-//             node.instruction
-//         );
-//         if node.childrens.len() > 0 {
-//             print_ast(node, depth + 1);
-//         }
-//     });
-// }
-
-/// provide syntactic analysis
-fn syntax_analysis(commands: Vec<Command>) -> Result<Node, String> {
-    let mut stack: Vec<Command> = Vec::new();
-    let filtered = commands
+/// The files of --file, lexed while they are read. Their text, joined by newlines,
+/// is only read when something needs more than the commands, like a diagnostic
+struct SourceFiles<'a> {
+    paths: Vec<&'a str>,
+    texts: OnceCell<Vec<String>>,
+    joined: OnceCell<String>,
+}
+
+impl<'a> SourceFiles<'a> {
+    fn new(paths: Vec<&'a str>) -> Self {
+        SourceFiles {
+            paths,
+            texts: OnceCell::new(),
+            joined: OnceCell::new(),
+        }
+    }
+
+    /// The commands of every file, paired with their offset in the joined text
+    fn lex(&self, extensions: bool) -> Result<Vec<(Command, usize)>, BrainfuckError> {
+        let mut reader: Box<dyn Read> = Box::new(io::empty());
+        for (index, path) in self.paths.iter().enumerate() {
+            // the newline between two files is part of the joined text
+            let separator: &'static [u8] = if index == 0 { b"" } else { b"\n" };
+            reader = Box::new(reader.chain(separator).chain(File::open(path)?));
+        }
+        LexerIterator::new(reader, extensions).collect()
+    }
+
+    /// The text of each file, in the order they were given
+    fn texts(&self) -> Result<&[String], Error> {
+        if self.texts.get().is_none() {
+            let texts = self
+                .paths
+                .iter()
+                .map(|path| read_file_to_string(path))
+                .collect::<Result<Vec<String>, _>>()?;
+            let _ = self.texts.set(texts);
+        }
+        Ok(self.texts.get().unwrap())
+    }
+
+    /// The text of the files joined by newlines, the offsets of the commands point into it
+    fn text(&self) -> Result<&str, Error> {
+        if self.joined.get().is_none() {
+            let joined = self.texts()?.join("\n");
+            let _ = self.joined.set(joined);
+        }
+        Ok(self.joined.get().unwrap())
+    }
+}
+
+/// parse and validate the requested tape size
+fn parse_memory_size(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(size) if (1..=MAX_MEMORY_SIZE).contains(&size) => Ok(size),
+        _ => Err(format!(
+            "invalid memory size '{}': expected a number from 1 to {}",
+            value, MAX_MEMORY_SIZE
+        )),
+    }
+}
+
+/// Stop deeply nested programs before running them recursively overflows the stack
+fn check_loop_depth(cli: &ArgMatches, ast: &Node) -> Result<(), Box<dyn std::error::Error>> {
+    let limit = match cli.value_of("max-loop-depth") {
+        Some(value) => value
+            .parse::<usize>()
+            .map_err(|_| format!("invalid loop depth '{}': expected a number", value))?,
+        None => MAX_LOOP_DEPTH,
+    };
+    let depth = max_loop_depth(ast);
+    if cli.is_present("verbose") {
+        eprintln!("loop nesting depth {}", depth);
+    }
+    if depth > limit {
+        return Err(format!("loop nesting depth {} exceeds limit", depth).into());
+    }
+    Ok(())
+}
+
+// Whether the program goes to run_program, the only engine knowing every extension
+fn runs_plain_tree(cli: &ArgMatches) -> bool {
+    cli.value_of("engine") == Some("tree")
+        && cli.value_of("emit").is_none_or(|target| target == "bf")
+        && ![
+            "debug",
+            "break",
+            "watch",
+            "reversible",
+            "trace",
+            "profile",
+            "heatmap",
+            "coverage",
+            "hotspot",
+        ]
         .iter()
-        .filter(|cmd| -> bool { **cmd == JumpForward || **cmd == JumpBackward });
+        .any(|flag| cli.is_present(flag))
+}
+
+/// Refuse the engines and backends that would skip the subroutines of the program
+fn check_subroutines(cli: &ArgMatches, ast: &Node) -> Result<(), Box<dyn std::error::Error>> {
+    if !runs_plain_tree(cli) && uses_subroutines(ast) {
+        return Err("subroutines only run on the plain tree engine".into());
+    }
+    Ok(())
+}
+
+/// The cells of --cell-size and --saturate, cells other than bytes only run on
+/// the plain tree engine and the optimizer only knows cells wrapping at 256
+fn cell_size(cli: &ArgMatches) -> Result<CellSize, Box<dyn std::error::Error>> {
+    let (flag, cell_size) = match cli.value_of("cell-size").unwrap_or_default() {
+        _ if cli.is_present("saturate") => ("--saturate", CellSize::Saturating8),
+        "16" => ("--cell-size=16", CellSize::Bits16),
+        "32" => ("--cell-size=32", CellSize::Bits32),
+        "8-signed" => ("--cell-size=8-signed", CellSize::Signed8),
+        _ => return Ok(CellSize::Bits8),
+    };
+    let others = ["repl", "run-bytecode", "check-equiv"];
+    if !runs_plain_tree(cli) || others.iter().any(|flag| cli.is_present(flag)) {
+        return Err(format!("{} only runs on the plain tree engine", flag).into());
+    }
+    if cell_size != CellSize::Signed8 && cli.is_present("optimize") {
+        return Err(format!(
+            "{} does not run with --optimize, it folds cell changes modulo 256",
+            flag
+        )
+        .into());
+    }
+    Ok(cell_size)
+}
 
-    for cmd in filtered {
-        match *cmd {
-            JumpForward => stack.push(JumpForward),
-            _ => match stack.pop() {
-                Some(_) => {}
-                None => return Err("missing bracket".to_string()),
-            },
+/// Run on the plain tree engine with the cells and the tape chosen on the command line,
+/// the outer error is a bad choice and the inner one the error of the run
+fn run_tree(
+    cli: &ArgMatches,
+    interpreter: &mut Interpreter,
+    ast: &Node,
+) -> Result<Result<(), InterpreterError>, Box<dyn std::error::Error>> {
+    if cli.is_present("sparse-tape") {
+        return Ok(run_with_sparse_tape(interpreter, ast).1);
+    }
+    if let Some(path) = cli.value_of("mmap-tape") {
+        let tape = MmapTape::open(path, interpreter.tape_size)?;
+        return Ok(run_on_tape(interpreter, ast, tape).1);
+    }
+    if let Some(path) = cli.value_of("persistent-tape") {
+        let tape = MmapTape::open_persistent(path, interpreter.tape_size)?;
+        let (tape, result) = run_on_tape(interpreter, ast, tape);
+        if result.is_ok() {
+            // the cells reach the file before the next run starts from them
+            tape.flush()?;
         }
+        return Ok(result);
     }
-    if !stack.is_empty() {
-        return Err("missing bracket".to_string());
+    Ok(run_with_cell_size(interpreter, ast, cell_size(cli)?))
+}
+
+/// parse a --timeout in seconds, fractions like 0.5 work
+fn parse_timeout(value: &str) -> Result<Duration, String> {
+    match value.parse::<f64>() {
+        Ok(seconds) if seconds.is_finite() && seconds > 0.0 => Ok(Duration::from_secs_f64(seconds)),
+        _ => Err(format!(
+            "invalid timeout '{}': expected a positive number of seconds",
+            value
+        )),
     }
+}
 
-    let mut program: Node = Node {
-        node_type: Program,
-        instruction: Default,
-        childrens: Vec::new(),
+/// The checkpoints of --checkpoint-interval, saved to CHECKPOINT_FILE in --checkpoint-path
+fn checkpoints(cli: &ArgMatches) -> Result<Option<Checkpoints>, String> {
+    let value = match cli.value_of("checkpoint-interval") {
+        Some(value) => value,
+        None => return Ok(None),
     };
+    let millions = value
+        .parse::<u64>()
+        .ok()
+        .filter(|&millions| millions > 0)
+        .ok_or_else(|| {
+            format!(
+                "invalid checkpoint interval '{}': expected a positive number of million commands",
+                value
+            )
+        })?;
+    let directory = Path::new(cli.value_of("checkpoint-path").unwrap_or("."));
+    Ok(Some(Checkpoints::new(
+        millions.saturating_mul(1_000_000),
+        directory.join(CHECKPOINT_FILE),
+    )))
+}
+
+/// parse a breakpoint source offset
+fn parse_offset(value: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .map_err(|_| format!("invalid breakpoint '{}': expected a source offset", value))
+}
+
+/// parse a watched cell index, it has to be on the tape
+fn parse_cell(value: &str, memory_size: usize) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(cell) if cell < memory_size => Ok(cell),
+        _ => Err(format!(
+            "invalid watchpoint '{}': expected a cell from 0 to {}",
+            value,
+            memory_size - 1
+        )),
+    }
+}
+
+/// Width of the terminal for charts, from $COLUMNS or 80 when unknown
+fn terminal_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .unwrap_or(80)
+}
+
+/// Interactive session, every line is run against the same tape
+fn run_repl(interpreter: &mut Interpreter) -> Result<(), Error> {
+    let mut line = String::new();
+    loop {
+        eprint!("bf> ");
+        line.clear();
+        if stdin().lock().read_line(&mut line)? == 0 || line.trim() == "quit" {
+            break;
+        }
+        let result = syntax_analysis(lexical_analysis_str(&line), &line)
+            .and_then(|ast| Ok(run_program(interpreter, &ast)?));
+        interpreter.output.flush()?;
+        match result {
+            // every syntax error already starts with "error:"
+            Err(BrainfuckError::Syntax(errors)) => errors
+                .iter()
+                .for_each(|error| print_diagnostic(&error.to_string())),
+            Err(e) => print_diagnostic(&format!("error: {}", e)),
+            Ok(()) => {}
+        }
+    }
+    eprintln!();
+    eprintln!("{}", tape_summary(interpreter));
+    Ok(())
+}
+
+// Set from --color once the options are parsed, read when printing diagnostics
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Print a message to stderr, a leading "error" or "warning" is colored
+fn print_diagnostic(message: &str) {
+    let color = COLOR.load(Ordering::Relaxed);
+    let styles = [("error", error_style()), ("warning", warning_style())];
+    match styles.iter().find(|(word, _)| message.starts_with(word)) {
+        Some((word, style)) => eprintln!("{}{}", paint(word, style, color), &message[word.len()..]),
+        None => eprintln!("{}", message),
+    }
+}
+
+/// Print a diagnostic under the line of source it points at
+fn print_rendered(source: &str, diagnostic: &Diagnostic) {
+    print_diagnostic(render_diagnostic(source, diagnostic).trim_end_matches('\n'));
+}
 
-    let mut pos: usize = 0;
-    create_ast(&mut program, &commands, &mut pos);
+/// An error of the program already shown under its line of source
+#[derive(Debug)]
+struct Shown(InterpreterError);
 
-    Ok(program)
+impl fmt::Display for Shown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
 }
 
-// Read one byte from user's input
-fn read_input() -> u8 {
-    let mut buffer = [0; 1];
-    if stdin().read_exact(&mut buffer).is_ok() {
-        return buffer[0];
+impl std::error::Error for Shown {}
+
+fn main() {
+    if let Err(e) = run() {
+        match interpreter_error(e.as_ref()) {
+            _ if e.is::<Shown>() => {}
+            Some(InterpreterError::StepLimitExceeded { .. }) => eprintln!("halted: {}", e),
+            _ => print_diagnostic(&format!("error: {}", e)),
+        }
+        std::process::exit(1);
     }
-    0
 }
 
-// Change pointer or memory according on command and index
-fn execute_instruction(interpreter: &mut Interpreter, cmd: &Command, index: usize) {
-    match cmd {
-        IncDP => interpreter.pointer += 1,
-        DecDP => interpreter.pointer -= 1,
-        IncByte => interpreter.memory[index] += 1,
-        DecByte => interpreter.memory[index] -= 1,
-        InByte => interpreter.memory[index] = read_input(),
-        OutByte => print!("{}", interpreter.memory[index] as char),
-        _ => {}
+/// Print the `tracing` events of the library to stderr, filtered by `RUST_LOG`
+#[cfg(feature = "logging")]
+fn init_logging() {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(stderr)
+        .init();
+}
+
+#[cfg(not(feature = "logging"))]
+fn init_logging() {}
+
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+    init_logging();
+    let cli = cli_init()?;
+    let memory_size = cli
+        .value_of("memory-size")
+        .map_or(Ok(MEMORY_SIZE), parse_memory_size)?;
+    let outside_tape = ["sparse-tape", "mmap-tape", "persistent-tape"]
+        .iter()
+        .find(|flag| cli.is_present(flag));
+    if let Some(flag) = outside_tape {
+        if !runs_plain_tree(&cli) || cli.is_present("repl") || cli.is_present("run-bytecode") {
+            return Err(format!("--{} only runs on the plain tree engine", flag).into());
+        }
+    }
+    // every other engine and backend would run with byte cells instead
+    cell_size(&cli)?;
+    // the cells of a sparse or mapped tape only ever live on that tape
+    let mut interpreter = interpreter_init(if outside_tape.is_some() {
+        0
+    } else {
+        memory_size
+    });
+    interpreter.tape_size = memory_size;
+    let choice = match cli.value_of("color") {
+        Some("always") => ColorChoice::Always,
+        Some("never") => ColorChoice::Never,
+        _ => ColorChoice::Auto,
+    };
+    // every colored output goes to stderr, NO_COLOR turns off the automatic colors
+    interpreter.color =
+        choice.enabled(stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none());
+    COLOR.store(interpreter.color, Ordering::Relaxed);
+    for flag in ["checkpoint-interval", "restart-from-checkpoint"].iter() {
+        if cli.is_present(flag) && cli.value_of("engine") != Some("flat") {
+            return Err(format!("--{} needs --engine=flat", flag).into());
+        }
+    }
+    if cli.is_present("overflow-check") {
+        if cli.value_of("engine") != Some("tree") {
+            return Err("--overflow-check needs --engine=tree".into());
+        }
+        if cli.is_present("optimize") {
+            // a run of 200 '+' becomes a single step of -56
+            return Err(
+                "--overflow-check does not run with --optimize, it folds cell changes modulo 256"
+                    .into(),
+            );
+        }
+        interpreter.overflow_check = true;
+    }
+    interpreter.overflow_mode = match cli.value_of("overflow") {
+        Some("error") => OverflowMode::Error,
+        _ => OverflowMode::Wrap,
+    };
+    if cli.is_present("dynamic-tape") || cli.is_present("bidirectional-tape") {
+        interpreter.overflow_mode = if cli.is_present("bidirectional-tape") {
+            OverflowMode::Bidirectional
+        } else {
+            OverflowMode::Grow
+        };
+        interpreter.max_tape_size = cli
+            .value_of("max-tape-size")
+            .map_or(Ok(MAX_MEMORY_SIZE), parse_memory_size)?;
+        if interpreter.max_tape_size < memory_size {
+            return Err(format!(
+                "memory size {} is larger than the maximum tape size {}",
+                memory_size, interpreter.max_tape_size
+            )
+            .into());
+        }
+    }
+    interpreter.eof_behavior = match cli.value_of("eof") {
+        Some("255") | Some("-1") => EofBehavior::Max,
+        Some("unchanged") => EofBehavior::Unchanged,
+        _ => EofBehavior::Zero,
+    };
+    interpreter.output_format = match cli.value_of("output-format") {
+        Some("decimal") => OutputFormatter::Decimal,
+        Some("hex") => OutputFormatter::Hex,
+        Some("binary") => OutputFormatter::Binary,
+        _ => OutputFormatter::Char,
+    };
+    if let Some(value) = cli.value_of("max-steps") {
+        interpreter.max_steps = Some(
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("invalid step limit '{}': expected a number", value))?,
+        );
+    }
+    if cli.is_present("progress") {
+        let value = cli.value_of("progress-interval").unwrap_or_default();
+        let interval = value
+            .parse::<u64>()
+            .ok()
+            .filter(|&steps| steps > 0)
+            .ok_or_else(|| {
+                format!(
+                    "invalid progress interval '{}': expected a positive number",
+                    value
+                )
+            })?;
+        // the line is overwritten with '\r', that only works on a terminal
+        if stderr().is_terminal() {
+            interpreter.progress = Some(Progress {
+                interval,
+                start: Instant::now(),
+                shown: false,
+            });
+        }
+    }
+    if let Some(value) = cli.value_of("max-loop-iters") {
+        interpreter.max_loop_iters = Some(value.parse::<u64>().map_err(|_| {
+            format!(
+                "invalid loop iteration limit '{}': expected a number",
+                value
+            )
+        })?);
+    }
+    if let Some(value) = cli.value_of("timeout") {
+        interpreter.timeout = Some(parse_timeout(value)?);
+    }
+    if let Some(text) = cli.value_of("input-string") {
+        interpreter.input = InputSource::Buffer(parse_escapes(text)?, 0);
+    }
+
+    if cli.is_present("record") {
+        interpreter.replay = Some(Replay::Record(ReplayLog::default()));
+    }
+    if let Some(path) = cli.value_of("replay") {
+        let log = ReplayLog::deserialize(&fs::read(path)?)?;
+        interpreter.replay = Some(Replay::play(log));
+    }
+
+    if let Some(path) = cli.value_of("output") {
+        interpreter.output = Box::new(BufWriter::new(File::create(path)?));
+    }
+
+    if let Some(path) = cli.value_of("load-snapshot") {
+        restore_snapshot(&mut interpreter, &fs::read(path)?)?;
+    }
+
+    if !cli.is_present("repl") {
+        // Ctrl+C still kills the REPL waiting for its next line
+        let should_stop = Arc::clone(&interpreter.should_stop);
+        ctrlc::set_handler(move || should_stop.store(true, Ordering::Relaxed))?;
+    }
+
+    let result = if cli.is_present("repl") {
+        run_repl(&mut interpreter).map_err(|e| e.into())
+    } else if let Some(path) = cli.value_of("run-bytecode") {
+        let program = deserialize_bytecode(&fs::read(path)?)?;
+        FlatVm::new(program, &mut interpreter)?
+            .run()
+            .map_err(|e| e.into())
+    } else if let Some(path) = cli.value_of("load-ast") {
+        let ast = load_ast(&read_file_to_string(path)?)?;
+        check_loop_depth(&cli, &ast)?;
+        run_tree(&cli, &mut interpreter, &ast)?.map_err(|e| e.into())
+    } else {
+        run_file(&cli, &mut interpreter)
     };
+    interpreter.output.flush()?;
+    if let (Some(path), Some(Replay::Record(log))) = (cli.value_of("record"), &interpreter.replay) {
+        fs::write(path, log.serialize())?;
+    }
+    // the tape a program stopped with is saved whether or not it failed
+    let ran = match &result {
+        Ok(()) => true,
+        Err(e) => interpreter_error(e.as_ref()).is_some(),
+    };
+    if let (true, Some(path)) = (ran, cli.value_of("save-snapshot")) {
+        fs::write(path, serialize_snapshot(&interpreter)?)?;
+    }
+    if matches!(&interpreter.progress, Some(progress) if progress.shown) {
+        eprintln!();
+    }
+    if let Err(e) = &result {
+        if is_interrupt(e.as_ref()) {
+            eprintln!("execution interrupted by user");
+            if cli.is_present("dump-on-interrupt") {
+                eprintln!("{}", tape_summary(&interpreter));
+            }
+            std::process::exit(130);
+        }
+    }
+    // '@' stops the program without failing it
+    let halted = Some(&InterpreterError::HaltInstruction);
+    match result {
+        Err(e) if interpreter_error(e.as_ref()) == halted => {}
+        result => result?,
+    }
+    if let Some(replay) = &interpreter.replay {
+        replay.finish()?;
+    }
+
+    if cli.is_present("exit-from-tape") {
+        // codes above 125 are left to the shell and signals
+        std::process::exit(i32::from(interpreter.get_cell(0)?).clamp(0, 125));
+    }
+    Ok(())
+}
+
+/// The interpreter error behind the error, whichever engine ran the program
+fn interpreter_error<'a>(
+    error: &'a (dyn std::error::Error + 'static),
+) -> Option<&'a InterpreterError> {
+    if let Some(TraceError::Interpreter(e)) = error.downcast_ref::<TraceError>() {
+        return Some(e);
+    }
+    if let Some(DebugError::Interpreter { error, .. }) = error.downcast_ref::<DebugError>() {
+        return Some(error);
+    }
+    if let Some(Shown(error)) = error.downcast_ref::<Shown>() {
+        return Some(error);
+    }
+    error.downcast_ref::<InterpreterError>()
+}
+
+/// Whether the error comes from Ctrl+C
+fn is_interrupt(error: &(dyn std::error::Error + 'static)) -> bool {
+    interpreter_error(error) == Some(&InterpreterError::Interrupted)
+}
+
+/// Write the tree as JSON for --dump-ast
+#[cfg(feature = "serde")]
+fn dump_ast(ast: &Node, interpreter: &mut Interpreter) -> Result<(), Box<dyn std::error::Error>> {
+    serde_json::to_writer(&mut interpreter.output, ast)?;
+    writeln!(interpreter.output)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+fn dump_ast(_: &Node, _: &mut Interpreter) -> Result<(), Box<dyn std::error::Error>> {
+    Err("--dump-ast needs the interpreter built with the serde feature".into())
+}
+
+/// Compile the program to native code for --emit=jit and run it
+#[cfg(feature = "jit")]
+fn run_jit(ast: &Node, interpreter: &mut Interpreter) -> Result<(), Box<dyn std::error::Error>> {
+    if !matches!(interpreter.overflow_mode, OverflowMode::Wrap) {
+        return Err("--emit=jit only runs on a tape that wraps around".into());
+    }
+    if interpreter.max_steps.is_some() {
+        return Err("--emit=jit does not count steps, it cannot stop at --max-steps".into());
+    }
+    let function = jit_compile(&compile_bytecode(ast), interpreter.tape_size)?;
+    Ok(function.run(interpreter)?)
+}
+
+#[cfg(not(feature = "jit"))]
+fn run_jit(_: &Node, _: &mut Interpreter) -> Result<(), Box<dyn std::error::Error>> {
+    Err("--emit=jit needs the interpreter built with the jit feature".into())
+}
+
+/// Read a tree saved with --dump-ast for --load-ast
+#[cfg(feature = "serde")]
+fn load_ast(json: &str) -> Result<Node, Box<dyn std::error::Error>> {
+    let ast: Node = serde_json::from_str(json)?;
+    if ast.node_type != brainfuck::NodeType::Program {
+        return Err(format!(
+            "the root of the syntax tree is a {:?} node, expected Program",
+            ast.node_type
+        )
+        .into());
+    }
+    Ok(ast)
+}
+
+#[cfg(not(feature = "serde"))]
+fn load_ast(_: &str) -> Result<Node, Box<dyn std::error::Error>> {
+    Err("--load-ast needs the interpreter built with the serde feature".into())
+}
+
+/// Whether the warning is turned on by its own flag or by --warn
+fn warns(cli: &ArgMatches, flag: &str) -> bool {
+    cli.is_present("warn") || cli.is_present(flag)
+}
+
+/// Print what --dry-run finds in the program: the size of its tree before and after
+/// the optimizer, its deepest loop and every warning, or its syntax errors. Returns
+/// whether the program is free of errors
+fn dry_run(
+    cli: &ArgMatches,
+    commands: Vec<(Command, usize)>,
+    source: &str,
+    overflow_mode: OverflowMode,
+    out: &mut dyn Write,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut warnings: Vec<String> = find_canceling_pairs(&commands)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let ast = match syntax_analysis(commands, source) {
+        Ok(ast) => ast,
+        Err(error) => {
+            for diagnostic in error.diagnostics(source) {
+                write!(out, "{}", render_diagnostic(source, &diagnostic))?;
+            }
+            return Ok(false);
+        }
+    };
+    warnings.extend(analyze_termination(&ast).iter().map(ToString::to_string));
+    if !has_output(&ast) {
+        warnings.push("warning: program produces no output".to_string());
+    }
+    let eliminated = Rc::new(Cell::new(0));
+    let optimized = optimizer_pipeline(&eliminated, overflow_mode).run(ast.clone());
+    writeln!(out, "nodes: {}", count_nodes(&ast))?;
+    writeln!(out, "nodes after optimization: {}", count_nodes(&optimized))?;
+    writeln!(out, "max loop depth: {}", max_loop_depth(&ast))?;
+    for warning in warnings.iter() {
+        writeln!(out, "{}", warning)?;
+    }
+    if let Err(error) = check_loop_depth(cli, &ast) {
+        writeln!(out, "error: {}", error)?;
+        return Ok(false);
+    }
+    Ok(true)
 }
 
-fn run_program(interpreter: &mut Interpreter, ast: &Node) {
-    ast.childrens.iter().for_each(|node| {
-        // actual index in memory vector
-        let mut index: usize =
-            (((interpreter.pointer % MEMORY_SIZE) + MEMORY_SIZE) % MEMORY_SIZE) as usize;
-        match node.node_type {
-            Loop => {
-                while interpreter.memory[index] != 0 {
-                    run_program(interpreter, node);
-                    index = (((interpreter.pointer % MEMORY_SIZE) + MEMORY_SIZE) % MEMORY_SIZE)
-                        as usize;
+/// Run the program and the one at `path` on the inputs of --equiv-input and print
+/// whether they agree, exits with 1 on the first input they do not
+fn check_equiv(
+    cli: &ArgMatches,
+    ast: &Node,
+    path: &str,
+    interpreter: &mut Interpreter,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let source = read_file_to_string(path)?;
+    let commands = match cli.value_of("dialect") {
+        Some("ook") => lex_ook(&source)?,
+        _ => lexical_analysis_with_extensions(source.clone(), cli.is_present("allow-extensions")),
+    };
+    let other = match syntax_analysis(commands, &source) {
+        Ok(ast) => ast,
+        Err(error) => {
+            for diagnostic in error.diagnostics(&source) {
+                print_rendered(&source, &diagnostic);
+            }
+            std::process::exit(1);
+        }
+    };
+    let inputs = match cli.values_of("equiv-input") {
+        Some(values) => values
+            .map(parse_escapes)
+            .collect::<Result<Vec<Vec<u8>>, String>>()?,
+        None => DEFAULT_INPUTS.iter().map(|input| input.to_vec()).collect(),
+    };
+    let divergence = check_equivalence(
+        ast,
+        &other,
+        &inputs,
+        interpreter.tape_size,
+        interpreter.eof_behavior,
+        interpreter.max_steps.unwrap_or(EQUIV_STEPS),
+    );
+    let out = &mut interpreter.output;
+    match divergence {
+        None => writeln!(out, "programs are equivalent on all test inputs")?,
+        Some(divergence) => {
+            let name = cli.value_of("file").unwrap_or_default();
+            writeln!(
+                out,
+                "programs diverge on input {}",
+                escape_bytes(&divergence.input)
+            )?;
+            for (name, outcome) in [(name, &divergence.first), (path, &divergence.second)] {
+                write!(out, "{}: {}", name, escape_bytes(&outcome.output))?;
+                if let Some(error) = &outcome.error {
+                    write!(out, " (stopped: {})", error)?;
                 }
+                writeln!(out)?;
             }
-            Operator => execute_instruction(interpreter, &node.instruction, index),
-            _ => {}
+            out.flush()?;
+            std::process::exit(1);
         }
-    });
+    }
+    Ok(())
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // println!("Hello BrainFuck!");
+/// The formatter for --format and --check-format
+fn formatter(cli: &ArgMatches) -> Result<Formatter, Box<dyn std::error::Error>> {
+    let indent = match cli.value_of("indent").unwrap_or_default() {
+        "tab" => "\t".to_string(),
+        value => " ".repeat(value.parse::<usize>().map_err(|_| {
+            format!(
+                "invalid indent '{}': expected a number of spaces or 'tab'",
+                value
+            )
+        })?),
+    };
+    let value = cli.value_of("max-line-width").unwrap_or_default();
+    let max_line_width = value
+        .parse::<usize>()
+        .ok()
+        .filter(|&width| width > 0)
+        .ok_or_else(|| format!("invalid line width '{}': expected a positive number", value))?;
+    Ok(Formatter {
+        indent,
+        max_line_width,
+    })
+}
 
-    let cli = cli_init();
-    // commands from file
-    match cli.value_of("file") {
-        Some(f) => {
-            let loaded_string: String = read_file_to_string(f)?;
-            let commands: Vec<Command> = lexical_analysis(loaded_string)?;
-            let program_ast = syntax_analysis(commands)?;
-            let mut interpreter = interpreter_init();
-            run_program(&mut interpreter, &program_ast);
+/// Run or translate the program from --file
+fn run_file(
+    cli: &ArgMatches,
+    interpreter: &mut Interpreter,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let memory_size = interpreter.tape_size;
+    // commands from the files, in the order they were given
+    match cli.values_of("file") {
+        Some(files) => {
+            let files = SourceFiles::new(files.collect());
+            let commands: Vec<(Command, usize)> = match cli.value_of("dialect") {
+                Some("ook") => lex_ook(files.text()?)?,
+                _ => files.lex(cli.is_present("allow-extensions"))?,
+            };
+            if cli.is_present("dry-run") {
+                let valid = dry_run(
+                    cli,
+                    commands,
+                    files.text()?,
+                    interpreter.overflow_mode,
+                    &mut interpreter.output,
+                )?;
+                interpreter.output.flush()?;
+                if !valid {
+                    std::process::exit(1);
+                }
+                return Ok(());
+            }
+            if warns(cli, "warn-nops") {
+                for warning in find_canceling_pairs(&commands) {
+                    print_diagnostic(&warning.to_string());
+                }
+            }
+            let mut program_ast = match try_syntax_analysis(&commands) {
+                Some(ast) => ast,
+                None => {
+                    // the text is only needed to show the line of each unmatched bracket
+                    let source = files.text()?;
+                    if let Err(error) = syntax_analysis(commands, source) {
+                        for diagnostic in error.diagnostics(source) {
+                            print_rendered(source, &diagnostic);
+                        }
+                    }
+                    std::process::exit(1);
+                }
+            };
+            check_loop_depth(cli, &program_ast)?;
+            check_subroutines(cli, &program_ast)?;
+            if warns(cli, "warn-infinite-loops") {
+                for warning in analyze_termination(&program_ast) {
+                    print_diagnostic(&warning.to_string());
+                }
+            }
+            if warns(cli, "warn-no-output") && !has_output(&program_ast) {
+                print_diagnostic("warning: program produces no output");
+            }
+            if let Some(path) = cli.value_of("check-equiv") {
+                return check_equiv(cli, &program_ast, path, interpreter);
+            }
+            if cli.is_present("format") || cli.is_present("check-format") {
+                let formatted = formatter(cli)?.format(&program_ast);
+                if cli.is_present("format") {
+                    interpreter.output.write_all(formatted.as_bytes())?;
+                } else if formatted != files.text()? {
+                    print_diagnostic("error: program is not formatted, see --format");
+                    std::process::exit(1);
+                }
+                return Ok(());
+            }
+            if cli.is_present("optimize") || cli.is_present("optimize-only") {
+                let eliminated = Rc::new(Cell::new(0));
+                program_ast =
+                    optimizer_pipeline(&eliminated, interpreter.overflow_mode).run(program_ast);
+                if cli.is_present("verbose") {
+                    eprintln!("eliminated {} dead nodes", eliminated.get());
+                }
+            }
+            if cli.is_present("metrics") {
+                eprint!("{}", compute_metrics(&program_ast).report());
+            }
+            if cli.is_present("optimize-only") {
+                writeln!(interpreter.output, "{}", program_ast)?;
+                return Ok(());
+            }
+            if cli.is_present("minify") {
+                writeln!(
+                    interpreter.output,
+                    "{}",
+                    minify(program_ast, interpreter.overflow_mode)
+                )?;
+                return Ok(());
+            }
+            if let Some(target) = cli.value_of("emit") {
+                if let Some(path) = cli.value_of("source-map") {
+                    let (code, map) = match target {
+                        "c" => emit_c_with_source_map(&program_ast, memory_size),
+                        "rust" => emit_rust_with_source_map(&program_ast, memory_size),
+                        _ => return Err("--source-map needs --emit=c or --emit=rust".into()),
+                    };
+                    let names = cli.values_of("file").unwrap_or_default();
+                    let sources: Vec<(&str, &str)> = names
+                        .zip(files.texts()?.iter().map(String::as_str))
+                        .collect();
+                    let file = cli.value_of("output").unwrap_or_default();
+                    fs::write(path, map.to_json(file, &sources))?;
+                    interpreter.output.write_all(code.as_bytes())?;
+                    return Ok(());
+                }
+                if target == "jit" {
+                    return run_jit(&program_ast, interpreter);
+                }
+                let code = match target {
+                    "c" => emit_c(&program_ast, memory_size).into_bytes(),
+                    "llvm-ir" => emit_llvm_ir(&program_ast, memory_size).into_bytes(),
+                    "qbe" => emit_qbe(&program_ast, memory_size).into_bytes(),
+                    "wat" => emit_wat(&program_ast, memory_size).into_bytes(),
+                    "wasm" => emit_wasm(&program_ast, memory_size),
+                    "rust" => emit_rust(&program_ast, memory_size).into_bytes(),
+                    "asm-nasm" => emit_nasm(&program_ast, memory_size).into_bytes(),
+                    "elf" => emit_elf(&program_ast, memory_size),
+                    "bytecode" => serialize_bytecode(&compile_bytecode(&program_ast)),
+                    "bf" => format!("{}\n", program_ast).into_bytes(),
+                    _ => unreachable!("unknown emit target {}", target),
+                };
+                interpreter.output.write_all(&code)?;
+                #[cfg(unix)]
+                if let ("elf", Some(path)) = (target, cli.value_of("output")) {
+                    use std::os::unix::fs::PermissionsExt;
+                    fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+                }
+                return Ok(());
+            }
+            if cli.is_present("dump-ast") {
+                return dump_ast(&program_ast, interpreter);
+            }
+            if cli.is_present("debug")
+                || cli.is_present("break")
+                || cli.is_present("watch")
+                || cli.is_present("reversible")
+            {
+                let breakpoints = match cli.values_of("break") {
+                    Some(offsets) => offsets
+                        .map(parse_offset)
+                        .collect::<Result<Vec<usize>, String>>()?,
+                    None => Vec::new(),
+                };
+                let watchpoints = match cli.values_of("watch") {
+                    Some(cells) => cells
+                        .map(|cell| parse_cell(cell, memory_size))
+                        .collect::<Result<HashSet<usize>, String>>()?,
+                    None => HashSet::new(),
+                };
+                let journal = if cli.is_present("reversible") {
+                    let value = cli.value_of("undo-limit").unwrap_or_default();
+                    let limit = value.parse::<usize>().map_err(|_| {
+                        format!("invalid undo limit '{}': expected a number", value)
+                    })?;
+                    Some(Journal::new(Some(limit)))
+                } else {
+                    None
+                };
+                let result = debug_run_program(
+                    interpreter,
+                    &program_ast,
+                    &breakpoints,
+                    &watchpoints,
+                    journal,
+                );
+                interpreter.output.flush()?;
+                return Ok(result?);
+            }
+            if cli.is_present("trace") {
+                let mut out = BufWriter::new(stderr().lock());
+                let mut trace = TraceContext {
+                    step: 0,
+                    out: &mut out,
+                };
+                let result = trace_run_program(interpreter, &program_ast, &mut trace);
+                out.flush()?;
+                interpreter.output.flush()?;
+                return Ok(result?);
+            }
+            if ["profile", "heatmap", "coverage", "hotspot"]
+                .iter()
+                .any(|flag| cli.is_present(flag))
+            {
+                let mut profile = InstructionProfile::default();
+                if cli.is_present("heatmap") {
+                    profile.heatmap = Some(TapeHeatmap::new(memory_size));
+                }
+                if cli.is_present("coverage") {
+                    profile.coverage = Some(Coverage::new(files.text()?.len()));
+                }
+                if cli.is_present("hotspot") {
+                    profile.hotspots = Some(Hotspots::new(files.text()?.len()));
+                }
+                let start = Instant::now();
+                let result = profile_run_program(interpreter, &program_ast, &mut profile);
+                profile.elapsed = start.elapsed();
+                interpreter.output.flush()?;
+                if cli.is_present("profile") {
+                    eprint!("{}", profile.report());
+                }
+                if let Some(heatmap) = &profile.heatmap {
+                    eprint!("{}", heatmap.report(terminal_width()));
+                }
+                if let Some(hotspots) = &profile.hotspots {
+                    eprint!("{}", hotspots.report(files.text()?));
+                }
+                if let Some(coverage) = &profile.coverage {
+                    eprint!("{}", coverage.report(&program_ast));
+                    if let Some(path) = cli.value_of("coverage-annotate") {
+                        fs::write(path, coverage.annotate(files.text()?, &program_ast))?;
+                    }
+                }
+                return Ok(result?);
+            }
+            match cli.value_of("engine") {
+                Some("vm" | "flat" | "dispatch") if interpreter.max_loop_iters.is_some() => {
+                    return Err("--max-loop-iters needs --engine=tree".into());
+                }
+                Some("vm") => FlatVm::new(compile_bytecode(&program_ast), interpreter)?.run()?,
+                Some("dispatch") => {
+                    FlatInterpreter::new(flatten_commands(&program_ast), interpreter)?
+                        .run_dispatch()?
+                }
+                Some("arena") => {
+                    let (arena, root) = Arena::from_node(&program_ast);
+                    run_arena(interpreter, &arena, root)?
+                }
+                Some("flat") => {
+                    let mut flat =
+                        FlatInterpreter::new(flatten_commands(&program_ast), interpreter)?;
+                    if let Some(path) = cli.value_of("restart-from-checkpoint") {
+                        flat.pc = restore_checkpoint(flat.interpreter, &fs::read(path)?)?;
+                        if flat.pc > flat.commands.len() {
+                            return Err("the checkpoint was saved by another program".into());
+                        }
+                    }
+                    flat.checkpoints = checkpoints(cli)?;
+                    flat.run()?
+                }
+                _ => {
+                    if let Err(error) = run_tree(cli, interpreter, &program_ast)? {
+                        // errors at a command are shown under their line of source
+                        let diagnostic = match interpreter_diagnostic(&error) {
+                            Some(diagnostic) => diagnostic,
+                            None => return Err(error.into()),
+                        };
+                        interpreter.output.flush()?;
+                        print_rendered(files.text()?, &diagnostic);
+                        return Err(Shown(error).into());
+                    }
+                }
+            }
         }
-        None => panic!("Something went wrong"),
+        None => return Err(BrainfuckError::NoProgram.into()),
     }
     Ok(())
 }
@@ -0,0 +1,82 @@
+//! Static metrics of a program, counted from its tree without running it.
+
+use crate::profile::{command_index, INSTRUCTION_NAMES};
+use crate::visit::{walk, walk_children, Visitor};
+use crate::Command::{self, *};
+use crate::Node;
+
+/// What the tree of a program is made of
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ProgramMetrics {
+    pub counts: [usize; 20], // operators indexed by the `Command` discriminant
+    pub loops: usize,
+    pub max_depth: usize,
+    pub io: usize, // `.` and `,` operators
+}
+
+impl ProgramMetrics {
+    /// Number of operators, loops left out
+    pub fn operators(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Loops per operator, 0 without operators
+    pub fn loop_ratio(&self) -> f64 {
+        match self.operators() {
+            0 => 0.0,
+            operators => self.loops as f64 / operators as f64,
+        }
+    }
+
+    /// Table of the operators by kind followed by the other metrics
+    pub fn report(&self) -> String {
+        let mut report = format!("{:<18}{:>10}\n", "instruction", "count");
+        for (name, count) in INSTRUCTION_NAMES.iter().zip(self.counts.iter()) {
+            if *count != 0 {
+                report.push_str(&format!("{:<18}{:>10}\n", name, count));
+            }
+        }
+        report.push_str(&format!("{:<18}{:>10}\n", "operators", self.operators()));
+        report.push_str(&format!("{:<18}{:>10}\n", "loops", self.loops));
+        report.push_str(&format!("{:<18}{:>10}\n", "max loop depth", self.max_depth));
+        report.push_str(&format!("{:<18}{:>10}\n", "I/O instructions", self.io));
+        report.push_str(&format!(
+            "{:<18}{:>10.2}\n",
+            "loops/operators",
+            self.loop_ratio()
+        ));
+        report
+    }
+}
+
+#[derive(Default)]
+struct MetricsVisitor {
+    metrics: ProgramMetrics,
+    depth: usize,
+}
+
+impl Visitor for MetricsVisitor {
+    fn visit_loop(&mut self, node: &Node) {
+        self.metrics.loops += 1;
+        self.depth += 1;
+        self.metrics.max_depth = self.metrics.max_depth.max(self.depth);
+        walk_children(node, self);
+        self.depth -= 1;
+    }
+
+    fn visit_operator(&mut self, node: &Node, cmd: Command) {
+        self.metrics.counts[command_index(&cmd)] += 1;
+        if let OutByte | InByte = cmd {
+            self.metrics.io += 1;
+        }
+        // the body of a subroutine definition
+        walk_children(node, self);
+    }
+}
+
+/// Metrics of the tree, run it through the optimizer first to measure what runs
+pub fn compute_metrics(node: &Node) -> ProgramMetrics {
+    let mut visitor = MetricsVisitor::default();
+    walk(node, &mut visitor);
+    visitor.metrics
+}
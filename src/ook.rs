@@ -0,0 +1,101 @@
+//! Ook! dialect: every command is a pair of the tokens `Ook.`, `Ook?` and `Ook!`.
+
+use crate::Command::{self, *};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OokError {
+    UnknownToken { token: String, offset: usize },
+    UnknownPair { offset: usize }, // `Ook? Ook?` is not a command
+    UnpairedToken { offset: usize },
+}
+
+impl fmt::Display for OokError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OokError::UnknownToken { token, offset } => {
+                write!(
+                    f,
+                    "unknown Ook! token '{}' at byte offset {}",
+                    token, offset
+                )
+            }
+            OokError::UnknownPair { offset } => {
+                write!(f, "'Ook? Ook?' at byte offset {} is not a command", offset)
+            }
+            OokError::UnpairedToken { offset } => {
+                write!(f, "Ook! token at byte offset {} has no second half", offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OokError {}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Token {
+    Dot,
+    Question,
+    Bang,
+}
+
+fn command(first: Token, second: Token) -> Option<Command> {
+    use Token::*;
+    match (first, second) {
+        (Dot, Question) => Some(IncDP),
+        (Question, Dot) => Some(DecDP),
+        (Dot, Dot) => Some(IncByte),
+        (Bang, Bang) => Some(DecByte),
+        (Bang, Dot) => Some(OutByte),
+        (Dot, Bang) => Some(InByte),
+        (Bang, Question) => Some(JumpForward),
+        (Question, Bang) => Some(JumpBackward),
+        (Question, Question) => None,
+    }
+}
+
+// Whitespace separated tokens with their byte offset
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, OokError> {
+    let mut tokens = Vec::new();
+    let mut rest = input;
+    while let Some(start) = rest.find(|c: char| !c.is_whitespace()) {
+        let end = rest[start..]
+            .find(char::is_whitespace)
+            .map_or(rest.len(), |length| start + length);
+        let offset = input.len() - rest.len() + start;
+        let token = match &rest[start..end] {
+            "Ook." => Token::Dot,
+            "Ook?" => Token::Question,
+            "Ook!" => Token::Bang,
+            token => {
+                return Err(OokError::UnknownToken {
+                    token: token.to_string(),
+                    offset,
+                })
+            }
+        };
+        tokens.push((token, offset));
+        rest = &rest[end..];
+    }
+    Ok(tokens)
+}
+
+/// Commands paired with the byte offset of their first token, the same shape as
+/// `lexical_analysis_str` so the result goes straight into `syntax_analysis`
+pub fn lex_ook(input: &str) -> Result<Vec<(Command, usize)>, OokError> {
+    tokenize(input)?
+        .chunks(2)
+        .map(|pair| match pair {
+            [(first, offset), (second, _)] => command(*first, *second)
+                .map(|cmd| (cmd, *offset))
+                .ok_or(OokError::UnknownPair { offset: *offset }),
+            [(_, offset)] => Err(OokError::UnpairedToken { offset: *offset }),
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Translate an Ook! program to the Brainfuck commands it stands for
+pub fn parse_ook(input: &str) -> Result<Vec<Command>, OokError> {
+    Ok(lex_ook(input)?.into_iter().map(|(cmd, _)| cmd).collect())
+}
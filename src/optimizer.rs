@@ -0,0 +1,340 @@
+//! Optimization passes rewriting the abstract syntactic tree before execution.
+
+use crate::Command::*;
+use crate::NodeType::*;
+use crate::{Command, Node, OverflowMode};
+use std::{cell::Cell, rc::Rc};
+
+/// A pass rewriting a whole tree
+pub trait Transform {
+    fn transform_node(&mut self, node: Node) -> Node;
+}
+
+/// Passes applied one after the other, in the order they were added
+#[derive(Default)]
+pub struct Pipeline {
+    pub passes: Vec<Box<dyn Transform>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Pipeline { passes: Vec::new() }
+    }
+
+    // a builder step rather than `std::ops::Add`
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, t: impl Transform + 'static) -> Self {
+        self.passes.push(Box::new(t));
+        self
+    }
+
+    pub fn run(self, ast: Node) -> Node {
+        log!(debug, passes = self.passes.len(), "running the optimizer");
+        self.passes
+            .into_iter()
+            .fold(ast, |ast, mut pass| pass.transform_node(ast))
+    }
+}
+
+/// `fold_moves` then `fold_adds`
+pub struct FoldPass;
+
+impl Transform for FoldPass {
+    fn transform_node(&mut self, mut node: Node) -> Node {
+        fold_moves(&mut node);
+        fold_adds(&mut node);
+        node
+    }
+}
+
+/// `fold_adds` alone, for a tape that does not wrap: every move has to check the
+/// bounds on its own, `><` at the last cell fails while a folded move does not
+pub struct FoldAddsPass;
+
+impl Transform for FoldAddsPass {
+    fn transform_node(&mut self, mut node: Node) -> Node {
+        fold_adds(&mut node);
+        node
+    }
+}
+
+/// `detect_clear_loops`
+pub struct ClearLoopPass;
+
+impl Transform for ClearLoopPass {
+    fn transform_node(&mut self, mut node: Node) -> Node {
+        detect_clear_loops(&mut node);
+        node
+    }
+}
+
+/// `detect_addition_loops`
+pub struct AdditionLoopPass;
+
+impl Transform for AdditionLoopPass {
+    fn transform_node(&mut self, mut node: Node) -> Node {
+        detect_addition_loops(&mut node);
+        node
+    }
+}
+
+/// `detect_scan_loops`
+pub struct ScanLoopPass;
+
+impl Transform for ScanLoopPass {
+    fn transform_node(&mut self, mut node: Node) -> Node {
+        detect_scan_loops(&mut node);
+        node
+    }
+}
+
+/// `eliminate_dead_loops`, adding the number of removed nodes to `eliminated`
+#[derive(Default)]
+pub struct DeadLoopPass {
+    pub eliminated: Rc<Cell<usize>>,
+}
+
+impl Transform for DeadLoopPass {
+    fn transform_node(&mut self, mut node: Node) -> Node {
+        let eliminated = eliminate_dead_loops(&mut node);
+        log!(debug, eliminated, "eliminated dead loops");
+        self.eliminated.set(self.eliminated.get() + eliminated);
+        node
+    }
+}
+
+/// Every optimization pass for a tape with the given `overflow_mode`, the dead nodes
+/// eliminated are counted in `eliminated`. Pointer moves are only folded on a tape
+/// that wraps around
+pub fn optimizer_pipeline(eliminated: &Rc<Cell<usize>>, overflow_mode: OverflowMode) -> Pipeline {
+    let pipeline = match overflow_mode {
+        OverflowMode::Wrap => Pipeline::new().add(FoldPass),
+        _ => Pipeline::new().add(FoldAddsPass),
+    };
+    pipeline
+        .add(ClearLoopPass)
+        .add(AdditionLoopPass)
+        .add(ScanLoopPass)
+        .add(DeadLoopPass {
+            eliminated: Rc::clone(eliminated),
+        })
+}
+
+/// Run every optimization pass on the tree for a tape that wraps around, returns the
+/// number of dead nodes eliminated
+pub fn optimize(program: &mut Node) -> usize {
+    let eliminated = Rc::new(Cell::new(0));
+    let ast = std::mem::replace(
+        program,
+        Node {
+            node_type: Program,
+            instruction: Default,
+            source_pos: 0,
+            childrens: Vec::new(),
+        },
+    );
+    *program = optimizer_pipeline(&eliminated, OverflowMode::Wrap).run(ast);
+    eliminated.get()
+}
+
+// Pointer movement of a node, if it only moves the pointer
+fn move_delta(node: &Node) -> Option<i32> {
+    match (node.node_type, node.instruction) {
+        (Operator, IncDP) => Some(1),
+        (Operator, DecDP) => Some(-1),
+        (Operator, MoveBy(delta)) => Some(delta),
+        _ => None,
+    }
+}
+
+// Cell change of a node, if it only adds to the current cell
+fn add_delta(node: &Node) -> Option<i8> {
+    match (node.node_type, node.instruction) {
+        (Operator, IncByte) => Some(1),
+        (Operator, DecByte) => Some(-1),
+        (Operator, AddByte(delta)) => Some(delta),
+        _ => None,
+    }
+}
+
+// Merge runs of adjacent nodes sharing a delta into a single command, when the
+// deltas of a run cancel out the run is removed
+fn fold_runs<T: Copy + PartialEq + std::default::Default>(
+    node: &mut Node,
+    delta_of: fn(&Node) -> Option<T>,
+    merge: fn(T, T) -> T,
+    command: fn(T) -> Command,
+) {
+    let mut folded: Vec<Node> = Vec::new();
+    for mut child in node.childrens.drain(..) {
+        if let Some(delta) = delta_of(&child) {
+            if let Some(last) = folded.last_mut() {
+                if let Some(previous) = delta_of(last) {
+                    last.instruction = command(merge(previous, delta));
+                    continue;
+                }
+            }
+            child.instruction = command(delta);
+        } else if child.node_type == Loop {
+            fold_runs(&mut child, delta_of, merge, command);
+        }
+        folded.push(child);
+    }
+    folded.retain(|child| delta_of(child) != Some(T::default()));
+    node.childrens = folded;
+}
+
+/// Merge adjacent pointer moves into a single `MoveBy`, moves cancelling out are removed
+pub fn fold_moves(node: &mut Node) {
+    fold_runs(node, move_delta, i32::wrapping_add, MoveBy);
+}
+
+/// Merge adjacent cell increments and decrements into a single `AddByte`, wrapping
+/// modulo 256 like the cells do, changes cancelling out are removed
+pub fn fold_adds(node: &mut Node) {
+    fold_runs(node, add_delta, i8::wrapping_add, AddByte);
+}
+
+// A loop is a clear loop when its only child changes the cell by an odd amount,
+// stepping by an odd amount reaches zero from every starting value
+fn is_clear_loop(node: &Node) -> bool {
+    node.node_type == Loop
+        && node.childrens.len() == 1
+        && add_delta(&node.childrens[0]).is_some_and(|delta| delta % 2 != 0)
+}
+
+/// Replace clear loops like `[-]` and `[+]` with a `SetZero` operator
+pub fn detect_clear_loops(node: &mut Node) {
+    for child in node.childrens.iter_mut() {
+        if is_clear_loop(child) {
+            *child = Node {
+                node_type: Operator,
+                instruction: SetZero,
+                source_pos: child.source_pos,
+                childrens: Vec::new(),
+            };
+        } else if child.node_type == Loop {
+            detect_clear_loops(child);
+        }
+    }
+}
+
+// Offset the current cell is added to when the loop is an addition loop, either
+// `[->+<]` or `[>+<-]` with any distance of the moves
+fn addition_offset(node: &Node) -> Option<i32> {
+    if node.node_type != Loop || node.childrens.len() != 4 {
+        return None;
+    }
+    let children = &node.childrens;
+    let (decrement, moves) = if add_delta(&children[0]) == Some(-1) {
+        (&children[0], &children[1..])
+    } else {
+        (&children[3], &children[..3])
+    };
+    match (
+        add_delta(decrement),
+        move_delta(&moves[0]),
+        add_delta(&moves[1]),
+        move_delta(&moves[2]),
+    ) {
+        (Some(-1), Some(there), Some(1), Some(back)) if there != 0 && there == -back => Some(there),
+        _ => None,
+    }
+}
+
+/// Replace addition loops like `[->+<]` with an `AddTo` operator
+pub fn detect_addition_loops(node: &mut Node) {
+    for child in node.childrens.iter_mut() {
+        if let Some(offset) = addition_offset(child) {
+            *child = Node {
+                node_type: Operator,
+                instruction: AddTo { offset },
+                source_pos: child.source_pos,
+                childrens: Vec::new(),
+            };
+        } else if child.node_type == Loop {
+            detect_addition_loops(child);
+        }
+    }
+}
+
+/// Replace scan loops `[>]` and `[<]` with `ScanRight` and `ScanLeft` operators
+pub fn detect_scan_loops(node: &mut Node) {
+    for child in node.childrens.iter_mut() {
+        let scan = match child.childrens.as_slice() {
+            [only] if child.node_type == Loop => match move_delta(only) {
+                Some(1) => Some(ScanRight),
+                Some(-1) => Some(ScanLeft),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(instruction) = scan {
+            *child = Node {
+                node_type: Operator,
+                instruction,
+                source_pos: child.source_pos,
+                childrens: Vec::new(),
+            };
+        } else if child.node_type == Loop {
+            detect_scan_loops(child);
+        }
+    }
+}
+
+// Whether the current cell is zero once the node ran: a loop only exits on a zero
+// cell, and the replacements of clear, addition and scan loops keep that property
+fn leaves_zero(node: &Node) -> bool {
+    node.node_type == Loop
+        || matches!(
+            (node.node_type, node.instruction),
+            (Operator, SetZero)
+                | (Operator, AddTo { .. })
+                | (Operator, ScanRight)
+                | (Operator, ScanLeft)
+        )
+}
+
+/// Remove the loops that can never be entered because they follow a `SetZero` or
+/// another node leaving the current cell at zero, and `Default` operators. Returns
+/// the number of removed nodes
+pub fn eliminate_dead_loops(node: &mut Node) -> usize {
+    let mut eliminated = 0;
+    let mut kept: Vec<Node> = Vec::new();
+    for mut child in node.childrens.drain(..) {
+        let dead = match child.node_type {
+            Loop => kept.last().is_some_and(leaves_zero),
+            _ => child.instruction == Default,
+        };
+        if dead {
+            eliminated += 1;
+            continue;
+        }
+        if child.node_type == Loop {
+            eliminated += eliminate_dead_loops(&mut child);
+        }
+        kept.push(child);
+    }
+    node.childrens = kept;
+    eliminated
+}
+
+/// The shortest equivalent tree these passes find on a tape with the given
+/// `overflow_mode`: `fold_moves`, `fold_adds` and `eliminate_dead_loops` run again
+/// until the tree stops changing. Only commands printed as Brainfuck are produced,
+/// `Display` writes it back as source. Empty loops that may be entered stay, they
+/// never end. Pointer moves are only folded on a tape that wraps around, elsewhere
+/// `<>` at the first cell stops the program
+pub fn minify(mut node: Node, overflow_mode: OverflowMode) -> Node {
+    loop {
+        let before = node.clone();
+        if overflow_mode == OverflowMode::Wrap {
+            fold_moves(&mut node);
+        }
+        fold_adds(&mut node);
+        eliminate_dead_loops(&mut node);
+        if node == before {
+            return node;
+        }
+    }
+}
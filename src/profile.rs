@@ -0,0 +1,475 @@
+//! Profiling mode counting how often every kind of instruction is executed
+//! and, optionally, how often every cell of the tape is read and written and
+//! how often every instruction of the source ran.
+
+use crate::visit::{walk_children, Visitor};
+use crate::Command::{self, *};
+use crate::NodeType::*;
+use crate::{
+    check_interrupt, check_timeout, count_step, execute_instruction, memory_index, wrap_pointer,
+    Interpreter, InterpreterError, Node,
+};
+use std::time::{Duration, Instant};
+
+/// Names of the counted instructions, in the order of `Command` variants
+pub const INSTRUCTION_NAMES: [&str; 20] = [
+    "Default",
+    "IncDP",
+    "DecDP",
+    "IncByte",
+    "DecByte",
+    "OutByte",
+    "InByte",
+    "JumpForward",
+    "JumpBackward",
+    "MoveBy",
+    "AddByte",
+    "SetZero",
+    "AddTo",
+    "ScanRight",
+    "ScanLeft",
+    "DebugPrint",
+    "Halt",
+    "DefineSubroutine",
+    "EndSubroutine",
+    "CallSubroutine",
+];
+
+/// Execution counts indexed by the `Command` discriminant
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InstructionProfile {
+    pub counts: [u64; 20],
+    pub elapsed: Duration,
+    pub heatmap: Option<TapeHeatmap>,
+    pub coverage: Option<Coverage>,
+    pub hotspots: Option<Hotspots>,
+}
+
+/// Whether the instruction at every byte offset of the source ran, a loop runs
+/// when its condition is checked
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    pub executed: Vec<bool>,
+}
+
+/// Number of reads and writes of every cell on the tape
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TapeHeatmap {
+    pub reads: Vec<u64>,
+    pub writes: Vec<u64>,
+    pub offset: i64, // address of the first counted cell
+}
+
+/// Times the instruction at every byte offset of the source ran, a loop runs
+/// when its condition is checked
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Hotspots {
+    pub counts: Vec<u64>,
+}
+
+/// Source offsets listed by `Hotspots::report`
+pub const HOTSPOTS: usize = 10;
+
+/// Cells listed by `TapeHeatmap::report`
+pub const HOTTEST_CELLS: usize = 20;
+// Rows of the bar chart printed by `TapeHeatmap::report`
+const CHART_ROWS: usize = 16;
+
+/// Position of a command in `InstructionProfile::counts`
+pub fn command_index(cmd: &Command) -> usize {
+    match cmd {
+        Default => 0,
+        IncDP => 1,
+        DecDP => 2,
+        IncByte => 3,
+        DecByte => 4,
+        OutByte => 5,
+        InByte => 6,
+        JumpForward => 7,
+        JumpBackward => 8,
+        MoveBy(_) => 9,
+        AddByte(_) => 10,
+        SetZero => 11,
+        AddTo { .. } => 12,
+        ScanRight => 13,
+        ScanLeft => 14,
+        DebugPrint => 15,
+        Halt => 16,
+        DefineSubroutine => 17,
+        EndSubroutine => 18,
+        CallSubroutine => 19,
+    }
+}
+
+impl InstructionProfile {
+    /// Number of executed instructions
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Table of executed instructions with their share of the total
+    pub fn report(&self) -> String {
+        let total = self.total();
+        let mut report = format!("{:<14}{:>14}{:>9}\n", "instruction", "count", "percent");
+        for (name, count) in INSTRUCTION_NAMES.iter().zip(self.counts.iter()) {
+            if *count == 0 {
+                continue;
+            }
+            report.push_str(&format!(
+                "{:<14}{:>14}{:>8.2}%\n",
+                name,
+                count,
+                *count as f64 * 100.0 / total as f64
+            ));
+        }
+        report.push_str(&format!("total steps: {}\n", total));
+        report.push_str(&format!("wall time: {:.3?}\n", self.elapsed));
+        report
+    }
+}
+
+impl TapeHeatmap {
+    pub fn new(tape_size: usize) -> Self {
+        TapeHeatmap {
+            reads: vec![0; tape_size],
+            writes: vec![0; tape_size],
+            offset: 0,
+        }
+    }
+
+    /// Extend the counts to a tape that grew on either side
+    pub fn fit(&mut self, interpreter: &Interpreter) {
+        if interpreter.offset < self.offset {
+            let added = (self.offset - interpreter.offset) as usize;
+            self.reads.splice(0..0, std::iter::repeat_n(0, added));
+            self.writes.splice(0..0, std::iter::repeat_n(0, added));
+            self.offset = interpreter.offset;
+        }
+        if self.reads.len() < interpreter.tape_size {
+            self.reads.resize(interpreter.tape_size, 0);
+            self.writes.resize(interpreter.tape_size, 0);
+        }
+    }
+
+    // Position in the counts of the cell at an address
+    fn slot(&self, address: i64) -> usize {
+        (address - self.offset) as usize
+    }
+
+    // Address of the cell the pointer reaches, wrapped onto a wrapping tape
+    fn address(interpreter: &Interpreter, pointer: i32) -> i64 {
+        interpreter.cell_address(wrap_pointer(interpreter, pointer))
+    }
+
+    /// Count a read of the cell at an address
+    pub fn read(&mut self, interpreter: &Interpreter, address: i64) {
+        self.fit(interpreter);
+        let slot = self.slot(address);
+        self.reads[slot] += 1;
+    }
+
+    /// Record the accesses of a command executed on the cell at an address,
+    /// given the pointer and cell value from before it ran
+    pub fn record(
+        &mut self,
+        interpreter: &Interpreter,
+        cmd: &Command,
+        address: i64,
+        pointer: i32,
+        value: u8,
+    ) {
+        self.fit(interpreter);
+        let index = self.slot(address);
+        match cmd {
+            IncByte | DecByte | AddByte(_) => {
+                self.reads[index] += 1;
+                self.writes[index] += 1;
+            }
+            SetZero | InByte => self.writes[index] += 1,
+            OutByte => self.reads[index] += 1,
+            AddTo { offset } => {
+                self.reads[index] += 1;
+                if value != 0 {
+                    let target = self.slot(Self::address(interpreter, pointer + offset));
+                    self.reads[target] += 1;
+                    self.writes[target] += 1;
+                    self.writes[index] += 1;
+                }
+            }
+            ScanRight | ScanLeft => {
+                for moved in 0..=interpreter.pointer.abs_diff(pointer) as i32 {
+                    let step = if *cmd == ScanRight { moved } else { -moved };
+                    let cell = self.slot(Self::address(interpreter, pointer + step));
+                    self.reads[cell] += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Addresses of the cells sorted by total access count, most accessed first
+    pub fn hottest(&self, limit: usize) -> Vec<(i64, u64, u64)> {
+        let mut cells: Vec<(i64, u64, u64)> = self
+            .reads
+            .iter()
+            .zip(self.writes.iter())
+            .enumerate()
+            .filter(|(_, (reads, writes))| **reads + **writes != 0)
+            .map(|(index, (reads, writes))| (index as i64 + self.offset, *reads, *writes))
+            .collect();
+        cells.sort_by(|a, b| (b.1 + b.2).cmp(&(a.1 + a.2)).then(a.0.cmp(&b.0)));
+        cells.truncate(limit);
+        cells
+    }
+
+    /// Table of the hottest cells followed by a bar chart of the accesses
+    /// across the used part of the tape, at most width columns wide
+    pub fn report(&self, width: usize) -> String {
+        let mut report = format!("{:<10}{:>14}{:>14}\n", "cell", "reads", "writes");
+        for (index, reads, writes) in self.hottest(HOTTEST_CELLS) {
+            report.push_str(&format!("{:<10}{:>14}{:>14}\n", index, reads, writes));
+        }
+
+        let totals: Vec<u64> = self
+            .reads
+            .iter()
+            .zip(self.writes.iter())
+            .map(|(reads, writes)| reads + writes)
+            .collect();
+        let (first, last) = match (
+            totals.iter().position(|total| *total != 0),
+            totals.iter().rposition(|total| *total != 0),
+        ) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return report,
+        };
+        let used = last + 1 - first;
+        let cells_per_row = used.div_ceil(CHART_ROWS);
+        let rows: Vec<(usize, u64)> = totals[first..=last]
+            .chunks(cells_per_row)
+            .enumerate()
+            .map(|(row, chunk)| (first + row * cells_per_row, chunk.iter().sum()))
+            .collect();
+        let highest = rows.iter().map(|(_, total)| *total).max().unwrap_or(0);
+        let address = |index: usize| index as i64 + self.offset;
+        let label_width = format!("{}..{}", address(first), address(last)).len() + 1;
+        let bar_width = width.saturating_sub(label_width + 2).max(1);
+        report.push('\n');
+        for (start, total) in rows {
+            let end = (start + cells_per_row).min(last + 1) - 1;
+            let label = if start == end {
+                address(start).to_string()
+            } else {
+                format!("{}..{}", address(start), address(end))
+            };
+            let bar = (total as f64 / highest as f64 * bar_width as f64).ceil() as usize;
+            report.push_str(&format!(
+                "{:>label_width$} |{}\n",
+                label,
+                "#".repeat(bar),
+                label_width = label_width
+            ));
+        }
+        report
+    }
+}
+
+impl Coverage {
+    /// Nothing executed yet in a source of `len` bytes
+    pub fn new(len: usize) -> Self {
+        Coverage {
+            executed: vec![false; len],
+        }
+    }
+
+    fn mark(&mut self, node: &Node) {
+        if let Some(executed) = self.executed.get_mut(node.source_pos) {
+            *executed = true;
+        }
+    }
+
+    /// Source offsets of the instructions of the tree that ran and that never did
+    pub fn split(&self, ast: &Node) -> (Vec<usize>, Vec<usize>) {
+        let mut offsets = NodeOffsets(Vec::new());
+        walk_children(ast, &mut offsets);
+        offsets
+            .0
+            .into_iter()
+            .partition(|&offset| self.executed.get(offset) == Some(&true))
+    }
+
+    /// The covered and uncovered lines `--coverage` prints
+    pub fn report(&self, ast: &Node) -> String {
+        let (covered, uncovered) = self.split(ast);
+        format!(
+            "covered: {} instructions\nuncovered: {} instructions\n",
+            covered.len(),
+            uncovered.len()
+        )
+    }
+
+    /// The source with a line of `^` under every line holding instructions that
+    /// never ran, one under each of them
+    pub fn annotate(&self, source: &str, ast: &Node) -> String {
+        let (_, uncovered) = self.split(ast);
+        let mut annotated = String::with_capacity(source.len() * 2);
+        let mut start = 0;
+        for line in source.split_inclusive('\n') {
+            let end = start + line.len();
+            annotated.push_str(line);
+            let marks: String = line
+                .trim_end_matches('\n')
+                .char_indices()
+                .map(|(pos, c)| match uncovered.binary_search(&(start + pos)) {
+                    Ok(_) => '^',
+                    Err(_) if c == '\t' => '\t', // keeps the marks under their commands
+                    Err(_) => ' ',
+                })
+                .collect();
+            if marks.contains('^') {
+                if !line.ends_with('\n') {
+                    annotated.push('\n');
+                }
+                annotated.push_str(marks.trim_end());
+                annotated.push('\n');
+            }
+            start = end;
+        }
+        annotated
+    }
+}
+
+impl Hotspots {
+    /// Nothing counted yet in a source of `len` bytes
+    pub fn new(len: usize) -> Self {
+        Hotspots {
+            counts: vec![0; len],
+        }
+    }
+
+    fn record(&mut self, node: &Node) {
+        if let Some(count) = self.counts.get_mut(node.source_pos) {
+            *count += 1;
+        }
+    }
+
+    /// Source offsets sorted by how often their instruction ran, most first
+    pub fn hottest(&self, limit: usize) -> Vec<(usize, u64)> {
+        let mut offsets: Vec<(usize, u64)> = self
+            .counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count != 0)
+            .map(|(offset, count)| (offset, *count))
+            .collect();
+        offsets.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        offsets.truncate(limit);
+        offsets
+    }
+
+    /// Table of the offsets run most often with the command of `source` at each
+    pub fn report(&self, source: &str) -> String {
+        let mut report = format!("{:<10}{:<10}{:>14}\n", "offset", "command", "count");
+        for (offset, count) in self.hottest(HOTSPOTS) {
+            let command = source.get(offset..).and_then(|rest| rest.chars().next());
+            report.push_str(&format!(
+                "{:<10}{:<10}{:>14}\n",
+                offset,
+                command.unwrap_or('?'),
+                count
+            ));
+        }
+        report
+    }
+}
+
+// Source offsets of every loop and operator, in source order
+struct NodeOffsets(Vec<usize>);
+
+impl Visitor for NodeOffsets {
+    fn visit_loop(&mut self, node: &Node) {
+        self.0.push(node.source_pos);
+        walk_children(node, self);
+    }
+
+    fn visit_operator(&mut self, node: &Node, _cmd: Command) {
+        self.0.push(node.source_pos);
+        // the body of a subroutine definition
+        walk_children(node, self);
+    }
+}
+
+/// Same as `run_program`, counting every executed operator. Loop condition
+/// checks are not instructions of their own and only count as cell reads.
+pub fn profile_run_program(
+    interpreter: &mut Interpreter,
+    ast: &Node,
+    profile: &mut InstructionProfile,
+) -> Result<(), InterpreterError> {
+    profile_nodes(interpreter, ast, profile, Instant::now())
+}
+
+// The children of `ast`, loops check the timeout of the run started at `start`
+fn profile_nodes(
+    interpreter: &mut Interpreter,
+    ast: &Node,
+    profile: &mut InstructionProfile,
+    start: Instant,
+) -> Result<(), InterpreterError> {
+    for node in ast.childrens.iter() {
+        let mut index = memory_index(interpreter);
+        match node.node_type {
+            Loop => loop {
+                if let Some(coverage) = profile.coverage.as_mut() {
+                    coverage.mark(node);
+                }
+                if let Some(hotspots) = profile.hotspots.as_mut() {
+                    hotspots.record(node);
+                }
+                if let Some(heatmap) = profile.heatmap.as_mut() {
+                    heatmap.read(interpreter, interpreter.cell_address(index));
+                }
+                if interpreter.memory[index] == 0 {
+                    break;
+                }
+                profile_nodes(interpreter, node, profile, start)?;
+                index = memory_index(interpreter);
+                if interpreter.memory[index] != 0 {
+                    count_step(interpreter)?;
+                    check_interrupt(interpreter)?;
+                    check_timeout(interpreter, start)?;
+                }
+            },
+            Operator => {
+                profile.counts[command_index(&node.instruction)] += 1;
+                if let Some(coverage) = profile.coverage.as_mut() {
+                    coverage.mark(node);
+                }
+                if let Some(hotspots) = profile.hotspots.as_mut() {
+                    hotspots.record(node);
+                }
+                let (pointer, value) = (interpreter.pointer, interpreter.memory[index]);
+                let address = interpreter.cell_address(index);
+                execute_instruction(interpreter, &node.instruction, index)?;
+                if let Some(heatmap) = profile.heatmap.as_mut() {
+                    heatmap.record(interpreter, &node.instruction, address, pointer, value);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Run the program and return how often every cell was accessed
+pub fn run_with_heatmap(
+    interpreter: &mut Interpreter,
+    ast: &Node,
+) -> Result<TapeHeatmap, InterpreterError> {
+    let mut profile = InstructionProfile {
+        heatmap: Some(TapeHeatmap::new(interpreter.tape_size)),
+        ..InstructionProfile::default()
+    };
+    profile_run_program(interpreter, ast, &mut profile)?;
+    Ok(profile.heatmap.unwrap_or_default())
+}
@@ -0,0 +1,91 @@
+//! The `brainfuck` Python module, built with the `python` feature:
+//!
+//! ```python
+//! import brainfuck
+//!
+//! interpreter = brainfuck.Interpreter()
+//! assert interpreter.run("++++++++[>++++++++<-]>+.") == b"A"
+//! ```
+
+use crate::{
+    interpreter_init, lexical_analysis_str, run_program, syntax_analysis, InputSource, Interpreter,
+    InterpreterError, MEMORY_SIZE,
+};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+    types::{PyBytes, PyList},
+};
+use std::{cell::RefCell, io, io::Write, rc::Rc};
+
+// Output of `.` kept until `run` hands it to Python
+struct CapturedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl Write for CapturedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// An interpreter keeping its tape between the programs it runs
+#[pyclass(name = "Interpreter", unsendable)]
+pub struct PyInterpreter {
+    interpreter: Interpreter,
+    output: Rc<RefCell<Vec<u8>>>,
+}
+
+#[pymethods]
+impl PyInterpreter {
+    #[new]
+    #[pyo3(signature = (tape_size = MEMORY_SIZE))]
+    fn new(tape_size: usize) -> PyResult<Self> {
+        if tape_size == 0 {
+            return Err(PyValueError::new_err("tape_size must be at least 1"));
+        }
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let mut interpreter = interpreter_init(tape_size);
+        interpreter.input = InputSource::Buffer(Vec::new(), 0);
+        interpreter.output = Box::new(CapturedOutput(output.clone()));
+        Ok(PyInterpreter {
+            interpreter,
+            output,
+        })
+    }
+
+    /// Run the program on the tape and return the bytes it wrote, an unmatched
+    /// bracket raises ValueError and a failing program RuntimeError
+    fn run<'py>(&mut self, py: Python<'py>, source: &str) -> PyResult<Bound<'py, PyBytes>> {
+        let ast = syntax_analysis(lexical_analysis_str(source), source)
+            .map_err(|error| PyValueError::new_err(error.to_string()))?;
+        let result = run_program(&mut self.interpreter, &ast);
+        let output = PyBytes::new(py, &self.output.borrow_mut().split_off(0));
+        match result {
+            Ok(()) | Err(InterpreterError::HaltInstruction) => Ok(output),
+            Err(error) => Err(PyRuntimeError::new_err(error.to_string())),
+        }
+    }
+
+    /// Every cell of the tape, a list of ints where a `Vec<u8>` would become bytes
+    fn get_tape<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyList>> {
+        PyList::new(py, self.interpreter.memory.iter())
+    }
+
+    fn get_pointer(&self) -> i32 {
+        self.interpreter.pointer
+    }
+
+    /// The bytes `,` reads in the next runs, the end of file comes after them
+    fn set_input(&mut self, data: &[u8]) {
+        self.interpreter.input = InputSource::Buffer(data.to_vec(), 0);
+    }
+}
+
+#[pymodule]
+fn brainfuck(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyInterpreter>()
+}
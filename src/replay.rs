@@ -0,0 +1,196 @@
+//! Logs of the bytes a run read and wrote, so it can be run again exactly.
+//!
+//! A log starts with `MAGIC`, followed by one event of 10 bytes per `,` and `.`:
+//! a tag, 0 for a byte read, 1 for a byte written and 2 for `,` at the end of the
+//! input, the step it ran at as an 8 byte little endian number and the byte.
+
+use alloc::{format, string::ToString, vec::Vec};
+use core::{convert::TryFrom, fmt};
+
+pub const MAGIC: &[u8; 7] = b"BFREPL\x01";
+
+const EVENT_SIZE: usize = 10;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Event {
+    Input { step: u64, byte: Option<u8> }, // None is the end of the input
+    Output { step: u64, byte: u8 },
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct ReplayLog {
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ReplayError {
+    InvalidFormat,
+    // expected is None where the log has no output, found is None when the program ended
+    OutputDiverged {
+        step: u64,
+        expected: Option<u8>,
+        found: Option<u8>,
+    },
+    InputDiverged {
+        step: u64,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let byte = |byte: &Option<u8>, none: &str| match byte {
+            Some(byte) => format!("byte {}", byte),
+            None => none.to_string(),
+        };
+        match self {
+            ReplayError::InvalidFormat => write!(f, "invalid replay log format"),
+            ReplayError::OutputDiverged {
+                step,
+                expected,
+                found,
+            } => write!(
+                f,
+                "output diverged from the replay log at step {}: expected {}, found {}",
+                step,
+                byte(expected, "no output"),
+                byte(found, "the end of the program")
+            ),
+            ReplayError::InputDiverged { step } => write!(
+                f,
+                "input read at step {} where the replay log has none",
+                step
+            ),
+        }
+    }
+}
+
+impl core::error::Error for ReplayError {}
+
+impl ReplayLog {
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MAGIC.len() + self.events.len() * EVENT_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        for event in self.events.iter() {
+            let (tag, step, byte) = match *event {
+                Event::Input {
+                    step,
+                    byte: Some(byte),
+                } => (0, step, byte),
+                Event::Output { step, byte } => (1, step, byte),
+                Event::Input { step, byte: None } => (2, step, 0),
+            };
+            bytes.push(tag);
+            bytes.extend_from_slice(&step.to_le_bytes());
+            bytes.push(byte);
+        }
+        bytes
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let rest = bytes
+            .strip_prefix(&MAGIC[..])
+            .ok_or(ReplayError::InvalidFormat)?;
+        if !rest.len().is_multiple_of(EVENT_SIZE) {
+            return Err(ReplayError::InvalidFormat);
+        }
+        let events = rest
+            .chunks(EVENT_SIZE)
+            .map(|event| {
+                let step = u64::from_le_bytes(<[u8; 8]>::try_from(&event[1..9]).unwrap());
+                let byte = event[9];
+                match event[0] {
+                    0 => Ok(Event::Input {
+                        step,
+                        byte: Some(byte),
+                    }),
+                    1 => Ok(Event::Output { step, byte }),
+                    2 => Ok(Event::Input { step, byte: None }),
+                    _ => Err(ReplayError::InvalidFormat),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ReplayLog { events })
+    }
+}
+
+/// What `--record` and `--replay` do with the bytes `,` reads and `.` writes
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Replay {
+    Record(ReplayLog), // every byte is added to the log
+    // `,` reads the bytes of the log and `.` must write the ones it has, position
+    // is the index of the next event
+    Play { log: ReplayLog, position: usize },
+}
+
+impl Replay {
+    pub fn play(log: ReplayLog) -> Self {
+        Replay::Play { log, position: 0 }
+    }
+
+    /// The byte of the `,` at `step`, `read` gives it when recording
+    pub fn input(
+        &mut self,
+        step: u64,
+        read: impl FnOnce() -> Option<u8>,
+    ) -> Result<Option<u8>, ReplayError> {
+        match self {
+            Replay::Record(log) => {
+                let byte = read();
+                log.events.push(Event::Input { step, byte });
+                Ok(byte)
+            }
+            Replay::Play { log, position } => match log.events.get(*position) {
+                Some(&Event::Input { byte, .. }) => {
+                    *position += 1;
+                    Ok(byte)
+                }
+                _ => Err(ReplayError::InputDiverged { step }),
+            },
+        }
+    }
+
+    /// Log the byte of the `.` at `step`, or check that it is the one logged
+    pub fn output(&mut self, step: u64, byte: u8) -> Result<(), ReplayError> {
+        match self {
+            Replay::Record(log) => log.events.push(Event::Output { step, byte }),
+            Replay::Play { log, position } => match log.events.get(*position) {
+                Some(&Event::Output { byte: logged, .. }) if logged == byte => *position += 1,
+                Some(&Event::Output { byte: logged, .. }) => {
+                    return Err(ReplayError::OutputDiverged {
+                        step,
+                        expected: Some(logged),
+                        found: Some(byte),
+                    })
+                }
+                _ => {
+                    return Err(ReplayError::OutputDiverged {
+                        step,
+                        expected: None,
+                        found: Some(byte),
+                    })
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Fail when the program ended before writing every byte of the log being played
+    pub fn finish(&self) -> Result<(), ReplayError> {
+        if let Replay::Play { log, position } = self {
+            let missing = log.events[*position..]
+                .iter()
+                .find_map(|event| match *event {
+                    Event::Output { step, byte } => Some((step, byte)),
+                    Event::Input { .. } => None,
+                });
+            if let Some((step, byte)) = missing {
+                return Err(ReplayError::OutputDiverged {
+                    step,
+                    expected: Some(byte),
+                    found: None,
+                });
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+//! Snapshots of the tape and the pointer, so a run can continue from the
+//! state another run finished in.
+//!
+//! A snapshot starts with `MAGIC`, followed by the tape size as a 4 byte little
+//! endian number, the cells as raw bytes and the pointer as a 4 byte little
+//! endian `i32`.
+//!
+//! A checkpoint of the flat interpreter starts with `CHECKPOINT_MAGIC` instead and
+//! ends with the program counter as an 8 byte little endian number, so a long run
+//! can start over from the command it stopped at.
+
+use crate::{memory_index, Interpreter};
+use std::convert::TryFrom;
+use std::{fmt, fs, io, path::Path};
+
+pub const MAGIC: &[u8; 7] = b"BFSNAP\x01";
+pub const CHECKPOINT_MAGIC: &[u8; 7] = b"BFCKPT\x01";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    InvalidFormat,
+    TapeTooLarge { size: usize },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::InvalidFormat => write!(f, "invalid snapshot format"),
+            SnapshotError::TapeTooLarge { size } => {
+                write!(f, "tape of {} cells is too large for a snapshot", size)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// Encode the tape and the pointer of the interpreter
+pub fn serialize_snapshot(interpreter: &Interpreter) -> Result<Vec<u8>, SnapshotError> {
+    encode(interpreter, MAGIC)
+}
+
+/// Replace the tape and the pointer of the interpreter with a saved snapshot
+pub fn restore_snapshot(interpreter: &mut Interpreter, bytes: &[u8]) -> Result<(), SnapshotError> {
+    decode(interpreter, bytes, MAGIC)
+}
+
+/// Encode the tape and the pointer of the interpreter with the program counter `pc`
+pub fn serialize_checkpoint(
+    interpreter: &Interpreter,
+    pc: usize,
+) -> Result<Vec<u8>, SnapshotError> {
+    let mut bytes = encode(interpreter, CHECKPOINT_MAGIC)?;
+    bytes.extend_from_slice(&(pc as u64).to_le_bytes());
+    Ok(bytes)
+}
+
+/// Replace the tape and the pointer of the interpreter with a saved checkpoint,
+/// giving the program counter to continue from
+pub fn restore_checkpoint(
+    interpreter: &mut Interpreter,
+    bytes: &[u8],
+) -> Result<usize, SnapshotError> {
+    if bytes.len() < 8 {
+        return Err(SnapshotError::InvalidFormat);
+    }
+    let (state, pc) = bytes.split_at(bytes.len() - 8);
+    decode(interpreter, state, CHECKPOINT_MAGIC)?;
+    usize::try_from(u64::from_le_bytes(<[u8; 8]>::try_from(pc).unwrap()))
+        .map_err(|_| SnapshotError::InvalidFormat)
+}
+
+/// Write a checkpoint to `path` through a temporary file beside it, so a run
+/// killed while writing leaves the last checkpoint whole
+pub fn save_checkpoint(path: &Path, interpreter: &Interpreter, pc: usize) -> io::Result<()> {
+    let bytes = serialize_checkpoint(interpreter, pc)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, bytes)?;
+    fs::rename(&temporary, path)
+}
+
+fn encode(interpreter: &Interpreter, magic: &[u8]) -> Result<Vec<u8>, SnapshotError> {
+    let size = u32::try_from(interpreter.tape_size).map_err(|_| SnapshotError::TapeTooLarge {
+        size: interpreter.tape_size,
+    })?;
+    let mut bytes = Vec::with_capacity(magic.len() + 8 + interpreter.tape_size);
+    bytes.extend_from_slice(magic);
+    bytes.extend_from_slice(&size.to_le_bytes());
+    bytes.extend(interpreter.memory.iter());
+    // the cell under the pointer, from the first cell even when a bidirectional
+    // tape grew left and onto the tape when a wrapping pointer went past an end
+    let pointer = memory_index(interpreter) as i32;
+    bytes.extend_from_slice(&pointer.to_le_bytes());
+    Ok(bytes)
+}
+
+fn decode(interpreter: &mut Interpreter, bytes: &[u8], magic: &[u8]) -> Result<(), SnapshotError> {
+    let rest = bytes
+        .strip_prefix(magic)
+        .ok_or(SnapshotError::InvalidFormat)?;
+    if rest.len() < 8 {
+        return Err(SnapshotError::InvalidFormat);
+    }
+    let (size, rest) = rest.split_at(4);
+    let size = u32::from_le_bytes(<[u8; 4]>::try_from(size).unwrap()) as usize;
+    if size == 0 || rest.len() != size + 4 {
+        return Err(SnapshotError::InvalidFormat);
+    }
+    let (memory, pointer) = rest.split_at(size);
+    let pointer = i32::from_le_bytes(<[u8; 4]>::try_from(pointer).unwrap());
+    if pointer < 0 || pointer as usize >= size {
+        return Err(SnapshotError::InvalidFormat);
+    }
+    interpreter.memory = memory.iter().copied().collect();
+    interpreter.offset = 0;
+    interpreter.tape_size = size;
+    interpreter.pointer = pointer;
+    Ok(())
+}
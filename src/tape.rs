@@ -0,0 +1,282 @@
+//! Storage of the tape cells. The interpreter keeps its cells in a `VecDeque`,
+//! `--sparse-tape` stores only the cells that are not zero and `--mmap-tape`
+//! keeps them in a file.
+
+use crate::cell::CellArithmetic;
+use alloc::collections::VecDeque;
+#[cfg(not(feature = "no_std"))]
+use memmap2::MmapMut;
+#[cfg(not(feature = "no_std"))]
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io,
+    path::Path,
+};
+
+/// Cells addressed by their index in memory, `Interpreter<T, M>` runs programs
+/// on a tape of type `M`
+pub trait Tape<T: CellArithmetic = u8> {
+    fn get(&self, index: usize) -> T;
+    fn set(&mut self, index: usize, value: T);
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add `count` zero cells on the left, the index of every cell grows by `count`
+    fn grow_front(&mut self, count: usize);
+
+    /// Add zero cells on the right or drop the last ones until there are `len`
+    fn resize(&mut self, len: usize);
+
+    /// Index of the first zero cell at or right of `index`
+    fn find_zero_from(&self, index: usize) -> Option<usize> {
+        (index..self.len()).find(|&cell| self.get(cell).is_zero())
+    }
+
+    /// Index of the last zero cell at or left of `index`
+    fn rfind_zero_until(&self, index: usize) -> Option<usize> {
+        (0..=index).rev().find(|&cell| self.get(cell).is_zero())
+    }
+
+    /// Index of the last cell that is not zero
+    fn last_nonzero(&self) -> Option<usize> {
+        (0..self.len())
+            .rev()
+            .find(|&cell| !self.get(cell).is_zero())
+    }
+}
+
+impl<T: CellArithmetic> Tape<T> for VecDeque<T> {
+    fn get(&self, index: usize) -> T {
+        self[index]
+    }
+
+    fn set(&mut self, index: usize, value: T) {
+        self[index] = value;
+    }
+
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+
+    fn grow_front(&mut self, count: usize) {
+        self.reserve(count);
+        for _ in 0..count {
+            self.push_front(T::default());
+        }
+    }
+
+    fn resize(&mut self, len: usize) {
+        VecDeque::resize(self, len, T::default());
+    }
+
+    // both halves of the ring buffer are searched with the fast search of the cell type
+    fn find_zero_from(&self, index: usize) -> Option<usize> {
+        let (front, back) = self.as_slices();
+        if index >= front.len() {
+            return T::find_zero(&back[index - front.len()..]).map(|position| index + position);
+        }
+        match T::find_zero(&front[index..]) {
+            Some(position) => Some(index + position),
+            None => T::find_zero(back).map(|position| front.len() + position),
+        }
+    }
+
+    fn rfind_zero_until(&self, index: usize) -> Option<usize> {
+        let (front, back) = self.as_slices();
+        if index < front.len() {
+            return T::rfind_zero(&front[..=index]);
+        }
+        match T::rfind_zero(&back[..=index - front.len()]) {
+            Some(position) => Some(front.len() + position),
+            None => T::rfind_zero(front),
+        }
+    }
+}
+
+/// A tape of bytes holding only the cells that are not zero, so a program using
+/// a few cells of a huge tape only takes memory for those
+#[cfg(not(feature = "no_std"))]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SparseTape {
+    cells: HashMap<usize, u8>,
+    len: usize,
+}
+
+#[cfg(not(feature = "no_std"))]
+impl SparseTape {
+    /// A tape of `len` zero cells
+    pub fn new(len: usize) -> Self {
+        SparseTape {
+            cells: HashMap::new(),
+            len,
+        }
+    }
+
+    /// Number of cells that are not zero, the ones taking memory
+    pub fn stored(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+#[cfg(not(feature = "no_std"))]
+impl Tape for SparseTape {
+    fn get(&self, index: usize) -> u8 {
+        self.cells.get(&index).copied().unwrap_or(0)
+    }
+
+    fn set(&mut self, index: usize, value: u8) {
+        if value == 0 {
+            self.cells.remove(&index);
+        } else {
+            self.cells.insert(index, value);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn grow_front(&mut self, count: usize) {
+        self.cells = self
+            .cells
+            .drain()
+            .map(|(index, value)| (index + count, value))
+            .collect();
+        self.len += count;
+    }
+
+    fn resize(&mut self, len: usize) {
+        self.cells.retain(|&index, _| index < len);
+        self.len = len;
+    }
+
+    // only the cells stored are visited, every other cell is zero
+    fn find_zero_from(&self, index: usize) -> Option<usize> {
+        (index..self.len).find(|cell| !self.cells.contains_key(cell))
+    }
+
+    fn rfind_zero_until(&self, index: usize) -> Option<usize> {
+        (0..=index)
+            .rev()
+            .find(|cell| !self.cells.contains_key(cell))
+    }
+
+    fn last_nonzero(&self) -> Option<usize> {
+        self.cells.keys().max().copied()
+    }
+}
+
+/// A tape of bytes in a file mapped into memory: the system pages the cells in and
+/// out, so the tape may be larger than the RAM, and other programs can read the
+/// cells in the file while the program runs
+#[cfg(not(feature = "no_std"))]
+#[derive(Debug)]
+pub struct MmapTape {
+    file: File,
+    map: MmapMut,
+}
+
+#[cfg(not(feature = "no_std"))]
+impl MmapTape {
+    /// Map the file at `path` as a tape of `len` cells. A new file is created full of
+    /// zeros, an existing one keeps its cells and is padded with zeros to `len`; a
+    /// file holding more than `len` cells is refused rather than cut
+    pub fn open(path: impl AsRef<Path>, len: usize) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let cells = file.metadata()?.len();
+        if cells > len as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "tape file {} holds {} cells, more than the {} of the tape, see --memory-size",
+                    path.display(),
+                    cells,
+                    len
+                ),
+            ));
+        }
+        file.set_len(len as u64)?;
+        let map = map_file(&file)?;
+        Ok(MmapTape { file, map })
+    }
+
+    /// Map the tape a previous run left at `path`, which must hold exactly `len` cells,
+    /// or a new file full of zeros when there is none
+    pub fn open_persistent(path: impl AsRef<Path>, len: usize) -> io::Result<Self> {
+        let path = path.as_ref();
+        match std::fs::metadata(path) {
+            Ok(metadata) if metadata.len() != len as u64 => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "tape file {} holds {} cells where the tape has {}, see --memory-size",
+                    path.display(),
+                    metadata.len(),
+                    len
+                ),
+            )),
+            _ => MmapTape::open(path, len),
+        }
+    }
+
+    /// Write the changed cells back to the file
+    pub fn flush(&self) -> io::Result<()> {
+        self.map.flush()
+    }
+}
+
+#[cfg(not(feature = "no_std"))]
+fn map_file(file: &File) -> io::Result<MmapMut> {
+    // the mapping stays sound as long as no other program shrinks the file
+    // while the tape is mapped
+    unsafe { MmapMut::map_mut(file) }
+}
+
+// Resizing changes the file and maps it again, the tape only resizes when a
+// growing tape reaches its end
+#[cfg(not(feature = "no_std"))]
+impl Tape for MmapTape {
+    fn get(&self, index: usize) -> u8 {
+        self.map[index]
+    }
+
+    fn set(&mut self, index: usize, value: u8) {
+        self.map[index] = value;
+    }
+
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    fn grow_front(&mut self, count: usize) {
+        let len = self.map.len();
+        self.resize(len + count);
+        self.map.copy_within(..len, count);
+        self.map[..count].fill(0);
+    }
+
+    fn resize(&mut self, len: usize) {
+        self.file
+            .set_len(len as u64)
+            .and_then(|_| map_file(&self.file))
+            .map(|map| self.map = map)
+            .expect("the tape file could not be resized");
+    }
+
+    fn find_zero_from(&self, index: usize) -> Option<usize> {
+        u8::find_zero(&self.map[index..]).map(|position| index + position)
+    }
+
+    fn rfind_zero_until(&self, index: usize) -> Option<usize> {
+        u8::rfind_zero(&self.map[..=index])
+    }
+}
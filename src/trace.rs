@@ -0,0 +1,104 @@
+//! Execution trace with one `key=value` record per executed instruction.
+
+use crate::profile::{command_index, INSTRUCTION_NAMES};
+use crate::Command::{self, *};
+use crate::NodeType::*;
+use crate::{
+    check_interrupt, check_timeout, count_step, execute_instruction, memory_index, Interpreter,
+    InterpreterError, Node,
+};
+use std::{fmt, io, time::Instant};
+
+pub struct TraceContext<'a> {
+    pub step: u64,
+    pub out: &'a mut dyn io::Write,
+}
+
+#[derive(Debug)]
+pub enum TraceError {
+    Interpreter(InterpreterError),
+    Io(io::Error),
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TraceError::Interpreter(e) => write!(f, "{}", e),
+            TraceError::Io(e) => write!(f, "failed writing trace: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+impl From<InterpreterError> for TraceError {
+    fn from(e: InterpreterError) -> Self {
+        TraceError::Interpreter(e)
+    }
+}
+
+impl From<io::Error> for TraceError {
+    fn from(e: io::Error) -> Self {
+        TraceError::Io(e)
+    }
+}
+
+/// Name of the command without spaces, arguments in parentheses
+pub fn trace_name(cmd: &Command) -> String {
+    let name = INSTRUCTION_NAMES[command_index(cmd)];
+    match cmd {
+        MoveBy(delta) => format!("{}({})", name, delta),
+        AddByte(delta) => format!("{}({})", name, delta),
+        AddTo { offset } => format!("{}({})", name, offset),
+        _ => name.to_string(),
+    }
+}
+
+/// Same as `run_program`, writing a trace record before every operator
+pub fn trace_run_program(
+    interpreter: &mut Interpreter,
+    ast: &Node,
+    trace: &mut TraceContext,
+) -> Result<(), TraceError> {
+    trace_nodes(interpreter, ast, trace, Instant::now())
+}
+
+// The children of `ast`, loops check the timeout of the run started at `start`
+fn trace_nodes(
+    interpreter: &mut Interpreter,
+    ast: &Node,
+    trace: &mut TraceContext,
+    start: Instant,
+) -> Result<(), TraceError> {
+    for node in ast.childrens.iter() {
+        let mut index = memory_index(interpreter);
+        match node.node_type {
+            Loop => {
+                while interpreter.memory[index] != 0 {
+                    trace_nodes(interpreter, node, trace, start)?;
+                    index = memory_index(interpreter);
+                    if interpreter.memory[index] != 0 {
+                        count_step(interpreter)?;
+                        check_interrupt(interpreter)?;
+                        check_timeout(interpreter, start)?;
+                    }
+                }
+            }
+            Operator => {
+                trace.step += 1;
+                writeln!(
+                    trace.out,
+                    "TRACE step={} cmd={} pointer={} cell[{}]={}",
+                    trace.step,
+                    trace_name(&node.instruction),
+                    interpreter.pointer,
+                    interpreter.cell_address(index),
+                    interpreter.memory[index]
+                )?;
+                execute_instruction(interpreter, &node.instruction, index)?
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,72 @@
+//! Read-only traversal of the abstract syntactic tree.
+
+use crate::NodeType::*;
+use crate::{Command, Node};
+
+/// Callbacks for every kind of node, the default implementations recurse into
+/// the children so an implementation only overrides the nodes it cares about
+pub trait Visitor {
+    fn visit_program(&mut self, node: &Node) {
+        walk_children(node, self);
+    }
+
+    fn visit_loop(&mut self, node: &Node) {
+        walk_children(node, self);
+    }
+
+    fn visit_operator(&mut self, _node: &Node, _cmd: Command) {}
+}
+
+/// Call the visitor method matching the kind of the node
+pub fn walk(node: &Node, visitor: &mut (impl Visitor + ?Sized)) {
+    match node.node_type {
+        Program => visitor.visit_program(node),
+        Loop => visitor.visit_loop(node),
+        Operator => visitor.visit_operator(node, node.instruction),
+    }
+}
+
+/// Walk every child of the node in order
+pub fn walk_children(node: &Node, visitor: &mut (impl Visitor + ?Sized)) {
+    for child in node.childrens.iter() {
+        walk(child, visitor);
+    }
+}
+
+/// Indented listing of the tree, one node per line
+#[derive(Default)]
+pub struct AstPrinter {
+    pub out: String,
+    depth: usize,
+}
+
+impl AstPrinter {
+    fn line(&mut self, node: &Node) {
+        self.out.push_str(&format!(
+            "{}{:?}  ---  {:?}\n",
+            " ".repeat(self.depth * 5),
+            node.node_type,
+            node.instruction
+        ));
+    }
+}
+
+impl Visitor for AstPrinter {
+    fn visit_loop(&mut self, node: &Node) {
+        self.line(node);
+        self.depth += 1;
+        walk_children(node, self);
+        self.depth -= 1;
+    }
+
+    fn visit_operator(&mut self, node: &Node, _cmd: Command) {
+        self.line(node);
+    }
+}
+
+/// Listing of the children of the program, nested loops indented
+pub fn print_ast(program: &Node) -> String {
+    let mut printer = AstPrinter::default();
+    walk(program, &mut printer);
+    printer.out
+}
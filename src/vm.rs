@@ -0,0 +1,441 @@
+//! Virtual machine running flat bytecode with a program counter instead of
+//! walking the tree recursively.
+
+use crate::bytecode::{check_jump_targets, Bytecode, BytecodeError};
+use crate::error::BrainfuckError;
+use crate::profile::command_index;
+use crate::snapshot::save_checkpoint;
+use crate::Command::{self, *};
+use crate::NodeType::*;
+use crate::{
+    check_interrupt, check_timeout, count_step, execute_instruction, memory_index, move_pointer,
+    Interpreter, InterpreterError, Node,
+};
+use std::{fmt, path::PathBuf, time::Instant};
+
+/// A bracket of commands without a source, at its index among them
+#[derive(Debug, PartialEq, Eq)]
+pub enum JumpTableError {
+    UnmatchedOpen { offset: usize },
+    UnmatchedClose { offset: usize },
+}
+
+impl fmt::Display for JumpTableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JumpTableError::UnmatchedOpen { offset } => {
+                write!(f, "unmatched '[' at offset {}", offset)
+            }
+            JumpTableError::UnmatchedClose { offset } => {
+                write!(f, "unmatched ']' at offset {}", offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for JumpTableError {}
+
+/// Index of the matching bracket for every `JumpForward` and `JumpBackward`,
+/// 0 for other commands. Commands have no source position, so an unmatched
+/// bracket is reported at its index among them
+pub fn build_jump_table(commands: &[Command]) -> Result<Vec<usize>, JumpTableError> {
+    let mut table = vec![0; commands.len()];
+    let mut stack: Vec<usize> = Vec::new();
+    for (position, command) in commands.iter().enumerate() {
+        match command {
+            JumpForward => stack.push(position),
+            JumpBackward => {
+                let start = stack
+                    .pop()
+                    .ok_or(JumpTableError::UnmatchedClose { offset: position })?;
+                table[start] = position;
+                table[position] = start;
+            }
+            _ => {}
+        }
+    }
+    match stack.first() {
+        Some(&start) => Err(JumpTableError::UnmatchedOpen { offset: start }),
+        None => Ok(table),
+    }
+}
+
+/// Virtual machine jumping straight to the targets stored in the bytecode
+pub struct FlatVm<'a> {
+    pub program: Vec<Bytecode>,
+    pub interpreter: &'a mut Interpreter,
+    pub pc: usize,
+}
+
+impl<'a> FlatVm<'a> {
+    /// Fails with the first jump whose target is not right after its matching bracket
+    pub fn new(
+        program: Vec<Bytecode>,
+        interpreter: &'a mut Interpreter,
+    ) -> Result<Self, BytecodeError> {
+        check_jump_targets(&program)?;
+        Ok(FlatVm {
+            program,
+            interpreter,
+            pc: 0,
+        })
+    }
+
+    /// Run from the current program counter until the end of the program
+    pub fn run(&mut self) -> Result<(), InterpreterError> {
+        let start = Instant::now();
+        while self.pc < self.program.len() {
+            let index = memory_index(self.interpreter);
+            match self.program[self.pc] {
+                // both jumps land right after the matching bracket
+                Bytecode::JumpForward(target) if self.interpreter.memory[index] == 0 => {
+                    self.pc = target as usize;
+                    continue;
+                }
+                Bytecode::JumpBackward(target) if self.interpreter.memory[index] != 0 => {
+                    count_step(self.interpreter)?;
+                    check_interrupt(self.interpreter)?;
+                    check_timeout(self.interpreter, start)?;
+                    self.pc = target as usize;
+                    continue;
+                }
+                Bytecode::JumpForward(_) | Bytecode::JumpBackward(_) => {}
+                instruction => {
+                    execute_instruction(self.interpreter, &instruction.command(), index)?
+                }
+            }
+            self.pc += 1;
+        }
+        Ok(())
+    }
+}
+
+// Append the commands of all children of the node, loops become bracket pairs
+fn flatten_nodes(node: &Node, commands: &mut Vec<Command>) {
+    for child in node.childrens.iter() {
+        match child.node_type {
+            Loop => {
+                commands.push(JumpForward);
+                flatten_nodes(child, commands);
+                commands.push(JumpBackward);
+            }
+            _ => commands.push(child.instruction),
+        }
+    }
+}
+
+/// Commands of the tree in execution order, optimized commands are kept as they are
+pub fn flatten_commands(ast: &Node) -> Vec<Command> {
+    let mut commands = Vec::new();
+    flatten_nodes(ast, &mut commands);
+    commands
+}
+
+/// Interpreter running the commands themselves with a program counter, the
+/// common commands change the tape and the pointer without going through
+/// `execute_instruction`
+pub struct FlatInterpreter<'a> {
+    pub commands: Vec<Command>,
+    pub jump_table: Vec<usize>,
+    pub interpreter: &'a mut Interpreter,
+    pub pc: usize,
+    pub checkpoints: Option<Checkpoints>, // None saves no checkpoint
+}
+
+/// A checkpoint saved to `path` every `interval` commands, each one replacing the last
+pub struct Checkpoints {
+    pub interval: u64,
+    pub path: PathBuf,
+    pub commands: u64, // run since the last checkpoint
+}
+
+impl Checkpoints {
+    pub fn new(interval: u64, path: PathBuf) -> Self {
+        Checkpoints {
+            interval,
+            path,
+            commands: 0,
+        }
+    }
+
+    // Count the command at `pc` and save a checkpoint before it runs once the interval is over
+    fn count(&mut self, interpreter: &Interpreter, pc: usize) -> Result<(), InterpreterError> {
+        self.commands += 1;
+        if self.commands > self.interval {
+            self.commands = 1;
+            save_checkpoint(&self.path, interpreter, pc)
+                .map_err(|error| InterpreterError::Checkpoint(error.kind()))?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> FlatInterpreter<'a> {
+    pub fn new(
+        commands: Vec<Command>,
+        interpreter: &'a mut Interpreter,
+    ) -> Result<Self, BrainfuckError> {
+        Ok(FlatInterpreter {
+            jump_table: build_jump_table(&commands)?,
+            commands,
+            interpreter,
+            pc: 0,
+            checkpoints: None,
+        })
+    }
+
+    /// Run from the current program counter until the end of the commands
+    pub fn run(&mut self) -> Result<(), InterpreterError> {
+        let start = Instant::now();
+        while self.pc < self.commands.len() {
+            let interpreter = &mut *self.interpreter;
+            if let Some(checkpoints) = &mut self.checkpoints {
+                checkpoints.count(interpreter, self.pc)?;
+            }
+            let index = memory_index(interpreter);
+            match self.commands[self.pc] {
+                JumpForward if interpreter.memory[index] == 0 => {
+                    self.pc = self.jump_table[self.pc];
+                }
+                JumpBackward if interpreter.memory[index] != 0 => {
+                    count_step(interpreter)?;
+                    check_interrupt(interpreter)?;
+                    check_timeout(interpreter, start)?;
+                    self.pc = self.jump_table[self.pc];
+                }
+                JumpForward | JumpBackward => {}
+                IncByte => {
+                    count_step(interpreter)?;
+                    interpreter.memory[index] = interpreter.memory[index].wrapping_add(1);
+                }
+                DecByte => {
+                    count_step(interpreter)?;
+                    interpreter.memory[index] = interpreter.memory[index].wrapping_sub(1);
+                }
+                AddByte(delta) => {
+                    count_step(interpreter)?;
+                    interpreter.memory[index] = interpreter.memory[index].wrapping_add(delta as u8);
+                }
+                SetZero => {
+                    count_step(interpreter)?;
+                    interpreter.memory[index] = 0;
+                }
+                MoveBy(delta) => {
+                    count_step(interpreter)?;
+                    interpreter.pointer += delta;
+                    move_pointer(interpreter, interpreter.pointer)?;
+                }
+                IncDP => {
+                    count_step(interpreter)?;
+                    interpreter.pointer += 1;
+                    move_pointer(interpreter, interpreter.pointer)?;
+                }
+                DecDP => {
+                    count_step(interpreter)?;
+                    interpreter.pointer -= 1;
+                    move_pointer(interpreter, interpreter.pointer)?;
+                }
+                command => execute_instruction(interpreter, &command, index)?,
+            }
+            self.pc += 1;
+        }
+        Ok(())
+    }
+
+    /// `run` calling the handler of every command from `DISPATCH` instead of
+    /// matching on it, checkpoints are not saved
+    pub fn run_dispatch(&mut self) -> Result<(), InterpreterError> {
+        let start = Instant::now();
+        let opcodes: Vec<usize> = self.commands.iter().map(command_index).collect();
+        while self.pc < self.commands.len() {
+            DISPATCH[opcodes[self.pc]](
+                self.interpreter,
+                &mut self.pc,
+                &self.commands,
+                &self.jump_table,
+                start,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs the command at `pc` of the commands, with their jump table and the time
+/// the run started, and moves `pc` to the next one
+pub type Handler =
+    fn(&mut Interpreter, &mut usize, &[Command], &[usize], Instant) -> Result<(), InterpreterError>;
+
+/// Number of kinds of `Command`, the length of `DISPATCH`
+pub const N_COMMANDS: usize = 20;
+
+/// Handler of every command, indexed by `command_index`
+pub const DISPATCH: [Handler; N_COMMANDS] = [
+    dispatch_other, // Default
+    dispatch_inc_dp,
+    dispatch_dec_dp,
+    dispatch_inc_byte,
+    dispatch_dec_byte,
+    dispatch_other, // OutByte
+    dispatch_other, // InByte
+    dispatch_jump_forward,
+    dispatch_jump_backward,
+    dispatch_move_by,
+    dispatch_add_byte,
+    dispatch_set_zero,
+    dispatch_other, // AddTo
+    dispatch_other, // ScanRight
+    dispatch_other, // ScanLeft
+    dispatch_other, // DebugPrint
+    dispatch_other, // Halt
+    dispatch_other, // DefineSubroutine
+    dispatch_other, // EndSubroutine
+    dispatch_other, // CallSubroutine
+];
+
+// Both jumps land on the matching bracket and continue after it
+fn dispatch_jump_forward(
+    interpreter: &mut Interpreter,
+    pc: &mut usize,
+    _commands: &[Command],
+    jump_table: &[usize],
+    _start: Instant,
+) -> Result<(), InterpreterError> {
+    if interpreter.memory[memory_index(interpreter)] == 0 {
+        *pc = jump_table[*pc];
+    }
+    *pc += 1;
+    Ok(())
+}
+
+fn dispatch_jump_backward(
+    interpreter: &mut Interpreter,
+    pc: &mut usize,
+    _commands: &[Command],
+    jump_table: &[usize],
+    start: Instant,
+) -> Result<(), InterpreterError> {
+    if interpreter.memory[memory_index(interpreter)] != 0 {
+        count_step(interpreter)?;
+        check_interrupt(interpreter)?;
+        check_timeout(interpreter, start)?;
+        *pc = jump_table[*pc];
+    }
+    *pc += 1;
+    Ok(())
+}
+
+fn dispatch_inc_byte(
+    interpreter: &mut Interpreter,
+    pc: &mut usize,
+    _commands: &[Command],
+    _jump_table: &[usize],
+    _start: Instant,
+) -> Result<(), InterpreterError> {
+    count_step(interpreter)?;
+    let index = memory_index(interpreter);
+    interpreter.memory[index] = interpreter.memory[index].wrapping_add(1);
+    *pc += 1;
+    Ok(())
+}
+
+fn dispatch_dec_byte(
+    interpreter: &mut Interpreter,
+    pc: &mut usize,
+    _commands: &[Command],
+    _jump_table: &[usize],
+    _start: Instant,
+) -> Result<(), InterpreterError> {
+    count_step(interpreter)?;
+    let index = memory_index(interpreter);
+    interpreter.memory[index] = interpreter.memory[index].wrapping_sub(1);
+    *pc += 1;
+    Ok(())
+}
+
+fn dispatch_add_byte(
+    interpreter: &mut Interpreter,
+    pc: &mut usize,
+    commands: &[Command],
+    _jump_table: &[usize],
+    _start: Instant,
+) -> Result<(), InterpreterError> {
+    if let AddByte(delta) = commands[*pc] {
+        count_step(interpreter)?;
+        let index = memory_index(interpreter);
+        interpreter.memory[index] = interpreter.memory[index].wrapping_add(delta as u8);
+    }
+    *pc += 1;
+    Ok(())
+}
+
+fn dispatch_set_zero(
+    interpreter: &mut Interpreter,
+    pc: &mut usize,
+    _commands: &[Command],
+    _jump_table: &[usize],
+    _start: Instant,
+) -> Result<(), InterpreterError> {
+    count_step(interpreter)?;
+    let index = memory_index(interpreter);
+    interpreter.memory[index] = 0;
+    *pc += 1;
+    Ok(())
+}
+
+fn dispatch_move_by(
+    interpreter: &mut Interpreter,
+    pc: &mut usize,
+    commands: &[Command],
+    _jump_table: &[usize],
+    _start: Instant,
+) -> Result<(), InterpreterError> {
+    if let MoveBy(delta) = commands[*pc] {
+        count_step(interpreter)?;
+        interpreter.pointer += delta;
+        move_pointer(interpreter, interpreter.pointer)?;
+    }
+    *pc += 1;
+    Ok(())
+}
+
+fn dispatch_inc_dp(
+    interpreter: &mut Interpreter,
+    pc: &mut usize,
+    _commands: &[Command],
+    _jump_table: &[usize],
+    _start: Instant,
+) -> Result<(), InterpreterError> {
+    count_step(interpreter)?;
+    interpreter.pointer += 1;
+    move_pointer(interpreter, interpreter.pointer)?;
+    *pc += 1;
+    Ok(())
+}
+
+fn dispatch_dec_dp(
+    interpreter: &mut Interpreter,
+    pc: &mut usize,
+    _commands: &[Command],
+    _jump_table: &[usize],
+    _start: Instant,
+) -> Result<(), InterpreterError> {
+    count_step(interpreter)?;
+    interpreter.pointer -= 1;
+    move_pointer(interpreter, interpreter.pointer)?;
+    *pc += 1;
+    Ok(())
+}
+
+// Every other command goes through execute_instruction
+fn dispatch_other(
+    interpreter: &mut Interpreter,
+    pc: &mut usize,
+    commands: &[Command],
+    _jump_table: &[usize],
+    _start: Instant,
+) -> Result<(), InterpreterError> {
+    let index = memory_index(interpreter);
+    execute_instruction(interpreter, &commands[*pc], index)?;
+    *pc += 1;
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use brainfuck::{
+    arena::{create_arena, run_arena, Arena, NONE},
+    interpreter_init, lexical_analysis_str,
+    optimizer::optimize,
+    run_program, Command, InterpreterError, NodeType,
+};
+
+mod common;
+use common::parse;
+
+#[test]
+fn arena_links_children_and_siblings() -> Result<(), Box<dyn std::error::Error>> {
+    let (arena, root) = create_arena(&lexical_analysis_str("+[->]<"));
+    assert_eq!(root, 0);
+    assert_eq!(arena.nodes.len(), 6);
+    let children: Vec<u32> = arena.children(root).collect();
+    assert_eq!(children, [1, 2, 5]);
+    assert_eq!(arena.node(2).node_type, NodeType::Loop);
+    assert_eq!(arena.node(2).source_pos, 1);
+    let body: Vec<Command> = arena
+        .children(2)
+        .map(|child| arena.node(child).instruction)
+        .collect();
+    assert_eq!(body, [Command::DecByte, Command::IncDP]);
+    assert_eq!(arena.node(5).next_sibling, NONE);
+    assert_eq!(arena.children(1).count(), 0);
+
+    // the arena of the tree is the one built from the commands
+    assert_eq!(Arena::from_node(&parse("+[->]<")?), (arena, root));
+
+    Ok(())
+}
+
+#[test]
+fn arena_runs_like_the_tree_walker() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "++++++[->++++++++<]>+[>+<-]>>+++[-<+<+>>]<<<[>]+[[-]>+[<]>>]";
+    for optimized in [false, true] {
+        let mut ast = parse(source)?;
+        if optimized {
+            optimize(&mut ast);
+        }
+        let mut expected = interpreter_init(16);
+        run_program(&mut expected, &ast)?;
+        let mut actual = interpreter_init(16);
+        let (arena, root) = Arena::from_node(&ast);
+        run_arena(&mut actual, &arena, root)?;
+        assert_eq!(actual.memory, expected.memory);
+        assert_eq!(actual.pointer, expected.pointer);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn arena_loops_stop_at_the_iteration_limit() -> Result<(), Box<dyn std::error::Error>> {
+    let (arena, root) = Arena::from_node(&parse("+++[-] +[]")?);
+    let mut interpreter = interpreter_init(4);
+    interpreter.max_loop_iters = Some(3);
+    assert_eq!(
+        run_arena(&mut interpreter, &arena, root),
+        Err(InterpreterError::LoopIterationLimitExceeded { offset: 8 })
+    );
+
+    Ok(())
+}
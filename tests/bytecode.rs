@@ -0,0 +1,119 @@
+use brainfuck::{
+    bytecode::{
+        bytecode_to_ast, compile_bytecode, deserialize_bytecode, serialize_bytecode, Bytecode,
+        BytecodeError,
+    },
+    interpreter_init,
+    optimizer::optimize,
+    run_program,
+};
+
+mod common;
+use common::parse;
+
+fn compile(source: &str) -> Result<Vec<Bytecode>, Box<dyn std::error::Error>> {
+    let mut ast = parse(source)?;
+    optimize(&mut ast);
+    Ok(compile_bytecode(&ast))
+}
+
+#[test]
+fn loops_get_jump_targets() -> Result<(), Box<dyn std::error::Error>> {
+    assert_eq!(
+        compile("+[>[-]<[>]-]")?,
+        vec![
+            Bytecode::AddBy(1),
+            Bytecode::JumpForward(8),
+            Bytecode::MoveBy(1),
+            Bytecode::SetZero,
+            Bytecode::MoveBy(-1),
+            Bytecode::ScanRight,
+            Bytecode::AddBy(-1),
+            Bytecode::JumpBackward(2),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn serialization_round_trips() -> Result<(), Box<dyn std::error::Error>> {
+    let program = vec![
+        Bytecode::MoveBy(-70000),
+        Bytecode::AddBy(-128),
+        Bytecode::AddBy(127),
+        Bytecode::AddTo { offset: 3 },
+        Bytecode::JumpForward(7),
+        Bytecode::Output,
+        Bytecode::JumpBackward(5),
+        Bytecode::Input,
+        Bytecode::ScanLeft,
+        Bytecode::SetZero,
+        Bytecode::DebugPrint,
+        Bytecode::Halt,
+    ];
+    let bytes = serialize_bytecode(&program);
+    assert!(bytes.starts_with(b"BF\x01\x01"));
+    assert_eq!(deserialize_bytecode(&bytes)?, program);
+
+    Ok(())
+}
+
+#[test]
+fn corrupt_bytecode_is_rejected() -> Result<(), Box<dyn std::error::Error>> {
+    let bytes = serialize_bytecode(&compile("+[-]>[<+>-]")?);
+    assert_eq!(
+        deserialize_bytecode(b"BF"),
+        Err(BytecodeError::InvalidMagic)
+    );
+    assert_eq!(
+        deserialize_bytecode(b"BF\x01\x07\x00"),
+        Err(BytecodeError::UnsupportedVersion(7))
+    );
+    assert_eq!(
+        deserialize_bytecode(&bytes[..bytes.len() - 1]),
+        Err(BytecodeError::UnexpectedEnd)
+    );
+    assert_eq!(
+        deserialize_bytecode(b"BF\x01\x01\x01\x0f"),
+        Err(BytecodeError::UnknownOpcode(15))
+    );
+    let unmatched = serialize_bytecode(&[Bytecode::JumpForward(1)]);
+    assert_eq!(
+        deserialize_bytecode(&unmatched),
+        Err(BytecodeError::InvalidJumpTarget { position: 0 })
+    );
+    let wrong_target = serialize_bytecode(&[Bytecode::JumpForward(2), Bytecode::JumpBackward(0)]);
+    assert_eq!(
+        deserialize_bytecode(&wrong_target),
+        Err(BytecodeError::InvalidJumpTarget { position: 1 })
+    );
+    let wrong_target = serialize_bytecode(&[Bytecode::JumpForward(5), Bytecode::JumpBackward(1)]);
+    assert_eq!(
+        deserialize_bytecode(&wrong_target),
+        Err(BytecodeError::InvalidJumpTarget { position: 0 })
+    );
+    let unmatched = serialize_bytecode(&[Bytecode::SetZero, Bytecode::JumpBackward(0)]);
+    assert_eq!(
+        deserialize_bytecode(&unmatched),
+        Err(BytecodeError::InvalidJumpTarget { position: 1 })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn rebuilt_ast_runs_like_the_original() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "++++++[->++++++++<]>+[>+<-]>>+++[-<+<+>>]<<<[>]";
+    let ast = parse(source)?;
+    let program = deserialize_bytecode(&serialize_bytecode(&compile(source)?))?;
+
+    let mut expected = interpreter_init(16);
+    run_program(&mut expected, &ast)?;
+    let mut actual = interpreter_init(16);
+    run_program(&mut actual, &bytecode_to_ast(&program))?;
+    assert_eq!(actual.memory, expected.memory);
+    assert_eq!(actual.pointer, expected.pointer);
+
+    Ok(())
+}
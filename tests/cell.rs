@@ -0,0 +1,116 @@
+use std::{cell::RefCell, io, io::Write, rc::Rc};
+
+use brainfuck::{
+    cell::{CellArithmetic, CellSize, SaturatingU8},
+    debug_print, interpreter_init, run_program, run_with_cell_size, Interpreter,
+};
+
+mod common;
+use common::parse;
+
+// Output shared with the test after the interpreter took ownership of it
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn cells_wrap_at_their_size() {
+    assert_eq!(0u8.dec(), 255);
+    assert_eq!(0u16.dec(), 65535);
+    assert_eq!(u32::MAX.inc(), 0);
+    assert_eq!(i8::MAX.inc(), i8::MIN);
+    assert_eq!(300u16.add(-1), 299);
+    assert_eq!(250u8.add_cell(10), 4);
+}
+
+#[test]
+fn wide_cells_read_and_write_bytes() {
+    assert_eq!(u16::from_input(200), 200);
+    assert_eq!(0x1234u16.to_output(), 0x34);
+    assert_eq!(i8::from_input(255), -1);
+    assert_eq!((-1i8).to_output(), 255);
+}
+
+#[test]
+fn generic_interpreter_runs_wide_cells() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = interpreter_init(8).with_cells::<u32>();
+    run_program(&mut interpreter, &parse("->+++[-<->]")?)?;
+    assert_eq!(interpreter.memory[0], u32::MAX - 3);
+
+    Ok(())
+}
+
+#[test]
+fn tape_keeps_low_bytes_after_a_wide_run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = interpreter_init(8);
+    interpreter.output = Box::new(Vec::new());
+    run_with_cell_size(&mut interpreter, &parse("->+")?, CellSize::Bits16)?;
+    assert_eq!(interpreter.memory[0], 255);
+    assert_eq!(interpreter.pointer, 1);
+
+    Ok(())
+}
+
+#[test]
+fn sixteen_bit_cells_count_past_255() -> Result<(), Box<dyn std::error::Error>> {
+    // 256 `+` leave a byte at 0 and skip the loop, a 16-bit cell holds 256 and prints '2'
+    let ast = parse(&format!("{}[>+++++++[<+++++++>-]<+.[-]]", "+".repeat(256)))?;
+
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let mut bytes = interpreter_init(8);
+    bytes.output = Box::new(SharedOutput(Rc::clone(&output)));
+    run_program(&mut bytes, &ast)?;
+    assert_eq!(*output.borrow(), b"");
+
+    let mut wide = Interpreter::<u16>::new(8);
+    wide.output = Box::new(SharedOutput(Rc::clone(&output)));
+    run_program(&mut wide, &ast)?;
+    assert_eq!(*output.borrow(), b"2");
+    assert_eq!(wide.memory[0], 0);
+
+    Ok(())
+}
+
+#[test]
+fn signed_cells_wrap_from_127_to_minus_128() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse(&format!("{}>+<", "+".repeat(128)))?;
+    let mut signed = Interpreter::<i8>::new(4);
+    run_program(&mut signed, &ast)?;
+    let mut bytes = interpreter_init(4);
+    run_program(&mut bytes, &ast)?;
+    assert_eq!((signed.memory[0], bytes.memory[0]), (-128, 128));
+    assert_eq!(signed.memory[0].to_output(), bytes.memory[0]);
+    // unsigned cells wrap at 255 instead
+    assert_eq!(255u8.inc(), 0);
+    assert_eq!(127i8.inc(), -128);
+    assert!(debug_print(&signed, 0).contains("-128   1"));
+
+    Ok(())
+}
+
+#[test]
+fn saturating_cells_stop_at_their_limits() -> Result<(), Box<dyn std::error::Error>> {
+    assert_eq!(SaturatingU8(255).inc(), SaturatingU8(255));
+    assert_eq!(SaturatingU8(0).dec(), SaturatingU8(0));
+    assert_eq!(SaturatingU8(250).add(10), SaturatingU8(255));
+    assert_eq!(SaturatingU8(3).add(-10), SaturatingU8(0));
+
+    // `[-]` counts down to zero and ends like it does on wrapping bytes
+    let mut interpreter = interpreter_init(4);
+    run_with_cell_size(
+        &mut interpreter,
+        &parse("-+++[-]>++++[->+++<]")?,
+        CellSize::Saturating8,
+    )?;
+    assert_eq!(interpreter.memory, [0, 0, 12, 0]);
+
+    Ok(())
+}
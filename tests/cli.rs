@@ -27,6 +27,118 @@ fn hello_world() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn ook_dialect() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.ook")
+        .arg("--dialect=ook")
+        .assert()
+        .success()
+        .stdout("Hello World!\n");
+
+    Ok(())
+}
+
+#[test]
+fn allow_extensions() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/debug-print.txt")
+        .arg("--allow-extensions")
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("pointer 1, cells 0 to 6\n   2   3   0   0   0   0   0\n       ^\n");
+
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/debug-print.txt")
+        .assert()
+        .success()
+        .stderr("");
+
+    Ok(())
+}
+
+#[test]
+fn halt_instruction() -> Result<(), Box<dyn std::error::Error>> {
+    for engine in ["tree", "vm", "flat"] {
+        let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+        cmd.arg("--file")
+            .arg("./test_files/halt.txt")
+            .arg("--allow-extensions")
+            .arg("--engine")
+            .arg(engine)
+            .assert()
+            .success()
+            .stdout("Hi")
+            .stderr("");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn subroutines() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/subroutine.txt")
+        .arg("--allow-extensions")
+        .assert()
+        .success()
+        .stdout("ABC");
+
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/subroutine.txt")
+        .arg("--allow-extensions")
+        .arg("--engine=vm")
+        .assert()
+        .failure()
+        .stderr("error: subroutines only run on the plain tree engine\n");
+
+    Ok(())
+}
+
+#[test]
+fn multiple_files() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/set-a.txt")
+        .arg("./test_files/print-three.txt")
+        .assert()
+        .success()
+        .stdout("ABC");
+
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("-f")
+        .arg("./test_files/print-three.txt")
+        .arg("-f")
+        .arg("./test_files/set-a.txt")
+        .assert()
+        .success()
+        .stdout("\u{0}\u{1}\u{2}");
+
+    Ok(())
+}
+
+#[test]
+fn errors_point_into_the_joined_files() -> Result<(), Box<dyn std::error::Error>> {
+    // the two lines of set-a.txt and the newline joining the files come first
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/set-a.txt")
+        .arg("./test_files/unmatched-close.txt")
+        .assert()
+        .failure()
+        .stderr(predicates::str::starts_with(
+            "error: unmatched ']'\n --> line 5, column 2\n  |\n5 | ]]\n",
+        ));
+
+    Ok(())
+}
+
 #[test]
 fn zero_to_ninety_nine() -> Result<(), Box<dyn std::error::Error>> {
     let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
@@ -55,3 +167,1426 @@ fn cat() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn overflow_wrap() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/pointer-underflow.txt")
+        .assert()
+        .success()
+        .stdout("\u{1}");
+
+    Ok(())
+}
+
+#[test]
+fn overflow_error() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/pointer-underflow.txt")
+        .arg("--overflow=error")
+        .assert()
+        .failure()
+        .stdout("");
+
+    Ok(())
+}
+
+#[test]
+fn memory_size() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--memory-size=16")
+        .assert()
+        .success()
+        .stdout("Hello World!\n");
+
+    Ok(())
+}
+
+#[test]
+fn invalid_memory_size() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--memory-size=0")
+        .assert()
+        .failure()
+        .stdout("");
+
+    Ok(())
+}
+
+#[test]
+fn repl_keeps_tape() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--repl")
+        .write_stdin("++++++++[>++++++++<-]>+.\n[\n+.\nquit\n")
+        .assert()
+        .success()
+        .stdout("AB")
+        .stderr(predicates::str::contains(
+            "error: unmatched '[' at line 1, column 1",
+        ))
+        .stderr(predicates::str::contains("cell[1]=66"));
+
+    Ok(())
+}
+
+#[test]
+fn optimized_hello_world() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--optimize")
+        .assert()
+        .success()
+        .stdout("Hello World!\n");
+
+    Ok(())
+}
+
+#[test]
+fn vm_engine() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    let reference_output = read_file_to_string("./test_outputs/print-0-to-99-result.txt")?;
+
+    cmd.arg("--file")
+        .arg("./test_files/print-0-to-99.txt")
+        .arg("--engine")
+        .arg("vm")
+        .assert()
+        .success()
+        .stdout(reference_output);
+
+    Ok(())
+}
+
+#[test]
+fn flat_engine() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    let reference_output = read_file_to_string("./test_outputs/print-0-to-99-result.txt")?;
+
+    cmd.arg("--file")
+        .arg("./test_files/print-0-to-99.txt")
+        .arg("--engine")
+        .arg("flat")
+        .assert()
+        .success()
+        .stdout(reference_output);
+
+    Ok(())
+}
+
+#[test]
+fn profile_report() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--profile")
+        .assert()
+        .success()
+        .stdout("Hello World!\n")
+        .stderr(predicates::str::contains("OutByte"))
+        .stderr(predicates::str::contains("total steps: 809"));
+
+    Ok(())
+}
+
+#[test]
+fn heatmap_report() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--heatmap")
+        .assert()
+        .success()
+        .stdout("Hello World!\n")
+        .stderr(predicates::str::contains("reads"))
+        .stderr(predicates::str::contains("writes"));
+
+    Ok(())
+}
+
+#[test]
+fn hotspot_report() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--hotspot")
+        .assert()
+        .success()
+        .stdout("Hello World!\n")
+        .stderr(predicates::str::starts_with(
+            "offset    command            count\n43        [                     48\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn coverage_report() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("coverage-{}.txt", std::process::id()));
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--coverage")
+        .arg("--coverage-annotate")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("Hello World!\n")
+        .stderr("covered: 103 instructions\nuncovered: 0 instructions\n");
+    // every instruction ran, so there is no line of marks
+    assert_eq!(
+        std::fs::read_to_string(&path)?,
+        std::fs::read_to_string("./test_files/hello-world.txt")?
+    );
+    std::fs::remove_file(&path)?;
+
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .args(["--coverage", "--optimize"])
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn trace_to_stderr() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--trace")
+        .assert()
+        .success()
+        .stdout("Hello World!\n")
+        .stderr(predicates::str::starts_with(
+            "TRACE step=1 cmd=IncByte pointer=0 cell[0]=0\n",
+        ))
+        .stderr(predicates::str::contains("TRACE step=809 "));
+
+    Ok(())
+}
+
+#[test]
+fn bytecode_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    let output = cmd
+        .arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--optimize")
+        .arg("--emit=bytecode")
+        .output()?;
+    assert!(output.stdout.starts_with(b"BF\x01"));
+
+    let path = std::env::temp_dir().join(format!("hello-world-{}.bfc", std::process::id()));
+    std::fs::write(&path, &output.stdout)?;
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--run-bytecode")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("Hello World!\n");
+    std::fs::remove_file(&path)?;
+
+    Ok(())
+}
+
+#[test]
+fn run_bytecode_refuses_jumps_off_their_brackets() -> Result<(), Box<dyn std::error::Error>> {
+    // "[]" with the '[' jumping 5 instructions ahead instead of past the ']'
+    let path = std::env::temp_dir().join(format!("bad-jump-{}.bfc", std::process::id()));
+    std::fs::write(&path, b"BF\x01\x01\x02\x08\x05\x09\x01")?;
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--run-bytecode")
+        .arg(&path)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "invalid jump target of instruction 0",
+        ));
+    std::fs::remove_file(&path)?;
+
+    Ok(())
+}
+
+#[test]
+fn snapshot_continues_a_run() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("hello-world-{}.snap", std::process::id()));
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--save-snapshot")
+        .arg(&path)
+        .assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--repl")
+        .arg("--load-snapshot")
+        .arg(&path)
+        .write_stdin("<<<<.\n")
+        .assert()
+        .success()
+        .stdout("H")
+        .stderr(predicates::str::contains(
+            "pointer=2 nonzero cells: cell[2]=72",
+        ));
+    std::fs::remove_file(&path)?;
+
+    Ok(())
+}
+
+#[test]
+fn eof_behavior() -> Result<(), Box<dyn std::error::Error>> {
+    for (eof, expected) in [("0", 0u8), ("255", 255), ("-1", 255), ("unchanged", 1)] {
+        let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+        cmd.arg("--file")
+            .arg("./test_files/read-after-eof.txt")
+            .arg("--eof")
+            .arg(eof)
+            .write_stdin("")
+            .assert()
+            .success()
+            .stdout(predicates::ord::eq(vec![expected]));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn input_string() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/cat.txt")
+        .arg("--input-string")
+        .arg("a\\tb\\x41\\n")
+        .assert()
+        .success()
+        .stdout("a\tbA\n");
+
+    Ok(())
+}
+
+#[test]
+fn output_to_file() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("hello-world-{}.out", std::process::id()));
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--output")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(
+        read_file_to_string(path.to_str().unwrap())?,
+        "Hello World!\n"
+    );
+    std::fs::remove_file(&path)?;
+
+    Ok(())
+}
+
+#[test]
+fn output_format_decimal() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/read-after-eof.txt")
+        .arg("--eof=255")
+        .arg("--output-format=decimal")
+        .write_stdin("")
+        .assert()
+        .success()
+        .stdout("255\n");
+
+    Ok(())
+}
+
+#[test]
+fn dynamic_tape() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--memory-size=1")
+        .arg("--dynamic-tape")
+        .assert()
+        .success()
+        .stdout("Hello World!\n");
+
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--memory-size=1")
+        .arg("--dynamic-tape")
+        .arg("--max-tape-size=4")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "error: pointer 4 is beyond the maximum tape size of 4 cells",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn bidirectional_tape() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/pointer-underflow.txt")
+        .arg("--memory-size=1")
+        .arg("--bidirectional-tape")
+        .arg("--output-format=decimal")
+        .assert()
+        .success()
+        .stdout("1\n");
+
+    Ok(())
+}
+
+#[test]
+fn syntax_error_position() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/unmatched-close.txt")
+        .assert()
+        .failure()
+        .stderr(
+            "error: unmatched ']'\n --> line 2, column 2\n  |\n2 | ]]\n  |  ^\n\
+             error: unmatched ']'\n --> line 3, column 5\n  |\n3 | [ ] ]\n  |     ^\n",
+        );
+
+    Ok(())
+}
+
+#[test]
+fn color_always() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--color=always")
+        .arg("--file")
+        .arg("./test_files/unmatched-close.txt")
+        .assert()
+        .failure()
+        .stderr(predicates::str::starts_with(
+            "\u{1b}[0m\u{1b}[1m\u{1b}[31merror\u{1b}[0m: unmatched ']'\n --> line 2, column 2\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn color_never() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--color=never")
+        .arg("--file")
+        .arg("./test_files/unmatched-close.txt")
+        .assert()
+        .failure()
+        .stderr(
+            "error: unmatched ']'\n --> line 2, column 2\n  |\n2 | ]]\n  |  ^\n\
+             error: unmatched ']'\n --> line 3, column 5\n  |\n3 | [ ] ]\n  |     ^\n",
+        );
+
+    Ok(())
+}
+
+#[test]
+fn warn_nops() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/nops.txt")
+        .arg("--warn-nops")
+        .assert()
+        .success()
+        .stdout("\u{1}")
+        .stderr(
+            "warning at offset 1: adjacent canceling instructions '+-'\n\
+             warning at offset 3: adjacent canceling instructions '><'\n",
+        );
+
+    Ok(())
+}
+
+#[test]
+fn warn_infinite_loops() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--warn-infinite-loops")
+        .assert()
+        .success()
+        .stdout("Hello World!\n")
+        .stderr("");
+
+    Ok(())
+}
+
+#[test]
+fn warn_no_output() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/set-a.txt")
+        .arg("--warn-no-output")
+        .assert()
+        .success()
+        .stderr("warning: program produces no output\n");
+
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--warn")
+        .assert()
+        .success()
+        .stdout("Hello World!\n")
+        .stderr("");
+
+    Ok(())
+}
+
+#[test]
+fn verbose_optimizer() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--optimize")
+        .arg("--verbose")
+        .assert()
+        .success()
+        .stdout("Hello World!\n")
+        .stderr(predicates::str::contains("eliminated 0 dead nodes"));
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn dump_ast_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/pointer-underflow.txt")
+        .arg("--dump-ast=json")
+        .assert()
+        .success()
+        .stdout(predicates::str::starts_with(
+            "{\"node_type\":\"Program\",\"instruction\":\"Default\"",
+        ));
+
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+#[test]
+fn dump_ast_needs_serde() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/pointer-underflow.txt")
+        .arg("--dump-ast=json")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("serde feature"));
+
+    Ok(())
+}
+
+#[cfg(feature = "jit")]
+#[test]
+fn emit_jit_runs_native_code() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .args(["--emit=jit", "--optimize"])
+        .assert()
+        .success()
+        .stdout("Hello World!\n");
+
+    Ok(())
+}
+
+#[cfg(feature = "jit")]
+#[test]
+fn emit_jit_stops_at_the_timeout() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/empty-loop.txt")
+        .args(["--emit=jit", "--timeout=0.1"])
+        .assert()
+        .failure()
+        .stderr("error: execution timed out\n");
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/empty-loop.txt")
+        .args(["--emit=jit", "--max-steps=100"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("cannot stop at --max-steps"));
+
+    Ok(())
+}
+
+#[cfg(not(feature = "jit"))]
+#[test]
+fn emit_jit_needs_the_jit_feature() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--emit=jit")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("jit feature"));
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn load_ast_runs_a_dumped_tree() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    let output = cmd
+        .arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--optimize")
+        .arg("--dump-ast=json")
+        .output()?;
+
+    let path = std::env::temp_dir().join(format!("hello-world-{}.json", std::process::id()));
+    std::fs::write(&path, &output.stdout)?;
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--load-ast")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("Hello World!\n");
+    std::fs::remove_file(&path)?;
+
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--load-ast")
+        .arg("./test_files/loop-root.json")
+        .assert()
+        .failure()
+        .stderr("error: the root of the syntax tree is a Loop node, expected Program\n");
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn config_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/halt.txt")
+        .arg("--config")
+        .arg("./test_files/config.toml")
+        .assert()
+        .success()
+        .stdout("72\n105\n");
+
+    // the command line wins over the file
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/halt.txt")
+        .arg("--config")
+        .arg("./test_files/config.toml")
+        .arg("--output-format=hex")
+        .assert()
+        .success()
+        .stdout("0x48\n0x69\n");
+
+    let path = std::env::temp_dir().join(format!("config-{}.toml", std::process::id()));
+    std::fs::write(&path, "optimize = yes\n")?;
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/halt.txt")
+        .arg("--config")
+        .arg(&path)
+        .assert()
+        .failure()
+        .stderr(predicates::str::starts_with("error: invalid config file"));
+    std::fs::remove_file(&path)?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+#[test]
+fn config_needs_serde() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/halt.txt")
+        .arg("--config")
+        .arg("./test_files/config.toml")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("serde feature"));
+
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[test]
+fn emit_elf_writes_an_executable() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("hello-world-{}", std::process::id()));
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .args(["--optimize", "--emit=elf", "--output"])
+        .arg(&path)
+        .assert()
+        .success();
+    let output = std::process::Command::new(&path).output()?;
+    std::fs::remove_file(&path)?;
+    assert_eq!(output.stdout, b"Hello World!\n");
+
+    Ok(())
+}
+
+#[test]
+fn emit_wasm_writes_a_binary_module() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    let output = cmd
+        .arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--emit=wasm")
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(&output.stdout[..4], b"\0asm");
+
+    Ok(())
+}
+
+#[test]
+fn emit_bf() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/nops.txt")
+        .arg("--optimize")
+        .arg("--emit=bf")
+        .assert()
+        .success()
+        .stdout("+.\n");
+
+    Ok(())
+}
+
+#[test]
+fn max_loop_depth() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("deep-loops-{}.b", std::process::id()));
+    std::fs::write(&path, "+[[[-]]]")?;
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg(&path)
+        .arg("--max-loop-depth=3")
+        .arg("--verbose")
+        .assert()
+        .success()
+        .stderr("loop nesting depth 3\n");
+
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg(&path)
+        .arg("--max-loop-depth=2")
+        .assert()
+        .failure()
+        .stderr("error: loop nesting depth 3 exceeds limit\n");
+    std::fs::remove_file(&path)?;
+
+    Ok(())
+}
+
+#[test]
+fn max_steps() -> Result<(), Box<dyn std::error::Error>> {
+    for engine in ["tree", "vm", "flat"] {
+        let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+        cmd.arg("--file")
+            .arg("./test_files/empty-loop.txt")
+            .arg("--max-steps=100")
+            .arg("--engine")
+            .arg(engine)
+            .assert()
+            .failure()
+            .stderr("halted: step limit of 100 exceeded\n");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn max_steps_while_tracing() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/empty-loop.txt")
+        .arg("--max-steps=100")
+        .arg("--trace")
+        .assert()
+        .failure()
+        .stderr(predicates::str::ends_with(
+            "halted: step limit of 100 exceeded\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn progress_needs_a_terminal() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--progress")
+        .arg("--progress-interval=10")
+        .assert()
+        .success()
+        .stdout("Hello World!\n")
+        .stderr("");
+
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--progress")
+        .arg("--progress-interval=0")
+        .assert()
+        .failure()
+        .stderr("error: invalid progress interval '0': expected a positive number\n");
+
+    Ok(())
+}
+
+#[test]
+fn timeout() -> Result<(), Box<dyn std::error::Error>> {
+    for engine in ["tree", "vm", "flat"] {
+        let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+        cmd.arg("--file")
+            .arg("./test_files/empty-loop.txt")
+            .arg("--timeout=0.1")
+            .arg("--engine")
+            .arg(engine)
+            .assert()
+            .failure()
+            .stderr("error: execution timed out\n");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn timeout_while_profiling_or_tracing() -> Result<(), Box<dyn std::error::Error>> {
+    for flag in ["--profile", "--trace", "--heatmap"] {
+        let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+        cmd.arg("--file")
+            .arg("./test_files/empty-loop.txt")
+            .arg("--timeout=0.1")
+            .arg(flag)
+            .timeout(std::time::Duration::from_secs(10))
+            .assert()
+            .failure()
+            .stderr(predicates::str::contains("execution timed out"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn max_loop_iters() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--max-loop-iters=20")
+        .assert()
+        .success()
+        .stdout("Hello World!\n");
+
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/empty-loop.txt")
+        .arg("--max-loop-iters=1000")
+        .assert()
+        .failure()
+        .stderr(
+            "error: loop at source offset 1 exceeded the iteration limit\n\
+             \x20--> line 1, column 2\n  |\n1 | +[]\n  |  ^\n",
+        );
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn ctrl_c_interrupts() -> Result<(), Box<dyn std::error::Error>> {
+    let child = std::process::Command::new(assert_cmd::cargo::cargo_bin("brainfuck_interpreter"))
+        .arg("--file")
+        .arg("./test_files/empty-loop.txt")
+        .arg("--dump-on-interrupt")
+        .stderr(std::process::Stdio::piped())
+        .spawn()?;
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    std::process::Command::new("kill")
+        .arg("-INT")
+        .arg(child.id().to_string())
+        .status()?;
+    let output = child.wait_with_output()?;
+    assert_eq!(output.status.code(), Some(130));
+    assert_eq!(
+        String::from_utf8(output.stderr)?,
+        "execution interrupted by user\npointer=0 nonzero cells: cell[0]=1\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn exit_from_tape() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/read-after-eof.txt")
+        .arg("--eof=unchanged")
+        .arg("--exit-from-tape")
+        .write_stdin("")
+        .assert()
+        .code(1);
+
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/exit-150.txt")
+        .arg("--exit-from-tape")
+        .assert()
+        .code(125);
+
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--exit-from-tape")
+        .assert()
+        .code(0)
+        .stdout("Hello World!\n");
+
+    Ok(())
+}
+
+#[test]
+fn minify_prints_source() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/nops.txt")
+        .arg("--minify")
+        .assert()
+        .success()
+        .stdout("+.\n");
+
+    Ok(())
+}
+
+#[test]
+fn metrics_report() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .args(["--metrics", "--optimize"])
+        .assert()
+        .success()
+        .stdout("Hello World!\n")
+        .stderr(predicates::str::contains("loops                      2\n"))
+        .stderr(predicates::str::contains("I/O instructions          13\n"));
+
+    Ok(())
+}
+
+#[test]
+fn check_equiv() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/cat.txt")
+        .args(["--check-equiv", "./test_files/cat.txt"])
+        .assert()
+        .success()
+        .stdout("programs are equivalent on all test inputs\n");
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/cat.txt")
+        .args(["--check-equiv", "./test_files/increment-input.txt"])
+        .args(["--equiv-input", "A"])
+        .assert()
+        .failure()
+        .stdout(
+            "programs diverge on input \"A\"\n./test_files/cat.txt: \"A\"\n\
+             ./test_files/increment-input.txt: \"B\"\n",
+        );
+
+    Ok(())
+}
+
+#[test]
+fn dry_run_reports_without_running() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stdout("nodes: 103\nnodes after optimization: 55\nmax loop depth: 2\n");
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/unmatched-close.txt")
+        .arg("--dry-run")
+        .assert()
+        .failure()
+        .stdout(predicates::str::starts_with("error: unmatched ']'\n"));
+
+    Ok(())
+}
+
+#[test]
+fn optimize_only_prints_the_optimized_source() -> Result<(), Box<dyn std::error::Error>> {
+    let program = std::env::temp_dir().join(format!("optimize-only-{}.bf", std::process::id()));
+    let optimized = std::env::temp_dir().join(format!("optimized-{}.bf", std::process::id()));
+    std::fs::write(&program, "+++[-]>>+++++[-<+>]<<<> comment")?;
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg(&program)
+        .arg("--optimize-only")
+        .assert()
+        .success()
+        .stdout("+++[-]>>+++++[-<+>]<<\n");
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--optimize-only")
+        .arg("--output")
+        .arg(&optimized)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg(&optimized)
+        .assert()
+        .success()
+        .stdout("Hello World!\n");
+    std::fs::remove_file(&program)?;
+    std::fs::remove_file(&optimized)?;
+
+    Ok(())
+}
+
+#[test]
+fn format_prints_source() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/print-three.txt")
+        .args(["--format", "--max-line-width=4"])
+        .assert()
+        .success()
+        .stdout(".+.+\n.\n");
+
+    Ok(())
+}
+
+#[test]
+fn check_format() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/print-three.txt")
+        .arg("--check-format")
+        .assert()
+        .failure()
+        .stderr("error: program is not formatted, see --format\n");
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/nops.txt")
+        .arg("--check-format")
+        .assert()
+        .success()
+        .stdout("");
+
+    Ok(())
+}
+
+#[test]
+fn wide_cells_need_the_plain_tree_engine() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/print-three.txt")
+        .args(["--cell-size=32", "--optimize"])
+        .assert()
+        .failure()
+        .stderr(
+            "error: --cell-size=32 does not run with --optimize, it folds cell changes modulo 256\n",
+        );
+
+    Ok(())
+}
+
+const CELL_OVERFLOW_FILE: &str = "--file=./test_files/cell-overflow.txt";
+
+// Fail with `flag` running with `args`, before anything of the program runs
+fn refuses_on_byte_engines(args: &[&str], flag: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.args(args)
+        .arg(flag)
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(format!(
+            "error: {} only runs on the plain tree engine\n",
+            flag
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn cell_size_is_refused_by_the_vm() -> Result<(), Box<dyn std::error::Error>> {
+    refuses_on_byte_engines(&[CELL_OVERFLOW_FILE, "--engine=vm"], "--cell-size=16")
+}
+
+#[test]
+fn cell_size_is_refused_by_the_flat_interpreter() -> Result<(), Box<dyn std::error::Error>> {
+    refuses_on_byte_engines(&[CELL_OVERFLOW_FILE, "--engine=flat"], "--cell-size=32")
+}
+
+#[test]
+fn cell_size_is_refused_by_the_dispatch_table() -> Result<(), Box<dyn std::error::Error>> {
+    refuses_on_byte_engines(
+        &[CELL_OVERFLOW_FILE, "--engine=dispatch"],
+        "--cell-size=8-signed",
+    )
+}
+
+#[test]
+fn cell_size_is_refused_by_the_arena() -> Result<(), Box<dyn std::error::Error>> {
+    refuses_on_byte_engines(&[CELL_OVERFLOW_FILE, "--engine=arena"], "--cell-size=16")
+}
+
+#[test]
+fn cell_size_is_refused_by_the_backends() -> Result<(), Box<dyn std::error::Error>> {
+    refuses_on_byte_engines(&[CELL_OVERFLOW_FILE, "--emit=c"], "--cell-size=16")?;
+    refuses_on_byte_engines(&[CELL_OVERFLOW_FILE, "--emit=bytecode"], "--cell-size=16")
+}
+
+#[test]
+fn cell_size_is_refused_by_trace_profile_and_repl() -> Result<(), Box<dyn std::error::Error>> {
+    refuses_on_byte_engines(&[CELL_OVERFLOW_FILE, "--trace"], "--cell-size=16")?;
+    refuses_on_byte_engines(&[CELL_OVERFLOW_FILE, "--profile"], "--cell-size=16")?;
+    refuses_on_byte_engines(&["--repl"], "--cell-size=16")
+}
+
+#[test]
+fn saturate_is_refused_by_the_other_engines() -> Result<(), Box<dyn std::error::Error>> {
+    for engine in ["vm", "flat", "dispatch", "arena"].iter() {
+        let engine = format!("--engine={}", engine);
+        refuses_on_byte_engines(&[CELL_OVERFLOW_FILE, &engine], "--saturate")?;
+    }
+    Ok(())
+}
+
+#[test]
+fn saturate_is_refused_by_the_backends_and_tools() -> Result<(), Box<dyn std::error::Error>> {
+    refuses_on_byte_engines(&[CELL_OVERFLOW_FILE, "--emit=c"], "--saturate")?;
+    refuses_on_byte_engines(&[CELL_OVERFLOW_FILE, "--trace"], "--saturate")?;
+    refuses_on_byte_engines(&[CELL_OVERFLOW_FILE, "--profile"], "--saturate")?;
+    refuses_on_byte_engines(
+        &[
+            CELL_OVERFLOW_FILE,
+            "--check-equiv=./test_files/cell-overflow.txt",
+        ],
+        "--saturate",
+    )?;
+    refuses_on_byte_engines(&["--repl"], "--saturate")
+}
+
+#[test]
+fn cell_size_16() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/cell-overflow.txt")
+        .assert()
+        .success()
+        .stdout("");
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/cell-overflow.txt")
+        .arg("--cell-size=16")
+        .assert()
+        .success()
+        .stdout("2");
+
+    Ok(())
+}
+
+#[test]
+fn saturate() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/increment-input.txt")
+        .arg("--input-string=\\xff")
+        .assert()
+        .success()
+        .stdout("\u{0}");
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/increment-input.txt")
+        .args(["--input-string=\\xff", "--saturate"])
+        .assert()
+        .success()
+        .stdout(&[255u8][..]);
+
+    Ok(())
+}
+
+#[test]
+fn overflow_check() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/increment-input.txt")
+        .args(["--input-string=\\xff", "--overflow-check"])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr("error: cell 0 overflowed past its largest value\n");
+
+    Ok(())
+}
+
+#[test]
+fn sparse_tape() -> Result<(), Box<dyn std::error::Error>> {
+    // a tape this large only fits in memory when its zero cells are not stored
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .args(["--sparse-tape", "--memory-size=1000000000000"])
+        .assert()
+        .success()
+        .stdout("Hello World!\n");
+    // the pointer wrapping to the last cell does not copy the tape up to it
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/pointer-underflow.txt")
+        .args(["--sparse-tape", "--memory-size=10000000000"])
+        .assert()
+        .success()
+        .stdout("\x01");
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .args(["--sparse-tape", "--engine=vm"])
+        .assert()
+        .failure()
+        .stderr("error: --sparse-tape only runs on the plain tree engine\n");
+
+    Ok(())
+}
+
+#[test]
+fn mmap_tape_keeps_cells_in_the_file() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("increment-{}.tape", std::process::id()));
+    for _ in 0..2 {
+        let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+        cmd.arg("--file")
+            .arg("./test_files/increment-input.txt")
+            .args(["--input-string=A", "--memory-size=4", "--mmap-tape"])
+            .arg(&path)
+            .assert()
+            .success()
+            .stdout("B");
+    }
+    // ',' overwrites the cell, so both runs leave the same tape
+    assert_eq!(std::fs::read(&path)?, b"B\0\0\0");
+    std::fs::remove_file(&path)?;
+
+    Ok(())
+}
+
+#[test]
+fn persistent_tape_accumulates_across_runs() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("counter-{}.tape", std::process::id()));
+    for count in 1..=3u8 {
+        let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+        cmd.arg("--file")
+            .arg("./test_files/set-a.txt")
+            .args(["--memory-size=2", "--persistent-tape"])
+            .arg(&path)
+            .assert()
+            .success();
+        assert_eq!(std::fs::read(&path)?, [0, 65 * count]);
+    }
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/set-a.txt")
+        .args(["--memory-size=3", "--persistent-tape"])
+        .arg(&path)
+        .assert()
+        .failure()
+        .stderr(format!(
+            "error: tape file {} holds 2 cells where the tape has 3, see --memory-size\n",
+            path.display()
+        ));
+    std::fs::remove_file(&path)?;
+
+    Ok(())
+}
+
+#[test]
+fn restart_from_checkpoint() -> Result<(), Box<dyn std::error::Error>> {
+    let directory = std::env::temp_dir().join(format!("checkpoints-{}", std::process::id()));
+    std::fs::create_dir_all(&directory)?;
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/checkpoint.txt")
+        .args([
+            "--engine=flat",
+            "--checkpoint-interval=1",
+            "--checkpoint-path",
+        ])
+        .arg(&directory)
+        .assert()
+        .success()
+        .stdout("AB");
+    // the checkpoint was saved after 'A' was printed
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/checkpoint.txt")
+        .arg("--engine=flat")
+        .arg("--restart-from-checkpoint")
+        .arg(directory.join("checkpoint.bfck"))
+        .assert()
+        .success()
+        .stdout("B");
+    std::fs::remove_dir_all(&directory)?;
+
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/checkpoint.txt")
+        .arg("--checkpoint-interval=1")
+        .assert()
+        .failure()
+        .stderr("error: --checkpoint-interval needs --engine=flat\n");
+
+    Ok(())
+}
+
+#[test]
+fn replay_a_recorded_run() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("increment-{}.replay", std::process::id()));
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/increment-input.txt")
+        .arg("--input-string=A")
+        .arg("--record")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("B");
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/increment-input.txt")
+        .arg("--replay")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("B");
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/set-a.txt")
+        .arg("--replay")
+        .arg(&path)
+        .assert()
+        .failure()
+        .stderr("error: output diverged from the replay log at step 3: expected byte 66, found the end of the program\n");
+    std::fs::remove_file(&path)?;
+
+    Ok(())
+}
+
+#[test]
+fn failed_run_still_writes_its_log_and_snapshot() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("failed-run-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let source = dir.join("endless.bf");
+    std::fs::write(&source, ",[]")?;
+    let (log, snapshot) = (dir.join("run.replay"), dir.join("run.snap"));
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg(&source)
+        .arg("--max-loop-iters=3")
+        .arg("--memory-size=4")
+        .arg("--input-string=A")
+        .arg("--record")
+        .arg(&log)
+        .arg("--save-snapshot")
+        .arg(&snapshot)
+        .assert()
+        .failure()
+        .stderr(
+            "error: loop at source offset 1 exceeded the iteration limit\n --> line 1, column 2\n  |\n1 | ,[]\n  |  ^\n",
+        );
+    assert!(std::fs::read(&log)?.starts_with(b"BFREPL\x01"));
+    assert_eq!(
+        std::fs::read(&snapshot)?,
+        b"BFSNAP\x01\x04\x00\x00\x00A\x00\x00\x00\x00\x00\x00\x00"
+    );
+    std::fs::remove_dir_all(&dir)?;
+
+    Ok(())
+}
+
+#[test]
+fn dispatch_engine() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--engine=dispatch")
+        .assert()
+        .success()
+        .stdout("Hello World!\n");
+
+    Ok(())
+}
+
+#[test]
+fn arena_engine() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/hello-world.txt")
+        .arg("--engine=arena")
+        .assert()
+        .success()
+        .stdout("Hello World!\n");
+
+    Ok(())
+}
+
+#[test]
+fn source_map_of_emitted_c() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("source-map-{}.json", std::process::id()));
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/print-three.txt")
+        .arg("--emit=c")
+        .arg("--source-map")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("putchar(*p);"));
+    let map: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+    std::fs::remove_file(&path)?;
+    assert_eq!(map["version"], 3);
+    assert_eq!(map["sources"][0], "./test_files/print-three.txt");
+    // five statements on the lines after the prologue, from line 2 of the source
+    assert_eq!(
+        map["mappings"],
+        format!("{}IACA;IAAC;IAAC;IAAC;IAAC", ";".repeat(16))
+    );
+
+    let mut cmd = Command::cargo_bin("brainfuck_interpreter")?;
+    cmd.arg("--file")
+        .arg("./test_files/print-three.txt")
+        .arg("--emit=wat")
+        .arg("--source-map")
+        .arg(&path)
+        .assert()
+        .failure()
+        .stderr("error: --source-map needs --emit=c or --emit=rust\n");
+
+    Ok(())
+}
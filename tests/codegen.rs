@@ -0,0 +1,445 @@
+use std::{
+    env,
+    fs::{self, File},
+    io::{BufReader, Error, Read, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use brainfuck::{
+    codegen::{
+        c::{emit_c, emit_c_with_source_map},
+        elf::emit_elf,
+        llvm::emit_llvm_ir,
+        nasm::emit_nasm,
+        qbe::emit_qbe,
+        rust::{emit_rust, emit_rust_with_source_map},
+        sourcemap::{encode_vlq, Mapping},
+        wasm::emit_wasm,
+        wat::emit_wat,
+    },
+    optimizer::optimize,
+    Node, MEMORY_SIZE,
+};
+
+mod common;
+use common::parse;
+
+fn read_file_to_string(path: &str) -> Result<String, Error> {
+    let file = File::open(path)?;
+    let mut result_string = String::new();
+
+    let mut buf_reader = BufReader::new(file);
+    buf_reader.read_to_string(&mut result_string)?;
+
+    Ok(result_string)
+}
+
+fn parse_file(path: &str) -> Result<Node, Box<dyn std::error::Error>> {
+    let source = read_file_to_string(path)?;
+    parse(&source)
+}
+
+// Run a command feeding it input, None when the tool is not installed
+fn run_tool(program: &str, args: &[&str], input: &[u8]) -> Option<Vec<u8>> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .ok()?;
+    child.stdin.take()?.write_all(input).ok()?;
+    let output = child.wait_with_output().ok()?;
+    assert!(output.status.success(), "{} failed", program);
+    Some(output.stdout)
+}
+
+fn scratch_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("bf-codegen-{}-{}", std::process::id(), name))
+}
+
+// Programs with their input and the output of the interpreter
+fn cases() -> Result<Vec<(&'static str, &'static str, String)>, Error> {
+    Ok(vec![
+        (
+            "./test_files/hello-world.txt",
+            "",
+            "Hello World!\n".to_string(),
+        ),
+        (
+            "./test_files/print-0-to-99.txt",
+            "",
+            read_file_to_string("./test_outputs/print-0-to-99-result.txt")?,
+        ),
+        (
+            "./test_files/cat.txt",
+            "cat input\n",
+            "cat input\n".to_string(),
+        ),
+    ])
+}
+
+// Major version of the installed LLVM tools, None when not installed
+fn llvm_version() -> Option<u32> {
+    let output = Command::new("llc").arg("--version").output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout).to_string();
+    let version = text.split("LLVM version ").nth(1)?;
+    version.split('.').next()?.trim().parse().ok()
+}
+
+#[test]
+fn c_backend_matches_interpreter() -> Result<(), Box<dyn std::error::Error>> {
+    for (index, (path, input, expected)) in cases()?.iter().enumerate() {
+        let code = emit_c(&parse_file(path)?, MEMORY_SIZE);
+        assert!(code.contains("static unsigned char tape[TAPE_SIZE] = {0};"));
+
+        let source = scratch_path(&format!("{}.c", index));
+        let binary = scratch_path(&format!("{}.out", index));
+        fs::write(&source, code)?;
+        let compiled = run_tool(
+            "gcc",
+            &[
+                "-O2",
+                "-o",
+                binary.to_str().unwrap(),
+                source.to_str().unwrap(),
+            ],
+            b"",
+        );
+        if compiled.is_none() {
+            eprintln!("gcc not found, skipping the compiled run");
+            return Ok(());
+        }
+        let output = run_tool(binary.to_str().unwrap(), &[], input.as_bytes());
+        fs::remove_file(&source)?;
+        fs::remove_file(&binary)?;
+        assert_eq!(output, Some(expected.as_bytes().to_vec()));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn llvm_backend_matches_interpreter() -> Result<(), Box<dyn std::error::Error>> {
+    for (index, (path, input, expected)) in cases()?.iter().enumerate() {
+        let code = emit_llvm_ir(&parse_file(path)?, MEMORY_SIZE);
+        assert!(code.contains("define i32 @main()"));
+        assert!(code.contains("%tape = alloca [30000 x i8]"));
+
+        // opaque pointers are the default since LLVM 15
+        if llvm_version().is_none_or(|version| version < 15) {
+            eprintln!("llc 15 or newer not found, skipping the compiled run");
+            return Ok(());
+        }
+        let source = scratch_path(&format!("{}.ll", index));
+        let assembly = scratch_path(&format!("{}.s", index));
+        let binary = scratch_path(&format!("{}.ll.out", index));
+        fs::write(&source, code)?;
+        run_tool(
+            "llc",
+            &[
+                "-O2",
+                "-o",
+                assembly.to_str().unwrap(),
+                source.to_str().unwrap(),
+            ],
+            b"",
+        );
+        if run_tool(
+            "gcc",
+            &["-o", binary.to_str().unwrap(), assembly.to_str().unwrap()],
+            b"",
+        )
+        .is_none()
+        {
+            eprintln!("gcc not found, skipping the compiled run");
+            return Ok(());
+        }
+        let output = run_tool(binary.to_str().unwrap(), &[], input.as_bytes());
+        for path in [&source, &assembly, &binary].iter() {
+            fs::remove_file(path)?;
+        }
+        assert_eq!(output, Some(expected.as_bytes().to_vec()));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn qbe_backend_matches_interpreter() -> Result<(), Box<dyn std::error::Error>> {
+    let mut programs = Vec::new();
+    for (path, input, expected) in cases()? {
+        let mut optimized = parse_file(path)?;
+        optimize(&mut optimized);
+        for program in [parse_file(path)?, optimized].iter() {
+            let code = emit_qbe(program, MEMORY_SIZE);
+            assert!(code.contains("data $tape = align 1 { z 30000 }"));
+            assert!(code.contains("export function w $main()"));
+            programs.push((code, input, expected.clone()));
+        }
+    }
+    for (index, (code, input, expected)) in programs.iter().enumerate() {
+        let source = scratch_path(&format!("{}.ssa", index));
+        let assembly = scratch_path(&format!("{}.ssa.s", index));
+        let binary = scratch_path(&format!("{}.ssa.out", index));
+        fs::write(&source, code)?;
+        let compiled = run_tool(
+            "qbe",
+            &["-o", assembly.to_str().unwrap(), source.to_str().unwrap()],
+            b"",
+        );
+        if compiled.is_none() {
+            fs::remove_file(&source)?;
+            eprintln!("qbe not found, skipping the compiled run");
+            return Ok(());
+        }
+        run_tool(
+            "cc",
+            &["-o", binary.to_str().unwrap(), assembly.to_str().unwrap()],
+            b"",
+        );
+        let output = run_tool(binary.to_str().unwrap(), &[], input.as_bytes());
+        for path in [&source, &assembly, &binary].iter() {
+            fs::remove_file(path)?;
+        }
+        assert_eq!(output, Some(expected.as_bytes().to_vec()));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn wat_backend_matches_interpreter() -> Result<(), Box<dyn std::error::Error>> {
+    for (index, (path, input, expected)) in cases()?.iter().enumerate() {
+        let code = emit_wat(&parse_file(path)?, MEMORY_SIZE);
+        let binary = wat::parse_str(&code)?;
+        wasmparser::Validator::new().validate_all(&binary)?;
+
+        let module = scratch_path(&format!("{}.wat", index));
+        fs::write(&module, code)?;
+        let output = run_tool("wasmtime", &[module.to_str().unwrap()], input.as_bytes());
+        fs::remove_file(&module)?;
+        match output {
+            Some(output) => assert_eq!(output, expected.as_bytes()),
+            None => {
+                eprintln!("wasmtime not found, skipping the run");
+                return Ok(());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn wasm_backend_matches_interpreter() -> Result<(), Box<dyn std::error::Error>> {
+    let mut modules = Vec::new();
+    for (path, input, expected) in cases()? {
+        let mut optimized = parse_file(path)?;
+        optimize(&mut optimized);
+        for program in [parse_file(path)?, optimized].iter() {
+            let binary = emit_wasm(program, MEMORY_SIZE);
+            wasmparser::Validator::new().validate_all(&binary)?;
+            modules.push((binary, input, expected.clone()));
+        }
+    }
+    for (index, (binary, input, expected)) in modules.iter().enumerate() {
+        let module = scratch_path(&format!("{}.wasm", index));
+        fs::write(&module, binary)?;
+        let output = run_tool("wasmtime", &[module.to_str().unwrap()], input.as_bytes());
+        fs::remove_file(&module)?;
+        match output {
+            Some(output) => assert_eq!(output, expected.as_bytes()),
+            None => {
+                eprintln!("wasmtime not found, skipping the run");
+                return Ok(());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn nasm_backend_matches_interpreter() -> Result<(), Box<dyn std::error::Error>> {
+    // `[-]>[-]>[-]` wraps around the end of the tape, the cells are cleared at once
+    let mut cleared = parse("+>+>+++++<<<+[-]>[-]>[-].<.<.>>>.")?;
+    optimize(&mut cleared);
+    let cleared = emit_nasm(&cleared, MEMORY_SIZE);
+    assert!(cleared.contains("rep stosb"));
+    assert!(cleared.contains("tape: resb TAPE_SIZE"));
+
+    let mut programs = vec![(cleared, "", vec![0, 0, 0, 5])];
+    for (path, input, expected) in cases()? {
+        programs.push((
+            emit_nasm(&parse_file(path)?, MEMORY_SIZE),
+            input,
+            expected.into_bytes(),
+        ));
+    }
+    for (index, (code, input, expected)) in programs.iter().enumerate() {
+        let source = scratch_path(&format!("{}.asm", index));
+        let object = scratch_path(&format!("{}.o", index));
+        let binary = scratch_path(&format!("{}.asm.out", index));
+        fs::write(&source, code)?;
+        let assembled = run_tool(
+            "nasm",
+            &[
+                "-f",
+                "elf64",
+                "-o",
+                object.to_str().unwrap(),
+                source.to_str().unwrap(),
+            ],
+            b"",
+        );
+        if assembled.is_none() {
+            fs::remove_file(&source)?;
+            eprintln!("nasm not found, skipping the assembled run");
+            return Ok(());
+        }
+        run_tool(
+            "ld",
+            &["-o", binary.to_str().unwrap(), object.to_str().unwrap()],
+            b"",
+        );
+        let output = run_tool(binary.to_str().unwrap(), &[], input.as_bytes());
+        for path in [&source, &object, &binary].iter() {
+            fs::remove_file(path)?;
+        }
+        assert_eq!(output.as_ref(), Some(expected));
+    }
+
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[test]
+fn elf_backend_matches_interpreter() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut cleared = parse("+>+>+++++<<<+[-]>[-]>[-].<.<.>>>.")?;
+    optimize(&mut cleared);
+    let mut programs = vec![(emit_elf(&cleared, MEMORY_SIZE), "", vec![0, 0, 0, 5])];
+    for (path, input, expected) in cases()? {
+        let mut optimized = parse_file(path)?;
+        optimize(&mut optimized);
+        for program in [parse_file(path)?, optimized].iter() {
+            programs.push((
+                emit_elf(program, MEMORY_SIZE),
+                input,
+                expected.clone().into_bytes(),
+            ));
+        }
+    }
+    for (index, (executable, input, expected)) in programs.iter().enumerate() {
+        assert_eq!(&executable[..4], b"\x7fELF");
+        let binary = scratch_path(&format!("{}.elf", index));
+        fs::write(&binary, executable)?;
+        fs::set_permissions(&binary, fs::Permissions::from_mode(0o755))?;
+        let output = run_tool(binary.to_str().unwrap(), &[], input.as_bytes());
+        fs::remove_file(&binary)?;
+        assert_eq!(output.as_ref(), Some(expected));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn rust_backend_matches_interpreter() -> Result<(), Box<dyn std::error::Error>> {
+    let mut sources = Vec::new();
+    for (path, input, expected) in cases()? {
+        sources.push((read_file_to_string(path)?, input, expected));
+    }
+    // programs using only part of the state still compile without warnings
+    sources.push((String::new(), "", String::new()));
+    sources.push((">".to_string(), "", String::new()));
+    sources.push(("+>,".to_string(), "x", String::new()));
+
+    for (index, (program, input, expected)) in sources.iter().enumerate() {
+        let code = emit_rust(&parse(program)?, MEMORY_SIZE);
+        let source = scratch_path(&format!("{}.rs", index));
+        let binary = scratch_path(&format!("{}.rs.out", index));
+        fs::write(&source, code)?;
+        run_tool("rustfmt", &["--check", source.to_str().unwrap()], b"");
+        run_tool(
+            "rustc",
+            &[
+                "-D",
+                "warnings",
+                "-o",
+                binary.to_str().unwrap(),
+                source.to_str().unwrap(),
+            ],
+            b"",
+        );
+        let output = run_tool(binary.to_str().unwrap(), &[], input.as_bytes());
+        fs::remove_file(&source)?;
+        fs::remove_file(&binary)?;
+        assert_eq!(output, Some(expected.as_bytes().to_vec()));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn vlq_encodes_like_source_map_v3() {
+    for (value, expected) in [
+        (0, "A"),
+        (1, "C"),
+        (-1, "D"),
+        (15, "e"),
+        (16, "gB"),
+        (-123, "3H"),
+    ] {
+        let mut out = String::new();
+        encode_vlq(value, &mut out);
+        assert_eq!(out, expected, "{}", value);
+    }
+}
+
+#[test]
+fn source_maps_point_at_the_commands() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "+\n[-]";
+    let ast = parse(source)?;
+    let (code, map) = emit_c_with_source_map(&ast, 8);
+    assert_eq!(code, emit_c(&ast, 8));
+    let lines: Vec<&str> = code.lines().collect();
+    let statements: Vec<(&str, usize)> = map
+        .mappings
+        .iter()
+        .map(|mapping| {
+            (
+                &lines[mapping.generated_line][mapping.generated_column..],
+                mapping.offset,
+            )
+        })
+        .collect();
+    assert_eq!(
+        statements,
+        [("++*p;", 0), ("while (*p) {", 2), ("--*p;", 3)]
+    );
+    assert!(map
+        .to_json("out.c", &[("in.bf", source)])
+        .ends_with(&format!(
+            "\"names\":[],\"mappings\":\"{}IAAA;IACA;QAAC\"}}",
+            ";".repeat(map.mappings[0].generated_line)
+        )));
+
+    let (code, map) = emit_rust_with_source_map(&ast, 8);
+    assert_eq!(code, emit_rust(&ast, 8));
+    let Mapping {
+        generated_line,
+        generated_column,
+        offset,
+    } = map.mappings[2];
+    assert_eq!(
+        (
+            &code.lines().nth(generated_line).unwrap()[generated_column..],
+            offset
+        ),
+        ("tape[p] = tape[p].wrapping_sub(1);", 3)
+    );
+
+    Ok(())
+}
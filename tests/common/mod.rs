@@ -0,0 +1,6 @@
+use brainfuck::{lexical_analysis_str, syntax_analysis, Node};
+
+// The tree of a source the tests know to be well formed
+pub fn parse(source: &str) -> Result<Node, Box<dyn std::error::Error>> {
+    Ok(syntax_analysis(lexical_analysis_str(source), source)?)
+}
@@ -0,0 +1,164 @@
+use brainfuck::{
+    debugger::{tape_window, DebugError, Debugger, Journal},
+    interpreter_init, InterpreterError, OverflowMode,
+};
+use std::{collections::HashSet, io::Cursor};
+
+mod common;
+use common::parse;
+
+fn debug(
+    source: &str,
+    commands: &str,
+    breakpoints: &[usize],
+) -> Result<(Result<(), DebugError>, String), Box<dyn std::error::Error>> {
+    let ast = parse(source)?;
+    let mut interpreter = interpreter_init(16);
+    let mut output = Vec::new();
+    let mut debugger = Debugger::new(Cursor::new(commands), &mut output, breakpoints);
+    let result = debugger.run(&mut interpreter, &ast);
+    Ok((result, String::from_utf8(output)?))
+}
+
+#[test]
+fn step_prints_every_instruction() -> Result<(), Box<dyn std::error::Error>> {
+    let (result, output) = debug("+>+", "s\ns\ns\n", &[])?;
+    assert!(result.is_ok());
+    assert!(output.contains("[step=1] cmd=IncByte pointer=0 cell[0]=1"));
+    assert!(output.contains("[step=2] cmd=IncDP pointer=1 cell[1]=0"));
+    assert!(output.contains("[step=3] cmd=IncByte pointer=1 cell[1]=1"));
+
+    Ok(())
+}
+
+#[test]
+fn continue_stops_at_breakpoints() -> Result<(), Box<dyn std::error::Error>> {
+    let (result, output) = debug("+++ [->+<]", "c\nt\nc\nc\nc\n", &[7])?;
+    assert!(result.is_ok());
+    assert!(output.contains("[step=1]"));
+    assert!(!output.contains("[step=2]"));
+    assert!(output.contains("[breakpoint] offset 7 before IncByte"));
+    assert!(output.contains("[step=5] cmd=IncByte pointer=1 cell[1]=0"));
+    assert!(output.contains("0=2 [1]=0 2=0"));
+    assert_eq!(output.matches("[breakpoint]").count(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn loops_can_be_breakpoints() -> Result<(), Box<dyn std::error::Error>> {
+    let (result, output) = debug("++>[-]<[-]", "c\nc\n", &[3, 7])?;
+    assert!(result.is_ok());
+    assert!(output.contains("[breakpoint] offset 3 before JumpForward"));
+    assert!(output.contains("[breakpoint] offset 7 before JumpForward"));
+    assert!(output.contains("[step=4] cmd=JumpForward pointer=0 cell[0]=2"));
+
+    Ok(())
+}
+
+#[test]
+fn watchpoints_pause_on_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("+++[->>+<<]>+")?;
+    let mut interpreter = interpreter_init(16);
+    let mut output = Vec::new();
+    let mut debugger = Debugger::new(Cursor::new("c\nc\nc\nc\n"), &mut output, &[]);
+    debugger.watchpoints = HashSet::from([2, 5]);
+    debugger.run(&mut interpreter, &ast)?;
+    let output = String::from_utf8(output)?;
+    assert!(output.contains("[watchpoint] cell[2]: 0 → 1"));
+    assert!(output.contains("[watchpoint] cell[2]: 1 → 2"));
+    assert!(output.contains("[watchpoint] cell[2]: 2 → 3"));
+    assert!(!output.contains("cell[5]:"));
+    assert!(!output.contains("cell[1]:"));
+    assert_eq!(output.matches("(bfdb) ").count(), 4);
+
+    Ok(())
+}
+
+#[test]
+fn quit_stops_the_program() -> Result<(), Box<dyn std::error::Error>> {
+    let (result, output) = debug("+++", "s\nq\n", &[])?;
+    assert!(matches!(result, Err(DebugError::Quit { step: 2 })));
+    assert!(!output.contains("[step=3]"));
+
+    Ok(())
+}
+
+#[test]
+fn errors_report_their_offset() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("+> <<")?;
+    let mut interpreter = interpreter_init(16);
+    interpreter.overflow_mode = OverflowMode::Error;
+    let mut output = Vec::new();
+    let result = Debugger::new(Cursor::new("c\n"), &mut output, &[]).run(&mut interpreter, &ast);
+    match result {
+        Err(error @ DebugError::Interpreter { .. }) => {
+            assert_eq!(
+                error.to_string(),
+                format!(
+                    "error at byte offset 4: {}",
+                    InterpreterError::PointerOutOfBounds { pointer: -1 }
+                )
+            );
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn tape_window_marks_the_pointer() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = interpreter_init(4);
+    interpreter.pointer = 1;
+    interpreter.memory[1] = 9;
+    assert_eq!(tape_window(&interpreter), "0=0 [1]=9 2=0 3=0");
+
+    Ok(())
+}
+
+#[test]
+fn undo_puts_back_the_tape() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("++>+++[-<+>]")?;
+    let mut interpreter = interpreter_init(16);
+    let mut output = Vec::new();
+    // stop after the first `<`, undo it and the `-` before it, then carry on
+    let commands = "s\ns\ns\ns\ns\ns\ns\nu 2\nc\n";
+    let mut debugger = Debugger::new(Cursor::new(commands), &mut output, &[]);
+    debugger.journal = Some(Journal::new(None));
+    debugger.run(&mut interpreter, &ast)?;
+    let output = String::from_utf8(output)?;
+    assert!(output.contains("[step=8] cmd=DecDP pointer=0 cell[0]=2"));
+    assert!(output.contains("[undo] 2 changes undone\n0=2 [1]=3 2=0"));
+    // the `+` after the `<` now lands on cell 1 and the loop ends on cell 2
+    assert_eq!((interpreter.memory[0], interpreter.memory[1]), (2, 4));
+
+    Ok(())
+}
+
+#[test]
+fn undo_warns_about_output() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("+.+")?;
+    let mut interpreter = interpreter_init(16);
+    interpreter.output = Box::new(Vec::new());
+    let mut output = Vec::new();
+    let mut debugger = Debugger::new(Cursor::new("s\nu 5\nu\nc\n"), &mut output, &[]);
+    debugger.journal = Some(Journal::new(Some(2)));
+    debugger.run(&mut interpreter, &ast)?;
+    let output = String::from_utf8(output)?;
+    assert!(output.contains("[undo] warning: output byte 1 was already written and stays"));
+    assert!(output.contains("[undo] 2 changes undone\n"));
+    assert!(output.contains("[undo] 0 changes undone\n"));
+    assert_eq!(interpreter.memory[0], 1);
+
+    Ok(())
+}
+
+#[test]
+fn undo_needs_a_journal() -> Result<(), Box<dyn std::error::Error>> {
+    let (result, output) = debug("+", "u\nc\n", &[])?;
+    assert!(result.is_ok());
+    assert!(output.contains("undo needs --reversible"));
+
+    Ok(())
+}
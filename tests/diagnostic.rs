@@ -0,0 +1,56 @@
+use brainfuck::{
+    diagnostic::{render_diagnostic, Diagnostic, Severity},
+    error::BrainfuckError,
+    lexical_analysis_str, syntax_analysis, InterpreterError,
+};
+
+#[test]
+fn caret_points_at_the_column() {
+    let source = "+++\n++]--\n";
+    let diagnostic = Diagnostic::error("unmatched ']'", 6);
+    assert_eq!(
+        render_diagnostic(source, &diagnostic),
+        "error: unmatched ']'\n --> line 2, column 3\n  |\n2 | ++]--\n  |   ^\n"
+    );
+}
+
+#[test]
+fn gutter_fits_the_line_number() {
+    let source = format!("{}[-", "+\n".repeat(11));
+    let diagnostic = Diagnostic {
+        message: "loop is never left".to_string(),
+        offset: 22,
+        severity: Severity::Warning,
+    };
+    assert_eq!(
+        render_diagnostic(&source, &diagnostic),
+        "warning: loop is never left\n  --> line 12, column 1\n   |\n12 | [-\n   | ^\n"
+    );
+}
+
+#[test]
+fn caret_keeps_tabs() {
+    let source = "\t+ ]";
+    let rendered = render_diagnostic(source, &Diagnostic::error("unmatched ']'", 3));
+    assert!(rendered.ends_with("1 | \t+ ]\n  | \t  ^\n"));
+}
+
+#[test]
+fn errors_with_offsets_become_diagnostics() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "[\n]]";
+    let error = syntax_analysis(lexical_analysis_str(source), source)
+        .err()
+        .ok_or("unmatched bracket was accepted")?;
+    assert_eq!(
+        error.diagnostics(source),
+        vec![Diagnostic::error("unmatched ']'", 3)]
+    );
+
+    let error = BrainfuckError::from(InterpreterError::UndefinedSubroutine { offset: 2 });
+    assert_eq!(error.diagnostics(source)[0].offset, 2);
+    assert!(BrainfuckError::from(InterpreterError::Timeout)
+        .diagnostics(source)
+        .is_empty());
+
+    Ok(())
+}
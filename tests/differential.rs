@@ -0,0 +1,166 @@
+use std::{
+    cell::RefCell,
+    env, fs,
+    io::{self, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+    rc::Rc,
+    sync::OnceLock,
+};
+
+use brainfuck::{
+    interpreter_init, lexical_analysis_str, run_program, syntax_analysis, EofBehavior, InputSource,
+    MEMORY_SIZE,
+};
+
+// Output shared with the test after the interpreter took ownership of it
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn scratch_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("bf-differential-{}-{}", std::process::id(), name))
+}
+
+// tests/reference/bf.c compiled once for all the tests, None when gcc is not installed
+fn reference_binary() -> Option<&'static PathBuf> {
+    static BINARY: OnceLock<Option<PathBuf>> = OnceLock::new();
+    BINARY
+        .get_or_init(|| {
+            let binary = scratch_path("reference");
+            let status = Command::new("gcc")
+                .args(["-O2", "-o"])
+                .arg(&binary)
+                .arg("./tests/reference/bf.c")
+                .status()
+                .ok()?;
+            assert!(status.success(), "gcc failed on the reference interpreter");
+            Some(binary)
+        })
+        .as_ref()
+}
+
+fn eof_name(eof: EofBehavior) -> &'static str {
+    match eof {
+        EofBehavior::Zero => "zero",
+        EofBehavior::Max => "max",
+        EofBehavior::Unchanged => "unchanged",
+    }
+}
+
+fn interpreter_output(
+    source: &str,
+    input: &[u8],
+    tape_size: usize,
+    eof: EofBehavior,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let ast = syntax_analysis(lexical_analysis_str(source), source)?;
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let mut interpreter = interpreter_init(tape_size);
+    interpreter.eof_behavior = eof;
+    interpreter.input = InputSource::Buffer(input.to_vec(), 0);
+    interpreter.output = Box::new(SharedOutput(Rc::clone(&output)));
+    run_program(&mut interpreter, &ast)?;
+    let bytes = output.borrow().clone();
+    Ok(bytes)
+}
+
+fn reference_output(
+    binary: &PathBuf,
+    name: &str,
+    source: &str,
+    input: &[u8],
+    tape_size: usize,
+    eof: EofBehavior,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let program = scratch_path(&format!("{}.bf", name));
+    fs::write(&program, source)?;
+    let mut child = Command::new(binary)
+        .arg(&program)
+        .arg(tape_size.to_string())
+        .arg(eof_name(eof))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(input)?;
+    let output = child.wait_with_output()?;
+    fs::remove_file(&program)?;
+    assert!(output.status.success(), "reference failed on {}", name);
+    Ok(output.stdout)
+}
+
+// Both interpreters must print the same bytes, the reference is skipped without gcc
+fn assert_same_output(
+    name: &str,
+    source: &str,
+    input: &[u8],
+    tape_size: usize,
+    eof: EofBehavior,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let binary = match reference_binary() {
+        Some(binary) => binary,
+        None => {
+            eprintln!("gcc not found, skipping the reference run");
+            return Ok(());
+        }
+    };
+    let expected = reference_output(binary, name, source, input, tape_size, eof)?;
+    let output = interpreter_output(source, input, tape_size, eof)?;
+    assert_eq!(output, expected, "{} with --eof={}", name, eof_name(eof));
+    Ok(())
+}
+
+#[test]
+fn golden_programs_match_reference() -> Result<(), Box<dyn std::error::Error>> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir("./tests/programs")? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == "bf") {
+            names.push(path.file_stem().unwrap().to_string_lossy().to_string());
+        }
+    }
+    names.sort();
+    assert!(names.len() >= 6);
+
+    for name in names.iter() {
+        let source = fs::read_to_string(format!("./tests/programs/{}.bf", name))?;
+        let input = fs::read(format!("./tests/programs/{}.input", name)).unwrap_or_default();
+        assert_same_output(name, &source, &input, MEMORY_SIZE, EofBehavior::Zero)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn pointer_wrapping_matches_reference() -> Result<(), Box<dyn std::error::Error>> {
+    // left of cell 0 is the last cell, right of the last cell is cell 0
+    let source = "<++++++++[<++++++++>-]<+.>>>.<<<<++.>>>>>>>>+.";
+    assert_same_output("pointer-wrapping", source, b"", 4, EofBehavior::Zero)
+}
+
+#[test]
+fn cell_wrapping_matches_reference() -> Result<(), Box<dyn std::error::Error>> {
+    // 0 - 1 is 255, 255 + 1 is 0 and a loop counting up from 1 ends at 256
+    let source = "-.+.>+[+>+<]>.";
+    assert_same_output("cell-wrapping", source, b"", 8, EofBehavior::Zero)
+}
+
+#[test]
+fn eof_behaviors_match_reference() -> Result<(), Box<dyn std::error::Error>> {
+    // three reads into cells holding 3, only the first one gets a byte with input
+    let source = "+++,.>+++,.>+++,.";
+    for eof in [EofBehavior::Zero, EofBehavior::Max, EofBehavior::Unchanged].iter() {
+        assert_same_output("eof", source, b"a", 8, *eof)?;
+        assert_same_output("eof-empty", source, b"", 8, *eof)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use brainfuck::{
+    equiv::{check_equivalence, escape_bytes, run_captured, DEFAULT_INPUTS},
+    EofBehavior, InterpreterError,
+};
+
+mod common;
+use common::parse;
+
+fn default_inputs() -> Vec<Vec<u8>> {
+    DEFAULT_INPUTS.iter().map(|input| input.to_vec()).collect()
+}
+
+#[test]
+fn programs_printing_the_same_bytes_are_equivalent() -> Result<(), Box<dyn std::error::Error>> {
+    // the second one copies the cell through another before printing it
+    let first = parse(",[.,]")?;
+    let second = parse(",[[->+<]>[-<+>]<.,]")?;
+    let divergence = check_equivalence(
+        &first,
+        &second,
+        &default_inputs(),
+        8,
+        EofBehavior::Zero,
+        10_000,
+    );
+    assert_eq!(divergence, None);
+
+    Ok(())
+}
+
+#[test]
+fn divergence_reports_the_first_differing_input() -> Result<(), Box<dyn std::error::Error>> {
+    let first = parse(",.")?;
+    let second = parse(",+.")?;
+    let divergence = check_equivalence(
+        &first,
+        &second,
+        &default_inputs(),
+        8,
+        EofBehavior::Zero,
+        10_000,
+    )
+    .ok_or("programs should diverge")?;
+    assert_eq!(divergence.input, b"");
+    assert_eq!(divergence.first.output, [0]);
+    assert_eq!(divergence.second.output, [1]);
+
+    Ok(())
+}
+
+#[test]
+fn step_limit_stops_a_run() -> Result<(), Box<dyn std::error::Error>> {
+    let outcome = run_captured(&parse("+.[]")?, b"", 8, EofBehavior::Zero, 100);
+    assert_eq!(outcome.output, [1]);
+    assert_eq!(
+        outcome.error,
+        Some(InterpreterError::StepLimitExceeded { steps: 100 })
+    );
+    assert_eq!(escape_bytes(b"a\n\xff\""), "\"a\\n\\xff\\\"\"");
+
+    Ok(())
+}
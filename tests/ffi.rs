@@ -0,0 +1,70 @@
+use brainfuck::ffi::{
+    bf_get_cell, bf_get_pointer, bf_interpreter_create, bf_interpreter_destroy, bf_run_source,
+    BF_INVALID_SOURCE, BF_OK, BF_PANIC, BF_RUNTIME_ERROR, BF_SYNTAX_ERROR,
+};
+use std::io::{self, Write};
+use std::os::raw::c_char;
+
+fn run(interp: *mut brainfuck::Interpreter, source: &[u8]) -> i32 {
+    unsafe { bf_run_source(interp, source.as_ptr() as *const c_char, source.len()) }
+}
+
+#[test]
+fn programs_run_through_the_c_interface() {
+    let interp = bf_interpreter_create(4);
+    assert_eq!(run(interp, b"+++>++"), BF_OK);
+    // the next program continues on the same tape
+    assert_eq!(run(interp, b"[<+>-]<"), BF_OK);
+    unsafe {
+        assert_eq!(bf_get_cell(interp, 0), 5);
+        assert_eq!(bf_get_cell(interp, 1), 0);
+        assert_eq!(bf_get_cell(interp, 100), 0);
+        assert_eq!(bf_get_pointer(interp), 0);
+    }
+
+    assert_eq!(run(interp, b"+[>+]]"), BF_SYNTAX_ERROR);
+    assert_eq!(run(interp, b"\xff+"), BF_INVALID_SOURCE);
+    unsafe {
+        assert_eq!(
+            bf_run_source(interp, std::ptr::null(), 0),
+            BF_INVALID_SOURCE
+        );
+        bf_interpreter_destroy(interp);
+        bf_interpreter_destroy(std::ptr::null_mut());
+    }
+}
+
+#[test]
+fn runtime_errors_have_their_code() {
+    let interp = bf_interpreter_create(4);
+    unsafe {
+        (*interp).overflow_mode = brainfuck::OverflowMode::Error;
+    }
+    assert_eq!(run(interp, b"<"), BF_RUNTIME_ERROR);
+    unsafe { bf_interpreter_destroy(interp) }
+}
+
+// Output panicking on the first write
+struct PanickingOutput;
+
+impl Write for PanickingOutput {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        panic!("output failed")
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn panics_do_not_unwind_into_c() {
+    assert!(bf_interpreter_create(0).is_null());
+
+    let interp = bf_interpreter_create(4);
+    unsafe {
+        (*interp).output = Box::new(PanickingOutput);
+    }
+    assert_eq!(run(interp, b"+."), BF_PANIC);
+    unsafe { bf_interpreter_destroy(interp) }
+}
@@ -0,0 +1,51 @@
+use brainfuck::{format::Formatter, lexical_analysis_with_extensions, syntax_analysis, Node};
+
+fn parse(source: &str) -> Result<Node, Box<dyn std::error::Error>> {
+    Ok(syntax_analysis(
+        lexical_analysis_with_extensions(source.to_string(), true),
+        source,
+    )?)
+}
+
+fn formatter(indent: &str, max_line_width: usize) -> Formatter {
+    Formatter {
+        indent: indent.to_string(),
+        max_line_width,
+    }
+}
+
+#[test]
+fn short_programs_stay_on_one_line() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("hello ++ [ > + < - ]\n.")?;
+    assert_eq!(Formatter::default().format(&ast), "++[>+<-].\n");
+    assert_eq!(Formatter::default().format(&parse("no commands")?), "");
+
+    Ok(())
+}
+
+#[test]
+fn long_blocks_are_split_and_indented() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("++++[>++++[>+++<-]<-]>>.{+++++++}")?;
+    assert_eq!(
+        formatter("  ", 12).format(&ast),
+        "++++\n[\n  >++++\n  [>+++<-]<-\n]\n>>.{+++++++}\n"
+    );
+    assert_eq!(
+        formatter("\t", 8).format(&ast),
+        "++++\n[\n\t>++++\n\t[\n\t\t>+++<-\n\t]\n\t<-\n]\n>>.\n{\n\t+++++++\n}\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn formatting_is_stable() -> Result<(), Box<dyn std::error::Error>> {
+    let formatter = formatter("    ", 16);
+    let source = std::fs::read_to_string("./test_files/hello-world.txt")?;
+    let formatted = formatter.format(&parse(&source)?);
+    assert!(parse(&formatted)? == parse(&source)?);
+    assert_eq!(formatter.format(&parse(&formatted)?), formatted);
+    assert!(formatted.lines().all(|line| line.len() <= 16));
+
+    Ok(())
+}
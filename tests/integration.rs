@@ -0,0 +1,78 @@
+use brainfuck::{
+    interpreter_init, lexical_analysis_str, run_program, syntax_analysis, InputSource, MEMORY_SIZE,
+};
+use std::{
+    cell::RefCell,
+    fs,
+    io::{self, Write},
+    rc::Rc,
+};
+
+// Output shared with the test after the interpreter took ownership of it
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Run tests/programs/<name>.bf with <name>.input, if there is one, and compare
+// the output with <name>.expected
+fn assert_golden(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let path = format!("./tests/programs/{}", name);
+    let source = fs::read_to_string(format!("{}.bf", path))?;
+    let input = fs::read(format!("{}.input", path)).unwrap_or_default();
+    let expected = fs::read(format!("{}.expected", path))?;
+
+    let ast = syntax_analysis(lexical_analysis_str(&source), &source)?;
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let mut interpreter = interpreter_init(MEMORY_SIZE);
+    interpreter.input = InputSource::Buffer(input, 0);
+    interpreter.output = Box::new(SharedOutput(Rc::clone(&output)));
+    run_program(&mut interpreter, &ast)?;
+
+    // text first for a readable diff, then the bytes the text may have replaced
+    assert_eq!(
+        String::from_utf8_lossy(&output.borrow()),
+        String::from_utf8_lossy(&expected),
+        "{}.bf",
+        name
+    );
+    assert_eq!(*output.borrow(), expected);
+    Ok(())
+}
+
+#[test]
+fn hello_world() -> Result<(), Box<dyn std::error::Error>> {
+    assert_golden("hello-world")
+}
+
+#[test]
+fn cat() -> Result<(), Box<dyn std::error::Error>> {
+    assert_golden("cat")
+}
+
+#[test]
+fn fibonacci() -> Result<(), Box<dyn std::error::Error>> {
+    assert_golden("fibonacci")
+}
+
+#[test]
+fn bottles() -> Result<(), Box<dyn std::error::Error>> {
+    assert_golden("bottles")
+}
+
+#[test]
+fn rot13() -> Result<(), Box<dyn std::error::Error>> {
+    assert_golden("rot13")
+}
+
+#[test]
+fn sort() -> Result<(), Box<dyn std::error::Error>> {
+    assert_golden("sort")
+}
@@ -0,0 +1,97 @@
+use brainfuck::{
+    bytecode::{compile_bytecode, Bytecode},
+    interpreter_init,
+    jit::{jit_compile, JitError},
+    lexical_analysis_with_extensions,
+    optimizer::optimize,
+    run_program, syntax_analysis, CallbackOutput, InputSource, InterpreterError,
+};
+use std::{cell::RefCell, rc::Rc, sync::atomic::Ordering, time::Duration};
+
+mod common;
+use common::parse;
+
+#[test]
+fn jit_runs_like_the_tree_walker() -> Result<(), Box<dyn std::error::Error>> {
+    // the last loops wrap the pointer around both ends of the tape
+    let source = "++++++[->++++++++<]>+[>+<-]>>+++[-<+<+>>]<<<[>]+[[-]>+[<]>>]<<<<[-]+[<+]";
+    for optimized in [false, true].iter() {
+        let mut ast = parse(source)?;
+        if *optimized {
+            optimize(&mut ast);
+        }
+        let mut expected = interpreter_init(16);
+        run_program(&mut expected, &ast)?;
+        let mut actual = interpreter_init(16);
+        jit_compile(&compile_bytecode(&ast), 16)?.run(&mut actual)?;
+        assert_eq!(actual.memory, expected.memory);
+        assert_eq!(
+            actual.pointer as usize,
+            expected.pointer.rem_euclid(16) as usize
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn jit_reads_and_writes_through_the_interpreter() -> Result<(), Box<dyn std::error::Error>> {
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let captured = Rc::clone(&output);
+    let mut interpreter = interpreter_init(8);
+    interpreter.input = InputSource::Buffer(b"ab".to_vec(), 0);
+    interpreter.output = Box::new(CallbackOutput(Box::new(move |byte| {
+        captured.borrow_mut().push(byte)
+    })));
+    jit_compile(&compile_bytecode(&parse(",[+.,]+.")?), 8)?.run(&mut interpreter)?;
+    drop(interpreter);
+    assert_eq!(*output.borrow(), b"bc\x01");
+
+    Ok(())
+}
+
+#[test]
+fn jit_stops_at_halt_and_refuses_debug_print() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "+++@+++";
+    let commands = lexical_analysis_with_extensions(source.to_string(), true);
+    let ast = syntax_analysis(commands, source)?;
+    let mut interpreter = interpreter_init(8);
+    jit_compile(&compile_bytecode(&ast), 8)?.run(&mut interpreter)?;
+    assert_eq!(interpreter.memory[0], 3);
+
+    assert_eq!(
+        jit_compile(&[Bytecode::DebugPrint], 8).err(),
+        Some(JitError::Unsupported("'#'"))
+    );
+    assert_eq!(
+        jit_compile(&[Bytecode::JumpBackward(0)], 8).err(),
+        Some(JitError::UnmatchedJump { position: 0 })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn jit_checks_interrupt_and_timeout_in_loops() -> Result<(), Box<dyn std::error::Error>> {
+    // the last one is a scan over a tape without a zero cell
+    for source in ["+[]", "+[>+<]", "+>+>+>+>+>+>+>+[>]"].iter() {
+        let mut ast = parse(source)?;
+        optimize(&mut ast);
+        let function = jit_compile(&compile_bytecode(&ast), 8)?;
+        let mut interpreter = interpreter_init(8);
+        interpreter.timeout = Some(Duration::from_millis(50));
+        assert_eq!(
+            function.run(&mut interpreter),
+            Err(InterpreterError::Timeout)
+        );
+
+        let mut interpreter = interpreter_init(8);
+        interpreter.should_stop.store(true, Ordering::Relaxed);
+        assert_eq!(
+            function.run(&mut interpreter),
+            Err(InterpreterError::Interrupted)
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,813 @@
+use brainfuck::{
+    color::{error_style, paint, pointer_style},
+    debug_print,
+    error::BrainfuckError,
+    interpreter_init, lexical_analysis, lexical_analysis_str, lexical_analysis_with_extensions,
+    parse_escapes, progress_line, run_program, syntax_analysis, try_syntax_analysis, Command,
+    EofBehavior, InputSource, InterpreterError, LexerIterator, Node, NodeType, OutputFormatter,
+    OverflowDirection, OverflowMode, Program, Progress, SyntaxError, LEXER_CHUNK_SIZE,
+};
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+mod common;
+use common::parse;
+
+// Output shared with the test after the interpreter took ownership of it
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct FullOutput;
+
+impl Write for FullOutput {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::from(io::ErrorKind::StorageFull))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn lexer_ignores_comments() -> Result<(), Box<dyn std::error::Error>> {
+    let commands = lexical_analysis_str("+ add [ loop > ] .");
+    assert_eq!(
+        commands,
+        vec![
+            (Command::IncByte, 0),
+            (Command::JumpForward, 6),
+            (Command::IncDP, 13),
+            (Command::JumpBackward, 15),
+            (Command::OutByte, 17)
+        ]
+    );
+
+    Ok(())
+}
+
+// Reader handing out one byte per read, failing once the source is exhausted
+// when `fail` is set
+struct Trickle {
+    source: Vec<u8>,
+    read: usize,
+    fail: bool,
+}
+
+impl io::Read for Trickle {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.source.get(self.read) {
+            Some(&byte) => {
+                buf[0] = byte;
+                self.read += 1;
+                Ok(1)
+            }
+            None if self.fail => Err(io::Error::other("disk on fire")),
+            None => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn streaming_lexer_matches_the_str_adapter() -> Result<(), Box<dyn std::error::Error>> {
+    // commands across several chunks, one of them split on a chunk boundary
+    let mut source = "+ add [ loop > ] .".repeat(1000);
+    source.insert_str(LEXER_CHUNK_SIZE - 1, "<>");
+    let expected = lexical_analysis_str(&source);
+    let commands = lexical_analysis(source.as_bytes()).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(commands, expected);
+
+    let trickle = Trickle {
+        source: source.clone().into_bytes(),
+        read: 0,
+        fail: false,
+    };
+    let commands = lexical_analysis(trickle).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(commands, expected);
+
+    let source = "+#@{:}".to_string();
+    let commands = LexerIterator::new(source.as_bytes(), true).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(commands, lexical_analysis_with_extensions(source, true));
+
+    Ok(())
+}
+
+#[test]
+fn streaming_lexer_stops_at_a_read_error() -> Result<(), Box<dyn std::error::Error>> {
+    let trickle = Trickle {
+        source: b"+[-]".to_vec(),
+        read: 0,
+        fail: true,
+    };
+    let mut lexer = lexical_analysis(trickle);
+    assert_eq!(lexer.by_ref().take(4).count(), 4);
+    assert!(matches!(
+        lexer.next(),
+        Some(Err(BrainfuckError::IoError(_)))
+    ));
+    assert!(lexer.next().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn streamed_commands_parse_without_their_source() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "++[->+<] done";
+    let commands = lexical_analysis(source.as_bytes()).collect::<Result<Vec<_>, _>>()?;
+    let ast = try_syntax_analysis(&commands).ok_or("matching brackets were refused")?;
+    assert_eq!(ast.to_string(), parse(source)?.to_string());
+
+    for source in ["[[]", "[]]", "{[}]"].iter() {
+        let commands = lexical_analysis_with_extensions(source.to_string(), true);
+        assert!(try_syntax_analysis(&commands).is_none());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn parser_builds_loops() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("+[->+<]")?;
+    assert_eq!(ast.node_type, NodeType::Program);
+    assert_eq!(ast.childrens.len(), 2);
+    assert_eq!(ast.childrens[1].node_type, NodeType::Loop);
+    assert_eq!(ast.childrens[1].childrens.len(), 4);
+
+    Ok(())
+}
+
+#[test]
+fn parser_keeps_source_offsets() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("+ [ ->+< ]")?;
+    assert_eq!(ast.childrens[0].source_pos, 0);
+    assert_eq!(ast.childrens[1].source_pos, 2);
+    assert_eq!(ast.childrens[1].childrens[0].source_pos, 4);
+    assert_eq!(ast.childrens[1].childrens[3].source_pos, 7);
+
+    Ok(())
+}
+
+#[test]
+fn cloned_trees_compare_equal() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("+[->[-]<]>.")?;
+    let clone = ast.clone();
+    assert!(clone == ast);
+    assert_eq!(clone.to_string(), ast.to_string());
+
+    // source offsets do not matter, commands do
+    assert!(parse("  +[->[-]<]>.")? == ast);
+    assert!(parse("+[->[+]<]>.")? != ast);
+    assert!(parse("+[->[-]<]>")? != ast);
+
+    let mut changed = ast.clone();
+    changed.childrens[1].childrens[2].childrens.clear();
+    assert!(changed != ast);
+
+    Ok(())
+}
+
+#[test]
+fn programs_parse_from_strings() -> Result<(), Box<dyn std::error::Error>> {
+    let program: Program = "++[->+<]. comment".parse()?;
+    assert!(program.ast == parse("++[->+<].")?);
+    assert_eq!(program.to_string(), "++[->+<].");
+
+    assert_eq!(
+        "+]\n[".parse::<Program>().err(),
+        Some(SyntaxError::UnmatchedClose { line: 1, col: 2 })
+    );
+
+    Ok(())
+}
+
+// Every step of a run converts into BrainfuckError with a plain `?`
+fn run_source(source: &str, max_steps: u64) -> Result<Vec<u8>, BrainfuckError> {
+    let ast = syntax_analysis(lexical_analysis_str(source), source)?;
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let mut interpreter = interpreter_init(4);
+    interpreter.max_steps = Some(max_steps);
+    interpreter.output = Box::new(SharedOutput(Rc::clone(&output)));
+    run_program(&mut interpreter, &ast)?;
+    interpreter.output.flush()?;
+    let bytes = output.borrow().clone();
+    Ok(bytes)
+}
+
+#[test]
+fn errors_convert_to_brainfuck_error() {
+    assert_eq!(run_source("+++.", 10).unwrap(), vec![3]);
+    assert!(matches!(
+        run_source("+[]", 10),
+        Err(BrainfuckError::StepLimitExceeded { steps: 10 })
+    ));
+
+    let error = run_source("]+[", 10).unwrap_err();
+    assert!(matches!(&error, BrainfuckError::Syntax(errors) if errors.len() == 2));
+    assert_eq!(
+        error.to_string(),
+        "error: unmatched ']' at line 1, column 1\nerror: unmatched '[' at line 1, column 3"
+    );
+
+    let error: BrainfuckError = io::Error::from(io::ErrorKind::NotFound).into();
+    assert!(matches!(error, BrainfuckError::IoError(_)));
+    assert_eq!(
+        BrainfuckError::from(InterpreterError::HaltInstruction).to_string(),
+        "program halted by '@'"
+    );
+    let error = BrainfuckError::from(InterpreterError::PointerOutOfBounds { pointer: -1 });
+    assert!(matches!(error, BrainfuckError::Interpreter(_)));
+    assert_eq!(error.to_string(), "pointer out of bounds: -1");
+}
+
+// The syntax errors of the commands, none when they parse
+fn syntax_errors(commands: Vec<(Command, usize)>, source: &str) -> Option<Vec<SyntaxError>> {
+    match syntax_analysis(commands, source) {
+        Err(BrainfuckError::Syntax(errors)) => Some(errors),
+        _ => None,
+    }
+}
+
+#[test]
+fn parser_rejects_unmatched_brackets() -> Result<(), Box<dyn std::error::Error>> {
+    assert_eq!(
+        syntax_errors(lexical_analysis_str("[[]"), "[[]"),
+        Some(vec![SyntaxError::UnmatchedOpen { line: 1, col: 1 }])
+    );
+    assert_eq!(
+        syntax_errors(lexical_analysis_str("+ []]"), "+ []]"),
+        Some(vec![SyntaxError::UnmatchedClose { line: 1, col: 5 }])
+    );
+    let source = "+\n[ [-]\n  ] ]";
+    assert_eq!(
+        syntax_errors(lexical_analysis_str(source), source),
+        Some(vec![SyntaxError::UnmatchedClose { line: 3, col: 5 }])
+    );
+    let source = "] [\n[-]]]\n[";
+    assert_eq!(
+        syntax_errors(lexical_analysis_str(source), source),
+        Some(vec![
+            SyntaxError::UnmatchedClose { line: 1, col: 1 },
+            SyntaxError::UnmatchedClose { line: 2, col: 5 },
+            SyntaxError::UnmatchedOpen { line: 3, col: 1 },
+        ])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn program_runs_on_tape() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("++[->+++<]>")?;
+    let mut interpreter = interpreter_init(8);
+    run_program(&mut interpreter, &ast)?;
+    assert_eq!(interpreter.pointer, 1);
+    assert_eq!(interpreter.memory[0], 0);
+    assert_eq!(interpreter.memory[1], 6);
+
+    Ok(())
+}
+
+#[test]
+fn cells_are_iterated() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = interpreter_init(5);
+    run_program(&mut interpreter, &parse("++>>+++>-")?)?;
+    assert_eq!(
+        interpreter.cells().collect::<Vec<_>>(),
+        vec![(0, 2), (1, 0), (2, 3), (3, 255), (4, 0)]
+    );
+    assert_eq!(
+        interpreter.nonzero_cells().collect::<Vec<_>>(),
+        vec![(0, 2), (2, 3), (3, 255)]
+    );
+    assert_eq!(interpreter_init(3).nonzero_cells().count(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn escapes_are_parsed() -> Result<(), String> {
+    assert_eq!(
+        parse_escapes("a\\n\\t\\x41\\\\\\0")?,
+        b"a\n\tA\\\0".to_vec()
+    );
+    assert_eq!(parse_escapes("é")?, "é".as_bytes().to_vec());
+    assert!(parse_escapes("\\x4").is_err());
+    assert!(parse_escapes("\\xzz").is_err());
+    assert!(parse_escapes("\\q").is_err());
+    assert!(parse_escapes("\\").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn input_comes_from_buffer() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse(",>,>+,")?;
+    let mut interpreter = interpreter_init(4);
+    interpreter.input = InputSource::Buffer(b"AB".to_vec(), 0);
+    interpreter.eof_behavior = EofBehavior::Unchanged;
+    run_program(&mut interpreter, &ast)?;
+    assert_eq!(interpreter.memory, vec![65, 66, 1, 0]);
+
+    Ok(())
+}
+
+#[test]
+fn io_goes_through_callbacks() -> Result<(), Box<dyn std::error::Error>> {
+    let mut input = b"hi".to_vec().into_iter();
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let written = Rc::clone(&output);
+    let mut interpreter = interpreter_init(4).with_io(
+        move || input.next(),
+        move |byte| written.borrow_mut().push(byte),
+    );
+    interpreter.eof_behavior = EofBehavior::Max;
+    run_program(&mut interpreter, &parse(",.,+.,.")?)?;
+    assert_eq!(*output.borrow(), b"hj\xff");
+
+    Ok(())
+}
+
+#[test]
+fn output_goes_to_writer() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("++++++++[>++++++++<-]>+.+.")?;
+    let captured = Rc::new(RefCell::new(Vec::new()));
+    let mut interpreter = interpreter_init(4);
+    interpreter.output = Box::new(SharedOutput(Rc::clone(&captured)));
+    run_program(&mut interpreter, &ast)?;
+    assert_eq!(*captured.borrow(), b"AB".to_vec());
+
+    Ok(())
+}
+
+#[test]
+fn output_errors_stop_the_program() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse(".+")?;
+    let mut interpreter = interpreter_init(4);
+    interpreter.output = Box::new(FullOutput);
+    assert_eq!(
+        run_program(&mut interpreter, &ast),
+        Err(InterpreterError::Output(io::ErrorKind::StorageFull))
+    );
+    assert_eq!(interpreter.memory[0], 0);
+
+    Ok(())
+}
+
+#[test]
+fn output_formats() {
+    assert_eq!(OutputFormatter::Char.format(10), b"\n".to_vec());
+    assert_eq!(OutputFormatter::Decimal.format(7), b"7\n".to_vec());
+    assert_eq!(OutputFormatter::Hex.format(10), b"0x0A\n".to_vec());
+    assert_eq!(OutputFormatter::Binary.format(5), b"00000101\n".to_vec());
+}
+
+#[test]
+fn dynamic_tape_doubles() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse(">>>>+")?;
+    let mut interpreter = interpreter_init(3);
+    interpreter.overflow_mode = OverflowMode::Grow;
+    run_program(&mut interpreter, &ast)?;
+    assert_eq!(interpreter.tape_size, 6);
+    assert_eq!(interpreter.memory, vec![0, 0, 0, 0, 1, 0]);
+
+    Ok(())
+}
+
+#[test]
+fn dynamic_tape_respects_limit() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("+[>+]")?;
+    let mut interpreter = interpreter_init(2);
+    interpreter.overflow_mode = OverflowMode::Grow;
+    interpreter.max_tape_size = 5;
+    assert_eq!(
+        run_program(&mut interpreter, &ast),
+        Err(InterpreterError::TapeLimitExceeded {
+            pointer: 5,
+            limit: 5
+        })
+    );
+    assert_eq!(interpreter.memory, vec![1; 5]);
+
+    let ast = parse("<")?;
+    interpreter.pointer = 0;
+    assert_eq!(
+        run_program(&mut interpreter, &ast),
+        Err(InterpreterError::PointerOutOfBounds { pointer: -1 })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bidirectional_tape_grows_left() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("<<<+>>>>+")?;
+    let mut interpreter = interpreter_init(2);
+    interpreter.overflow_mode = OverflowMode::Bidirectional;
+    run_program(&mut interpreter, &ast)?;
+    assert_eq!(interpreter.pointer, 1);
+    assert_eq!(interpreter.offset, -6);
+    assert_eq!(interpreter.tape_size, 8);
+    assert_eq!(interpreter.get_cell(-3)?, 1);
+    assert_eq!(interpreter.get_cell(1)?, 1);
+    assert_eq!(interpreter.get_cell(0)?, 0);
+
+    Ok(())
+}
+
+#[test]
+fn cell_helpers_grow_a_growing_tape() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = interpreter_init(2);
+    interpreter.overflow_mode = OverflowMode::Bidirectional;
+    interpreter.set_cell(-2, 7)?;
+    interpreter.set_cell(3, 9)?;
+    assert_eq!(interpreter.offset, -2);
+    assert_eq!(interpreter.tape_size, 8);
+    assert_eq!(interpreter.memory, vec![7, 0, 0, 0, 0, 9, 0, 0]);
+    assert_eq!(interpreter.get_cell(-2)?, 7);
+    assert_eq!(interpreter.get_cell(3)?, 9);
+    assert_eq!(interpreter.cell_address(5), 3);
+
+    Ok(())
+}
+
+#[test]
+fn cell_helpers_wrap_or_refuse_a_fixed_tape() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = interpreter_init(4);
+    interpreter.set_cell(-1, 7)?;
+    assert_eq!(interpreter.get_cell(3)?, 7);
+    assert_eq!(interpreter.offset, 0);
+    assert_eq!(interpreter.tape_size, 4);
+    assert_eq!(interpreter.memory, vec![0, 0, 0, 7]);
+
+    interpreter.overflow_mode = OverflowMode::Error;
+    assert_eq!(
+        interpreter.get_cell(-1),
+        Err(InterpreterError::PointerOutOfBounds { pointer: -1 })
+    );
+    assert_eq!(
+        interpreter.set_cell(4, 1),
+        Err(InterpreterError::PointerOutOfBounds { pointer: 4 })
+    );
+    assert_eq!(interpreter.memory, vec![0, 0, 0, 7]);
+
+    Ok(())
+}
+
+// Lists the kind and command of every node, depth first
+fn shape(node: &Node) -> Vec<(NodeType, Command, usize)> {
+    let mut nodes = vec![(node.node_type, node.instruction, node.childrens.len())];
+    for child in node.childrens.iter() {
+        nodes.extend(shape(child));
+    }
+    nodes
+}
+
+#[test]
+fn display_round_trips_to_source() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "read , print . [> move [-] <+] done";
+    let ast = parse(source)?;
+    let printed = ast.to_string();
+    assert_eq!(printed, ",.[>[-]<+]");
+    let reparsed = parse(&printed)?;
+    assert_eq!(shape(&reparsed), shape(&ast));
+
+    Ok(())
+}
+
+#[test]
+fn deep_nesting_does_not_overflow_the_stack() -> Result<(), Box<dyn std::error::Error>> {
+    // a clear loop nested 100000 loops deep, built bottom up as the parser recurses too
+    let mut node = Node {
+        node_type: NodeType::Loop,
+        instruction: Command::JumpForward,
+        source_pos: 0,
+        childrens: vec![Node {
+            node_type: NodeType::Operator,
+            instruction: Command::DecByte,
+            source_pos: 0,
+            childrens: Vec::new(),
+        }],
+    };
+    for _ in 0..100_000 {
+        node = Node {
+            node_type: NodeType::Loop,
+            instruction: Command::JumpForward,
+            source_pos: 0,
+            childrens: vec![node],
+        };
+    }
+    let ast = Node {
+        node_type: NodeType::Program,
+        instruction: Command::Default,
+        source_pos: 0,
+        childrens: vec![
+            Node {
+                node_type: NodeType::Operator,
+                instruction: Command::AddByte(3),
+                source_pos: 0,
+                childrens: Vec::new(),
+            },
+            node,
+        ],
+    };
+    let mut interpreter = interpreter_init(4);
+    run_program(&mut interpreter, &ast)?;
+    assert_eq!(interpreter.memory[0], 0);
+    // dropping the tree recurses once per level
+    std::mem::forget(ast);
+
+    Ok(())
+}
+
+#[test]
+fn step_limit_stops_infinite_loops() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("+[]")?;
+    let mut interpreter = interpreter_init(4);
+    interpreter.max_steps = Some(10);
+    assert_eq!(
+        run_program(&mut interpreter, &ast),
+        Err(InterpreterError::StepLimitExceeded { steps: 10 })
+    );
+    assert_eq!(interpreter.steps, 10);
+
+    let ast = parse("++[-]")?;
+    let mut interpreter = interpreter_init(4);
+    interpreter.max_steps = Some(5);
+    run_program(&mut interpreter, &ast)?;
+    assert_eq!(interpreter.steps, 5);
+
+    Ok(())
+}
+
+#[test]
+fn progress_counts_steps() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("++[->+<]")?;
+    let mut interpreter = interpreter_init(4);
+    interpreter.progress = Some(Progress {
+        interval: 1_000_000,
+        start: Instant::now(),
+        shown: false,
+    });
+    run_program(&mut interpreter, &ast)?;
+    assert_eq!(interpreter.steps, 11);
+    assert!(!interpreter.progress.as_ref().unwrap().shown);
+
+    interpreter.steps = 20_000_000;
+    interpreter.pointer = 1;
+    assert_eq!(
+        progress_line(&interpreter, Duration::from_millis(1240)),
+        "[step=20M | ptr=1 | cell=2 | elapsed=1.2s]"
+    );
+    interpreter.steps = 1500;
+    assert_eq!(
+        progress_line(&interpreter, Duration::from_secs(3)),
+        "[step=1500 | ptr=1 | cell=2 | elapsed=3.0s]"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn timeout_stops_infinite_loops() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("+[]")?;
+    let mut interpreter = interpreter_init(4);
+    interpreter.timeout = Some(Duration::from_millis(50));
+    assert_eq!(
+        run_program(&mut interpreter, &ast),
+        Err(InterpreterError::Timeout)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn timeout_covers_nested_subroutine_calls() -> Result<(), Box<dyn std::error::Error>> {
+    // every call takes a few milliseconds before calling itself again, until the
+    // call stack overflows long after the timeout
+    let source = "{>
+        ++++++++++++++++[>++++++++++++++++[>++++++++++++++++[>++++++++++++++++
+        [-]<-]<-]<-]
+    :}:";
+    let ast = syntax_analysis(
+        lexical_analysis_with_extensions(source.to_string(), true),
+        source,
+    )?;
+    let mut interpreter = interpreter_init(512);
+    interpreter.timeout = Some(Duration::from_millis(50));
+    assert_eq!(
+        run_program(&mut interpreter, &ast),
+        Err(InterpreterError::Timeout)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn loop_iteration_limit_names_the_loop() -> Result<(), Box<dyn std::error::Error>> {
+    // the inner loop runs 3 times each time it is entered, the outer one 4 times
+    let source = "++++[>+++[-]<-] +[>+<]";
+    let ast = parse(source)?;
+    let mut interpreter = interpreter_init(4);
+    interpreter.max_loop_iters = Some(4);
+    assert_eq!(
+        run_program(&mut interpreter, &ast),
+        Err(InterpreterError::LoopIterationLimitExceeded { offset: 17 })
+    );
+    assert_eq!(interpreter.memory[1], 4);
+
+    let mut interpreter = interpreter_init(4);
+    interpreter.max_loop_iters = Some(3);
+    assert_eq!(
+        run_program(&mut interpreter, &ast),
+        Err(InterpreterError::LoopIterationLimitExceeded { offset: 4 })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn should_stop_interrupts_loops() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("+>+[]")?;
+    let mut interpreter = interpreter_init(4);
+    interpreter
+        .should_stop
+        .store(true, std::sync::atomic::Ordering::Relaxed);
+    assert_eq!(
+        run_program(&mut interpreter, &ast),
+        Err(InterpreterError::Interrupted)
+    );
+    assert_eq!(interpreter.memory, vec![1, 1, 0, 0]);
+
+    Ok(())
+}
+
+#[test]
+fn debug_print_is_an_extension() -> Result<(), Box<dyn std::error::Error>> {
+    assert_eq!(lexical_analysis_str("+#").len(), 1);
+    assert_eq!(
+        lexical_analysis_with_extensions("+#".to_string(), true),
+        vec![(Command::IncByte, 0), (Command::DebugPrint, 1)]
+    );
+
+    let source = "+>++>>>>>>>+++#<<<<<<<<#";
+    let ast = syntax_analysis(
+        lexical_analysis_with_extensions(source.to_string(), true),
+        source,
+    )?;
+    let mut interpreter = interpreter_init(16);
+    run_program(&mut interpreter, &ast)?;
+    assert_eq!(interpreter.memory[8], 3);
+    assert_eq!(
+        debug_print(&interpreter, 0),
+        "pointer 0, cells 0 to 5\n   1   2   0   0   0   0\n   ^\n"
+    );
+    interpreter.pointer = 8;
+    assert_eq!(
+        debug_print(&interpreter, 8),
+        "pointer 8, cells 3 to 13\n   0   0   0   0   0   3   0   0   0   0   0\n                       ^\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn colored_debug_print() {
+    let mut interpreter = interpreter_init(4);
+    interpreter.memory[1] = 7;
+    interpreter.pointer = 1;
+    interpreter.color = true;
+    assert_eq!(
+        debug_print(&interpreter, 1),
+        format!(
+            "pointer 1, cells 0 to 3\n   0{}   0   0\n       ^\n",
+            paint("   7", &pointer_style(), true)
+        )
+    );
+    assert_eq!(paint("   7", &pointer_style(), false), "   7");
+    assert!(paint("error", &error_style(), true).contains("\u{1b}[31m"));
+}
+
+#[test]
+fn halt_stops_the_program() -> Result<(), Box<dyn std::error::Error>> {
+    assert_eq!(lexical_analysis_str("@"), vec![]);
+
+    let source = "+++[>+@<-]>++";
+    let ast = syntax_analysis(
+        lexical_analysis_with_extensions(source.to_string(), true),
+        source,
+    )?;
+    let mut interpreter = interpreter_init(4);
+    assert_eq!(
+        run_program(&mut interpreter, &ast),
+        Err(InterpreterError::HaltInstruction)
+    );
+    assert_eq!(interpreter.memory, vec![3, 1, 0, 0]);
+    assert_eq!(interpreter.pointer, 1);
+
+    Ok(())
+}
+
+#[test]
+fn subroutines_run_when_called() -> Result<(), Box<dyn std::error::Error>> {
+    let run = |source: &str| -> Result<_, Box<dyn std::error::Error>> {
+        let ast = syntax_analysis(
+            lexical_analysis_with_extensions(source.to_string(), true),
+            source,
+        )?;
+        let mut interpreter = interpreter_init(4);
+        let result = run_program(&mut interpreter, &ast);
+        Ok((interpreter, result))
+    };
+
+    let (interpreter, result) = run(">{>+++<}<:>:")?;
+    result?;
+    assert_eq!(interpreter.memory, vec![0, 3, 3, 0]);
+    assert_eq!(interpreter.last_subroutine, Some(1));
+    assert!(interpreter.subroutines.contains_key(&1));
+    assert!(interpreter.call_stack.is_empty());
+
+    let (_, result) = run("+:")?;
+    assert_eq!(
+        result,
+        Err(InterpreterError::UndefinedSubroutine { offset: 1 })
+    );
+    let (interpreter, result) = run("{>:}:")?;
+    assert_eq!(
+        result,
+        Err(InterpreterError::CallStackOverflow { offset: 2 })
+    );
+    assert_eq!(interpreter.pointer, 256);
+    assert!(interpreter.call_stack.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn subroutine_braces_must_match() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "[{]}}";
+    let errors = syntax_errors(
+        lexical_analysis_with_extensions(source.to_string(), true),
+        source,
+    )
+    .ok_or("mismatched braces were accepted")?;
+    assert_eq!(
+        errors,
+        vec![
+            SyntaxError::UnmatchedOpen { line: 1, col: 1 },
+            SyntaxError::UnmatchedClose { line: 1, col: 3 },
+            SyntaxError::UnmatchedCloseBrace { line: 1, col: 5 },
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn overflow_check_stops_wrapping_cells() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = interpreter_init(8);
+    interpreter.overflow_check = true;
+    assert_eq!(
+        run_program(&mut interpreter, &parse(">+-<-")?),
+        Err(InterpreterError::CellOverflow {
+            cell: 0,
+            direction: OverflowDirection::Down,
+        })
+    );
+
+    let source = format!(",>{}[-<+>]", "+".repeat(56));
+    let mut interpreter = interpreter_init(8);
+    interpreter.overflow_check = true;
+    interpreter.input = InputSource::Buffer(vec![200], 0);
+    assert_eq!(
+        run_program(&mut interpreter, &parse(&source)?),
+        Err(InterpreterError::CellOverflow {
+            cell: 0,
+            direction: OverflowDirection::Up,
+        })
+    );
+    assert_eq!(interpreter.memory[0], 255);
+
+    // optimized, the whole transfer is checked at once
+    let mut ast = parse(&source)?;
+    brainfuck::optimizer::optimize(&mut ast);
+    let mut interpreter = interpreter_init(8);
+    interpreter.overflow_check = true;
+    interpreter.input = InputSource::Buffer(vec![200], 0);
+    assert!(run_program(&mut interpreter, &ast).is_err());
+    assert_eq!((interpreter.memory[0], interpreter.memory[1]), (200, 56));
+
+    Ok(())
+}
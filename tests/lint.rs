@@ -0,0 +1,109 @@
+use brainfuck::{
+    lexical_analysis_str, lexical_analysis_with_extensions,
+    lint::{
+        analyze_termination, count_nodes, find_canceling_pairs, has_output, max_loop_depth,
+        CancelingPair, InfiniteLoopWarning,
+    },
+    syntax_analysis,
+};
+
+mod common;
+use common::parse;
+
+#[test]
+fn canceling_pairs_are_found() -> Result<(), Box<dyn std::error::Error>> {
+    let commands = lexical_analysis_str("+-> comment <<>.-+[-]");
+    assert_eq!(
+        find_canceling_pairs(&commands),
+        vec![
+            CancelingPair {
+                offset: 0,
+                pair: "+-"
+            },
+            CancelingPair {
+                offset: 2,
+                pair: "><"
+            },
+            CancelingPair {
+                offset: 13,
+                pair: "<>"
+            },
+            CancelingPair {
+                offset: 16,
+                pair: "-+"
+            },
+        ]
+    );
+    assert!(find_canceling_pairs(&lexical_analysis_str("++>>[-]<<")).is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn warning_names_the_pair() {
+    let warning = CancelingPair {
+        offset: 7,
+        pair: "><",
+    };
+    assert_eq!(
+        warning.to_string(),
+        "warning at offset 7: adjacent canceling instructions '><'"
+    );
+}
+
+#[test]
+fn loops_that_only_print_never_end() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("+[.] [>] [[-]] [,] [.[]]")?;
+    assert_eq!(
+        analyze_termination(&ast),
+        vec![
+            InfiniteLoopWarning { offset: 1 },
+            InfiniteLoopWarning { offset: 19 },
+            InfiniteLoopWarning { offset: 21 },
+        ]
+    );
+    assert_eq!(
+        InfiniteLoopWarning { offset: 1 }.to_string(),
+        "warning: potentially infinite loop at source offset 1"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn loop_depth_is_the_deepest_nesting() -> Result<(), Box<dyn std::error::Error>> {
+    assert_eq!(max_loop_depth(&parse("+>-.")?), 0);
+    assert_eq!(max_loop_depth(&parse("[[-]]+[[[>]]<]")?), 3);
+    assert_eq!(
+        max_loop_depth(&parse(&format!("{}{}", "[".repeat(600), "]".repeat(600)))?),
+        600
+    );
+
+    Ok(())
+}
+
+#[test]
+fn nodes_are_counted_below_the_root() -> Result<(), Box<dyn std::error::Error>> {
+    assert_eq!(count_nodes(&parse("")?), 0);
+    assert_eq!(count_nodes(&parse("+>-.")?), 4);
+    assert_eq!(count_nodes(&parse("+[->[+]<]")?), 7);
+
+    Ok(())
+}
+
+#[test]
+fn output_is_found_anywhere() -> Result<(), Box<dyn std::error::Error>> {
+    assert!(has_output(&parse("+.")?));
+    assert!(has_output(&parse("+[>[.-]<-]")?));
+    assert!(!has_output(&parse("+[->+<],")?));
+    assert!(!has_output(&parse("")?));
+
+    let source = "{.}:";
+    let ast = syntax_analysis(
+        lexical_analysis_with_extensions(source.to_string(), true),
+        source,
+    )?;
+    assert!(has_output(&ast));
+
+    Ok(())
+}
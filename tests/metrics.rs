@@ -0,0 +1,28 @@
+use brainfuck::{metrics::compute_metrics, profile::command_index, Command};
+
+mod common;
+use common::parse;
+
+#[test]
+fn metrics_count_the_tree() -> Result<(), Box<dyn std::error::Error>> {
+    let metrics = compute_metrics(&parse("++[>,[-.]<]")?);
+    assert_eq!(metrics.counts[command_index(&Command::IncByte)], 2);
+    assert_eq!(metrics.counts[command_index(&Command::InByte)], 1);
+    assert_eq!(metrics.operators(), 7);
+    assert_eq!(metrics.loops, 2);
+    assert_eq!(metrics.max_depth, 2);
+    assert_eq!(metrics.io, 2);
+    assert!((metrics.loop_ratio() - 2.0 / 7.0).abs() < 1e-9);
+    assert!(metrics.report().contains("loops/operators         0.29\n"));
+
+    Ok(())
+}
+
+#[test]
+fn metrics_of_an_empty_program() -> Result<(), Box<dyn std::error::Error>> {
+    let metrics = compute_metrics(&parse("")?);
+    assert_eq!(metrics.operators(), 0);
+    assert_eq!(metrics.loop_ratio(), 0.0);
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{boxed::Box, rc::Rc, string::ToString, vec::Vec};
+use brainfuck::{interpreter_init, run_program, IoProvider, Program};
+use core::cell::RefCell;
+
+// Input from a slice with the output kept where the test can read it
+struct BufferIo {
+    input: Vec<u8>,
+    output: Rc<RefCell<Vec<u8>>>,
+}
+
+impl IoProvider for BufferIo {
+    fn read_byte(&mut self) -> Option<u8> {
+        if self.input.is_empty() {
+            None
+        } else {
+            Some(self.input.remove(0))
+        }
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        self.output.borrow_mut().push(byte)
+    }
+}
+
+#[test]
+fn runs_through_an_io_provider() {
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let mut interpreter = interpreter_init(8);
+    interpreter.io = Box::new(BufferIo {
+        input: b"ab".to_vec(),
+        output: output.clone(),
+    });
+    run_program(
+        &mut interpreter,
+        &",+.,+.,.".parse::<Program>().unwrap().ast,
+    )
+    .unwrap();
+    assert_eq!(&output.borrow()[..], b"bc\0");
+}
+
+#[test]
+fn with_io_takes_callbacks() {
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let written = output.clone();
+    let mut interpreter =
+        interpreter_init(4).with_io(|| Some(3), move |byte| written.borrow_mut().push(byte));
+    run_program(
+        &mut interpreter,
+        &",[->++<]>.".parse::<Program>().unwrap().ast,
+    )
+    .unwrap();
+    assert_eq!(&output.borrow()[..], &[6]);
+}
@@ -0,0 +1,60 @@
+use brainfuck::{
+    interpreter_init, lexical_analysis_str,
+    ook::{lex_ook, parse_ook, OokError},
+    run_program, syntax_analysis,
+};
+
+#[test]
+fn ook_pairs_map_to_commands() -> Result<(), Box<dyn std::error::Error>> {
+    let ook = "Ook. Ook? Ook? Ook. Ook. Ook. Ook! Ook!\nOok! Ook. Ook. Ook! Ook! Ook? Ook? Ook!";
+    let expected: Vec<_> = lexical_analysis_str("><+-.,[]")
+        .into_iter()
+        .map(|(cmd, _)| cmd)
+        .collect();
+    assert_eq!(parse_ook(ook)?, expected);
+
+    Ok(())
+}
+
+#[test]
+fn ook_offsets_point_at_the_first_token() -> Result<(), OokError> {
+    let offsets: Vec<usize> = lex_ook("Ook. Ook.\n  Ook! Ook!")?
+        .into_iter()
+        .map(|(_, offset)| offset)
+        .collect();
+    assert_eq!(offsets, vec![0, 12]);
+
+    Ok(())
+}
+
+#[test]
+fn ook_rejects_bad_input() {
+    assert_eq!(
+        parse_ook("Ook. Ook. Ook, Ook."),
+        Err(OokError::UnknownToken {
+            token: "Ook,".to_string(),
+            offset: 10
+        })
+    );
+    assert_eq!(
+        parse_ook("Ook. Ook. Ook? Ook?"),
+        Err(OokError::UnknownPair { offset: 10 })
+    );
+    assert_eq!(
+        parse_ook("Ook. Ook. Ook."),
+        Err(OokError::UnpairedToken { offset: 10 })
+    );
+}
+
+#[test]
+fn ook_runs_through_the_tree_walker() -> Result<(), Box<dyn std::error::Error>> {
+    // +++[>++<-]
+    let source = "Ook. Ook. Ook. Ook. Ook. Ook. Ook! Ook? Ook. Ook? Ook. Ook. Ook. Ook. \
+                  Ook? Ook. Ook! Ook! Ook? Ook!";
+    let ast = syntax_analysis(lex_ook(source)?, source)?;
+    let mut interpreter = interpreter_init(4);
+    run_program(&mut interpreter, &ast)?;
+    assert_eq!(interpreter.memory, vec![0, 6, 0, 0]);
+
+    Ok(())
+}
@@ -0,0 +1,366 @@
+use brainfuck::{
+    interpreter_init,
+    optimizer::{
+        detect_addition_loops, detect_clear_loops, detect_scan_loops, eliminate_dead_loops,
+        fold_adds, fold_moves, minify, optimize, optimizer_pipeline, ClearLoopPass, FoldPass,
+        Pipeline, Transform,
+    },
+    run_program, Command, InterpreterError, Node, NodeType, OverflowMode,
+};
+use std::{cell::Cell, rc::Rc};
+
+mod common;
+use common::parse;
+
+#[test]
+fn fold_moves_merges_pointer_moves() -> Result<(), Box<dyn std::error::Error>> {
+    let mut ast = parse(">>><+[<<>]><")?;
+    fold_moves(&mut ast);
+    let instructions: Vec<Command> = ast.childrens.iter().map(|n| n.instruction).collect();
+    assert_eq!(
+        instructions,
+        vec![Command::MoveBy(2), Command::IncByte, Command::JumpForward]
+    );
+    assert_eq!(
+        ast.childrens[2].childrens[0].instruction,
+        Command::MoveBy(-1)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn fold_adds_merges_cell_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let source = format!("++++--.+-.{}.[-]", "-".repeat(257));
+    let mut ast = parse(&source)?;
+    fold_adds(&mut ast);
+    let instructions: Vec<Command> = ast.childrens.iter().map(|n| n.instruction).collect();
+    assert_eq!(
+        instructions,
+        vec![
+            Command::AddByte(2),
+            Command::OutByte,
+            Command::OutByte,
+            Command::AddByte(-1),
+            Command::OutByte,
+            Command::JumpForward
+        ]
+    );
+    assert_eq!(
+        ast.childrens[5].childrens[0].instruction,
+        Command::AddByte(-1)
+    );
+
+    let mut interpreter = interpreter_init(1);
+    let source = "-".repeat(300);
+    let mut ast = parse(&source)?;
+    fold_adds(&mut ast);
+    run_program(&mut interpreter, &ast)?;
+    assert_eq!(interpreter.memory[0], (256 * 2 - 300) as u8);
+
+    Ok(())
+}
+
+#[test]
+fn clear_loops_become_set_zero() -> Result<(), Box<dyn std::error::Error>> {
+    let mut ast = parse("+++[-]>[+]>[--][>[---]]")?;
+    fold_adds(&mut ast);
+    detect_clear_loops(&mut ast);
+    let instructions: Vec<Command> = ast.childrens.iter().map(|n| n.instruction).collect();
+    assert_eq!(
+        instructions,
+        vec![
+            Command::AddByte(3),
+            Command::SetZero,
+            Command::IncDP,
+            Command::SetZero,
+            Command::IncDP,
+            Command::JumpForward,
+            Command::JumpForward
+        ]
+    );
+    assert_eq!(ast.childrens[6].childrens[1].instruction, Command::SetZero);
+
+    let mut interpreter = interpreter_init(1);
+    run_program(&mut interpreter, &ast)?;
+    assert_eq!(interpreter.memory[0], 0);
+
+    Ok(())
+}
+
+#[test]
+fn addition_loops_become_add_to() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "+++++[->>+<<]>>>++[<+>-]<[->+<<]";
+    let mut ast = parse(source)?;
+    fold_moves(&mut ast);
+    fold_adds(&mut ast);
+    detect_addition_loops(&mut ast);
+    let instructions: Vec<Command> = ast.childrens.iter().map(|n| n.instruction).collect();
+    assert_eq!(
+        instructions,
+        vec![
+            Command::AddByte(5),
+            Command::AddTo { offset: 2 },
+            Command::MoveBy(3),
+            Command::AddByte(2),
+            Command::AddTo { offset: -1 },
+            Command::MoveBy(-1),
+            Command::JumpForward
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn optimized_program_matches_plain_run() -> Result<(), Box<dyn std::error::Error>> {
+    run_both(
+        "+++++[->>+<<]>>[-<+>]<[->+++<]>[>+<-]++[>[-]<-]<<++[>>>+<<<-]",
+        8,
+    )
+}
+
+fn run_both(source: &str, size: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let plain = parse(source)?;
+    let mut optimized = parse(source)?;
+    optimize(&mut optimized);
+
+    let mut expected = interpreter_init(size);
+    run_program(&mut expected, &plain)?;
+    let mut actual = interpreter_init(size);
+    run_program(&mut actual, &optimized)?;
+    assert_eq!(actual.memory, expected.memory);
+    assert_eq!(actual.pointer, expected.pointer);
+
+    Ok(())
+}
+
+#[test]
+fn scan_loops_match_plain_run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut ast = parse("[>]+[<]")?;
+    detect_scan_loops(&mut ast);
+    let instructions: Vec<Command> = ast.childrens.iter().map(|n| n.instruction).collect();
+    assert_eq!(
+        instructions,
+        vec![Command::ScanRight, Command::IncByte, Command::ScanLeft]
+    );
+
+    run_both("+>+>+>>+<<<<[>]<[<]", 8)?;
+    run_both("+>+>+>+>+>+[>]+[<]", 8)?;
+    run_both(">>>+<<<+<+<+[<]>+[>]", 8)?;
+
+    Ok(())
+}
+
+#[test]
+fn scan_past_tape_end_is_an_error() -> Result<(), Box<dyn std::error::Error>> {
+    let mut ast = parse("+>+>+>+[>]")?;
+    optimize(&mut ast);
+    let mut interpreter = interpreter_init(4);
+    interpreter.overflow_mode = OverflowMode::Error;
+    assert!(run_program(&mut interpreter, &ast).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn scan_without_zero_cell_is_an_error() -> Result<(), Box<dyn std::error::Error>> {
+    for source in ["+>+>+>+[>]", "+>+>+>+[<]"].iter() {
+        let mut ast = parse(source)?;
+        optimize(&mut ast);
+        let mut interpreter = interpreter_init(4);
+        assert_eq!(
+            run_program(&mut interpreter, &ast),
+            Err(InterpreterError::EndlessScan { pointer: 3 })
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn moves_are_only_folded_on_a_wrapping_tape() -> Result<(), Box<dyn std::error::Error>> {
+    let eliminated = Rc::new(Cell::new(0));
+    let ast = optimizer_pipeline(&eliminated, OverflowMode::Error).run(parse(">>>><+")?);
+    assert_eq!(ast.childrens.len(), 6);
+    let mut interpreter = interpreter_init(4);
+    interpreter.overflow_mode = OverflowMode::Error;
+    assert_eq!(
+        run_program(&mut interpreter, &ast),
+        Err(InterpreterError::PointerOutOfBounds { pointer: 4 })
+    );
+
+    let ast = optimizer_pipeline(&eliminated, OverflowMode::Wrap).run(parse(">>>><+")?);
+    let instructions: Vec<Command> = ast.childrens.iter().map(|n| n.instruction).collect();
+    assert_eq!(instructions, vec![Command::MoveBy(3), Command::AddByte(1)]);
+
+    Ok(())
+}
+
+#[test]
+fn scans_and_additions_grow_the_tape() -> Result<(), Box<dyn std::error::Error>> {
+    let mut ast = parse("+>+>+[>]+<<[->>>>+<<<<]")?;
+    optimize(&mut ast);
+    let mut interpreter = interpreter_init(3);
+    interpreter.overflow_mode = OverflowMode::Grow;
+    run_program(&mut interpreter, &ast)?;
+    assert_eq!(interpreter.pointer, 1);
+    assert_eq!(interpreter.tape_size, 6);
+    assert_eq!(interpreter.memory, vec![1, 0, 1, 1, 0, 1]);
+
+    Ok(())
+}
+
+#[test]
+fn scans_grow_a_bidirectional_tape() -> Result<(), Box<dyn std::error::Error>> {
+    let mut ast = parse("+>+<[<]+>>[>]+")?;
+    optimize(&mut ast);
+    let mut interpreter = interpreter_init(2);
+    interpreter.overflow_mode = OverflowMode::Bidirectional;
+    run_program(&mut interpreter, &ast)?;
+    assert_eq!(interpreter.pointer, 2);
+    assert_eq!(interpreter.offset, -2);
+    assert_eq!(interpreter.tape_size, 8);
+    let cells = (-2..4)
+        .map(|address| interpreter.get_cell(address))
+        .collect::<Result<Vec<u8>, _>>()?;
+    assert_eq!(cells, vec![0, 1, 1, 1, 1, 0]);
+
+    Ok(())
+}
+
+#[test]
+fn dead_loops_are_eliminated() -> Result<(), Box<dyn std::error::Error>> {
+    let mut ast = parse("+[-][>+<][-]>[<]+[[-][.]]")?;
+    ast.childrens.insert(
+        1,
+        Node {
+            node_type: NodeType::Operator,
+            instruction: Command::Default,
+            source_pos: 0,
+            childrens: Vec::new(),
+        },
+    );
+    detect_clear_loops(&mut ast);
+    assert_eq!(eliminate_dead_loops(&mut ast), 3);
+    let instructions: Vec<Command> = ast.childrens.iter().map(|n| n.instruction).collect();
+    assert_eq!(
+        instructions,
+        vec![
+            Command::IncByte,
+            Command::SetZero,
+            Command::SetZero,
+            Command::IncDP,
+            Command::JumpForward,
+            Command::IncByte,
+            Command::JumpForward
+        ]
+    );
+    assert_eq!(ast.childrens[6].childrens.len(), 1);
+    assert_eq!(optimize(&mut parse("+[-][>+<]>[-][+>]")?), 2);
+
+    Ok(())
+}
+
+// Appends an output command to the program
+struct AppendOutput;
+
+impl Transform for AppendOutput {
+    fn transform_node(&mut self, mut node: Node) -> Node {
+        node.childrens.push(Node {
+            node_type: NodeType::Operator,
+            instruction: Command::OutByte,
+            source_pos: 0,
+            childrens: Vec::new(),
+        });
+        node
+    }
+}
+
+#[test]
+fn pipeline_runs_passes_in_order() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = Pipeline::new()
+        .add(FoldPass)
+        .add(ClearLoopPass)
+        .add(AppendOutput)
+        .run(parse("++[--]>>")?);
+    let instructions: Vec<Command> = ast.childrens.iter().map(|n| n.instruction).collect();
+    assert_eq!(
+        instructions,
+        vec![
+            Command::AddByte(2),
+            Command::JumpForward,
+            Command::MoveBy(2),
+            Command::OutByte
+        ]
+    );
+
+    let ast = Pipeline::new()
+        .add(ClearLoopPass)
+        .add(FoldPass)
+        .run(parse("+[-]+")?);
+    let instructions: Vec<Command> = ast.childrens.iter().map(|n| n.instruction).collect();
+    assert_eq!(
+        instructions,
+        vec![Command::AddByte(1), Command::SetZero, Command::AddByte(1)]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn optimized_tree_prints_as_source() -> Result<(), Box<dyn std::error::Error>> {
+    let mut ast = parse("+++>>>-<[-]<<[->>>+<<<]>[<]--")?;
+    optimize(&mut ast);
+    assert_eq!(ast.to_string(), "+++>>>-<[-]<<[->>>+<<<]>[<]--");
+    let mut ast = parse("<<>+-+")?;
+    optimize(&mut ast);
+    assert_eq!(ast.to_string(), "<+");
+
+    Ok(())
+}
+
+#[test]
+fn minify_removes_what_has_no_effect() -> Result<(), Box<dyn std::error::Error>> {
+    // a live empty loop never ends and has to stay
+    let ast = minify(
+        parse("+ comment [-][.]>><<[-[]+][-+]-+>+[]")?,
+        OverflowMode::Wrap,
+    );
+    assert_eq!(ast.to_string(), "+[-]>+[]");
+    assert!(minify(ast.clone(), OverflowMode::Wrap) == ast);
+
+    Ok(())
+}
+
+#[test]
+fn minify_keeps_the_moves_of_a_tape_with_ends() -> Result<(), Box<dyn std::error::Error>> {
+    // `<` at the first cell fails the run on these tapes
+    for overflow_mode in [OverflowMode::Error, OverflowMode::Grow] {
+        let ast = minify(parse("<>+-+>><")?, overflow_mode);
+        assert_eq!(ast.to_string(), "<>+>><");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn minified_program_leaves_the_same_tape() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "++++++++[>++++<-]>+-+.[-]-+[.]<<>>++++++++++[>+++++<-]>-.";
+    let minified = minify(parse(source)?, OverflowMode::Wrap);
+    assert_eq!(
+        minified.to_string(),
+        "++++++++[>++++<-]>+.[-]++++++++++[>+++++<-]>-."
+    );
+
+    let mut outputs = Vec::new();
+    for ast in [parse(source)?, parse(&minified.to_string())?].iter() {
+        let mut interpreter = interpreter_init(64);
+        interpreter.output = Box::new(Vec::new());
+        run_program(&mut interpreter, ast)?;
+        outputs.push((interpreter.memory.clone(), interpreter.pointer));
+    }
+    assert_eq!(outputs[0], outputs[1]);
+
+    Ok(())
+}
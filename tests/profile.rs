@@ -0,0 +1,133 @@
+use brainfuck::{
+    interpreter_init,
+    optimizer::optimize,
+    profile::{
+        command_index, profile_run_program, run_with_heatmap, Coverage, Hotspots,
+        InstructionProfile,
+    },
+    Command, OverflowMode,
+};
+
+mod common;
+use common::parse;
+
+#[test]
+fn profile_counts_executed_instructions() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("++[->+++<]")?;
+    let mut interpreter = interpreter_init(8);
+    let mut profile = InstructionProfile::default();
+    profile_run_program(&mut interpreter, &ast, &mut profile)?;
+    assert_eq!(profile.counts[command_index(&Command::IncByte)], 8);
+    assert_eq!(profile.counts[command_index(&Command::DecByte)], 2);
+    assert_eq!(profile.counts[command_index(&Command::IncDP)], 2);
+    assert_eq!(profile.counts[command_index(&Command::DecDP)], 2);
+    assert_eq!(profile.total(), 14);
+    assert_eq!(interpreter.memory[1], 6);
+
+    Ok(())
+}
+
+#[test]
+fn profile_report_lists_optimized_commands() -> Result<(), Box<dyn std::error::Error>> {
+    let mut ast = parse("+++[-]")?;
+    optimize(&mut ast);
+    let mut interpreter = interpreter_init(8);
+    let mut profile = InstructionProfile::default();
+    profile_run_program(&mut interpreter, &ast, &mut profile)?;
+    let report = profile.report();
+    assert!(report.contains("AddByte"));
+    assert!(report.contains("SetZero"));
+    assert!(!report.contains("IncByte"));
+    assert!(report.contains("total steps: 2"));
+
+    Ok(())
+}
+
+#[test]
+fn heatmap_counts_cell_accesses() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("+[-]>>+.")?;
+    let mut interpreter = interpreter_init(4);
+    let heatmap = run_with_heatmap(&mut interpreter, &ast)?;
+    assert_eq!(heatmap.reads, vec![4, 0, 2, 0]);
+    assert_eq!(heatmap.writes, vec![2, 0, 1, 0]);
+    assert_eq!(heatmap.hottest(20), vec![(0, 4, 2), (2, 2, 1)]);
+
+    Ok(())
+}
+
+#[test]
+fn heatmap_follows_optimized_commands() -> Result<(), Box<dyn std::error::Error>> {
+    let mut ast = parse("++[->+<]>[<]")?;
+    optimize(&mut ast);
+    let mut interpreter = interpreter_init(4);
+    let heatmap = run_with_heatmap(&mut interpreter, &ast)?;
+    assert_eq!(heatmap.reads, vec![3, 2, 0, 0]);
+    assert_eq!(heatmap.writes, vec![2, 1, 0, 0]);
+    assert!(heatmap.report(40).contains(" 0 |###"));
+
+    Ok(())
+}
+
+#[test]
+fn heatmap_counts_by_address_on_a_tape_grown_left() -> Result<(), Box<dyn std::error::Error>> {
+    let mut ast = parse("+>+<[<]+>>++[-<+>]")?;
+    optimize(&mut ast);
+    let mut interpreter = interpreter_init(2);
+    interpreter.overflow_mode = OverflowMode::Bidirectional;
+    let heatmap = run_with_heatmap(&mut interpreter, &ast)?;
+    assert_eq!(heatmap.offset, -2);
+    assert_eq!(heatmap.reads, vec![0, 2, 3, 3]);
+    assert_eq!(heatmap.writes, vec![0, 1, 2, 3]);
+    assert_eq!(heatmap.hottest(20), vec![(1, 3, 3), (0, 3, 2), (-1, 2, 1)]);
+
+    Ok(())
+}
+
+#[test]
+fn coverage_marks_the_instructions_that_ran() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "+[-]\n>[+++\n\t.<]";
+    let ast = parse(source)?;
+    let mut interpreter = interpreter_init(8);
+    let mut profile = InstructionProfile {
+        coverage: Some(Coverage::new(source.len())),
+        ..InstructionProfile::default()
+    };
+    profile_run_program(&mut interpreter, &ast, &mut profile)?;
+    let coverage = profile.coverage.unwrap();
+    // the second loop is checked but never entered
+    assert_eq!(
+        coverage.split(&ast),
+        (vec![0, 1, 2, 5, 6], vec![7, 8, 9, 12, 13])
+    );
+    assert_eq!(
+        coverage.report(&ast),
+        "covered: 5 instructions\nuncovered: 5 instructions\n"
+    );
+    assert_eq!(
+        coverage.annotate(source, &ast),
+        "+[-]\n>[+++\n  ^^^\n\t.<]\n\t^^\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn hotspots_count_every_run_of_an_instruction() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "+++[->++<]";
+    let ast = parse(source)?;
+    let mut interpreter = interpreter_init(8);
+    let mut profile = InstructionProfile {
+        hotspots: Some(Hotspots::new(source.len())),
+        ..InstructionProfile::default()
+    };
+    profile_run_program(&mut interpreter, &ast, &mut profile)?;
+    let hotspots = profile.hotspots.unwrap();
+    // the loop condition is checked before each of the 3 iterations and once more
+    assert_eq!(hotspots.hottest(3), [(3, 4), (4, 3), (5, 3)]);
+    assert_eq!(
+        hotspots.report(source).lines().nth(1),
+        Some("3         [                      4")
+    );
+
+    Ok(())
+}
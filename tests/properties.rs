@@ -0,0 +1,53 @@
+use brainfuck::{
+    interpreter_init, lexical_analysis_str, run_program, syntax_analysis, InputSource,
+};
+use proptest::prelude::*;
+use std::io;
+
+fn brainfuck_source(max_length: usize) -> impl Strategy<Value = String> {
+    proptest::string::string_regex(&format!("[><+\\-.,\\[\\]]{{0,{}}}", max_length)).unwrap()
+}
+
+// Drop the brackets without a partner so the program parses
+fn balance(source: String) -> String {
+    let mut open = Vec::new();
+    let mut keep = vec![true; source.len()];
+    for (position, c) in source.char_indices() {
+        match c {
+            '[' => open.push(position),
+            ']' => keep[position] = open.pop().is_some(),
+            _ => {}
+        }
+    }
+    open.iter().for_each(|&position| keep[position] = false);
+    source
+        .char_indices()
+        .filter(|&(position, _)| keep[position])
+        .map(|(_, c)| c)
+        .collect()
+}
+
+proptest! {
+    #[test]
+    fn lexing_never_panics(source in brainfuck_source(50)) {
+        let commands = lexical_analysis_str(&source);
+        prop_assert_eq!(commands.len(), source.len());
+    }
+
+    #[test]
+    fn parsing_never_panics(source in brainfuck_source(50)) {
+        let commands = lexical_analysis_str(&source);
+        let _ = syntax_analysis(commands, &source);
+    }
+
+    #[test]
+    fn running_never_panics(source in brainfuck_source(20).prop_map(balance)) {
+        let commands = lexical_analysis_str(&source);
+        let ast = syntax_analysis(commands, &source).unwrap();
+        let mut interpreter = interpreter_init(16);
+        interpreter.max_steps = Some(10000);
+        interpreter.input = InputSource::Buffer(Vec::new(), 0);
+        interpreter.output = Box::new(io::sink());
+        let _ = run_program(&mut interpreter, &ast);
+    }
+}
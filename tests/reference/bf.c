@@ -0,0 +1,68 @@
+/*
+ * Reference Brainfuck interpreter for tests/differential.rs, written to be
+ * obviously correct rather than fast: no optimizations, one command at a time.
+ *
+ * usage: bf PROGRAM TAPE_SIZE EOF
+ *   the pointer wraps around a tape of TAPE_SIZE cells, cells wrap at 256 and
+ *   EOF is "zero", "max" or "unchanged", like --eof of the interpreter
+ */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+int main(int argc, char **argv) {
+    if (argc != 4) {
+        fprintf(stderr, "usage: bf PROGRAM TAPE_SIZE EOF\n");
+        return 2;
+    }
+    FILE *file = fopen(argv[1], "rb");
+    if (!file) {
+        perror(argv[1]);
+        return 2;
+    }
+    static char code[1 << 20];
+    size_t length = fread(code, 1, sizeof code, file);
+    fclose(file);
+
+    long size = atol(argv[2]);
+    unsigned char *tape = calloc(size, 1);
+    long pointer = 0;
+    for (size_t pc = 0; pc < length; pc++) {
+        switch (code[pc]) {
+        case '>': pointer = (pointer + 1) % size; break;
+        case '<': pointer = (pointer + size - 1) % size; break;
+        case '+': tape[pointer]++; break;
+        case '-': tape[pointer]--; break;
+        case '.': putchar(tape[pointer]); break;
+        case ',': {
+            int c = getchar();
+            if (c != EOF) {
+                tape[pointer] = c;
+            } else if (strcmp(argv[3], "zero") == 0) {
+                tape[pointer] = 0;
+            } else if (strcmp(argv[3], "max") == 0) {
+                tape[pointer] = 255;
+            }
+            break;
+        }
+        case '[':
+            if (!tape[pointer]) {
+                for (int depth = 1; depth; ) {
+                    pc++;
+                    depth += (code[pc] == '[') - (code[pc] == ']');
+                }
+            }
+            break;
+        case ']':
+            if (tape[pointer]) {
+                for (int depth = 1; depth; ) {
+                    pc--;
+                    depth += (code[pc] == ']') - (code[pc] == '[');
+                }
+            }
+            break;
+        }
+    }
+    free(tape);
+    return 0;
+}
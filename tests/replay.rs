@@ -0,0 +1,98 @@
+use brainfuck::{
+    interpreter_init,
+    replay::{Event, Replay, ReplayError, ReplayLog},
+    run_program, InputSource, InterpreterError,
+};
+
+mod common;
+use common::parse;
+
+// The log of a run of `source` reading `input`
+fn record(source: &str, input: &[u8]) -> Result<ReplayLog, Box<dyn std::error::Error>> {
+    let mut interpreter = interpreter_init(8);
+    interpreter.input = InputSource::Buffer(input.to_vec(), 0);
+    interpreter.output = Box::new(Vec::new());
+    interpreter.replay = Some(Replay::Record(ReplayLog::default()));
+    run_program(&mut interpreter, &parse(source)?)?;
+    match interpreter.replay {
+        Some(Replay::Record(log)) => Ok(log),
+        _ => Err("the log was not kept".into()),
+    }
+}
+
+#[test]
+fn record_logs_every_byte_with_its_step() -> Result<(), Box<dyn std::error::Error>> {
+    let log = record(",+.,", b"a")?;
+    assert_eq!(
+        log.events,
+        vec![
+            Event::Input {
+                step: 1,
+                byte: Some(b'a')
+            },
+            Event::Output {
+                step: 3,
+                byte: b'b'
+            },
+            Event::Input {
+                step: 4,
+                byte: None
+            },
+        ]
+    );
+    assert_eq!(ReplayLog::deserialize(&log.serialize())?, log);
+    assert_eq!(
+        ReplayLog::deserialize(b"BFREPL\x01\x03"),
+        Err(ReplayError::InvalidFormat)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn replay_feeds_the_input_and_checks_the_output() -> Result<(), Box<dyn std::error::Error>> {
+    let log = record(",+.", b"a")?;
+    let mut interpreter = interpreter_init(8);
+    interpreter.input = InputSource::Buffer(b"z".to_vec(), 0);
+    interpreter.output = Box::new(Vec::new());
+    interpreter.replay = Some(Replay::play(log.clone()));
+    run_program(&mut interpreter, &parse(",+.")?)?;
+    assert_eq!(interpreter.memory[0], b'b');
+    assert_eq!(
+        interpreter.replay.as_ref().map(Replay::finish),
+        Some(Ok(()))
+    );
+
+    let mut interpreter = interpreter_init(8);
+    interpreter.output = Box::new(Vec::new());
+    interpreter.replay = Some(Replay::play(log));
+    assert_eq!(
+        run_program(&mut interpreter, &parse(",++.")?),
+        Err(InterpreterError::Replay(ReplayError::OutputDiverged {
+            step: 4,
+            expected: Some(b'b'),
+            found: Some(b'c')
+        }))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn replay_fails_when_output_is_missing() {
+    let mut replay = Replay::play(ReplayLog {
+        events: vec![Event::Output { step: 2, byte: 7 }],
+    });
+    assert_eq!(
+        replay.finish(),
+        Err(ReplayError::OutputDiverged {
+            step: 2,
+            expected: Some(7),
+            found: None
+        })
+    );
+    assert_eq!(
+        replay.input(1, || Some(0)),
+        Err(ReplayError::InputDiverged { step: 1 })
+    );
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "serde")]
+
+use brainfuck::{optimizer::optimize, Command, Node, NodeType, Program};
+
+mod common;
+use common::parse;
+
+#[test]
+fn tree_serializes_to_json() -> Result<(), Box<dyn std::error::Error>> {
+    let json = serde_json::to_string(&parse("+[-]")?)?;
+    assert_eq!(
+        json,
+        "{\"node_type\":\"Program\",\"instruction\":\"Default\",\"source_pos\":0,\"childrens\":[\
+         {\"node_type\":\"Operator\",\"instruction\":\"IncByte\",\"source_pos\":0,\"childrens\":[]},\
+         {\"node_type\":\"Loop\",\"instruction\":\"JumpForward\",\"source_pos\":1,\"childrens\":[\
+         {\"node_type\":\"Operator\",\"instruction\":\"DecByte\",\"source_pos\":2,\"childrens\":[]}]}]}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn optimized_tree_round_trips() -> Result<(), Box<dyn std::error::Error>> {
+    let mut ast = parse("+++[->>+<<]>>[<]")?;
+    optimize(&mut ast);
+    let json = serde_json::to_string(&ast)?;
+    assert!(json.contains("{\"AddTo\":{\"offset\":2}}"));
+    let restored: Node = serde_json::from_str(&json)?;
+    assert_eq!(restored.node_type, NodeType::Program);
+    let instructions: Vec<Command> = restored.childrens.iter().map(|n| n.instruction).collect();
+    assert_eq!(
+        instructions,
+        vec![
+            Command::AddByte(3),
+            Command::AddTo { offset: 2 },
+            Command::MoveBy(2),
+            Command::ScanLeft
+        ]
+    );
+
+    Ok(())
+}
+
+#[derive(serde::Deserialize)]
+struct Example {
+    name: String,
+    program: Program,
+}
+
+#[test]
+fn programs_deserialize_from_source() -> Result<(), Box<dyn std::error::Error>> {
+    let example: Example = serde_json::from_str(r#"{"name": "move", "program": "++[->+<]"}"#)?;
+    assert_eq!(example.name, "move");
+    assert!(example.program.ast == parse("++[->+<]")?);
+    assert_eq!(serde_json::to_string(&example.program)?, "\"++[->+<]\"");
+
+    let error = serde_json::from_str::<Example>(r#"{"name": "broken", "program": "++["}"#)
+        .err()
+        .unwrap();
+    assert!(error
+        .to_string()
+        .starts_with("error: unmatched '[' at line 1, column 3"));
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use brainfuck::{
+    interpreter_init, run_program,
+    snapshot::{
+        restore_checkpoint, restore_snapshot, serialize_checkpoint, serialize_snapshot,
+        SnapshotError,
+    },
+    vm::{flatten_commands, Checkpoints, FlatInterpreter},
+};
+
+mod common;
+use common::parse;
+
+#[test]
+fn snapshot_round_trips() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("+++>++>+<")?;
+    let mut saved = interpreter_init(5);
+    run_program(&mut saved, &ast)?;
+    saved.pointer = -7;
+    let bytes = serialize_snapshot(&saved)?;
+    assert_eq!(bytes.len(), 7 + 4 + 5 + 4);
+    assert!(bytes.starts_with(b"BFSNAP\x01\x05\x00\x00\x00\x03\x02\x01"));
+
+    let mut restored = interpreter_init(30);
+    restore_snapshot(&mut restored, &bytes)?;
+    assert_eq!(restored.memory, saved.memory);
+    assert_eq!(restored.tape_size, 5);
+    assert_eq!(restored.pointer, 3);
+
+    Ok(())
+}
+
+#[test]
+fn corrupt_snapshots_are_rejected() -> Result<(), Box<dyn std::error::Error>> {
+    let bytes = serialize_snapshot(&interpreter_init(4))?;
+    let mut interpreter = interpreter_init(4);
+    for corrupt in [
+        &b"BFSNAP"[..],
+        &b"BFSNAP\x02\x04\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00"[..],
+        &b"BFSNAP\x01\x00\x00\x00\x00\x00\x00\x00\x00"[..],
+        &bytes[..bytes.len() - 1],
+    ] {
+        assert_eq!(
+            restore_snapshot(&mut interpreter, corrupt),
+            Err(SnapshotError::InvalidFormat)
+        );
+    }
+    // the pointer is off the tape of the snapshot, on either side
+    for pointer in [-1i32, 4] {
+        let mut off_tape = bytes.clone();
+        off_tape.truncate(bytes.len() - 4);
+        off_tape.extend_from_slice(&pointer.to_le_bytes());
+        assert_eq!(
+            restore_snapshot(&mut interpreter, &off_tape),
+            Err(SnapshotError::InvalidFormat)
+        );
+    }
+    assert_eq!(interpreter.tape_size, 4);
+    let mut longer = bytes.clone();
+    longer.push(0);
+    assert_eq!(
+        restore_snapshot(&mut interpreter, &longer),
+        Err(SnapshotError::InvalidFormat)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn flat_interpreter_continues_from_a_checkpoint() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("flat-{}.bfck", std::process::id()));
+    let commands = flatten_commands(&parse("+++[>++<-]>.")?);
+    let mut interpreter = interpreter_init(4);
+    interpreter.output = Box::new(Vec::new());
+    let mut flat = FlatInterpreter::new(commands.clone(), &mut interpreter)?;
+    flat.checkpoints = Some(Checkpoints::new(10, path.clone()));
+    flat.run()?;
+
+    // the last checkpoint was saved before the 21st command, the '-' of the last iteration
+    let mut restored = interpreter_init(1);
+    let pc = restore_checkpoint(&mut restored, &std::fs::read(&path)?)?;
+    assert_eq!((pc, restored.tape_size), (8, 4));
+    let mut flat = FlatInterpreter::new(commands, &mut restored)?;
+    flat.pc = pc;
+    flat.run()?;
+    assert_eq!(restored.memory, interpreter.memory);
+    std::fs::remove_file(&path)?;
+
+    assert_eq!(
+        restore_checkpoint(&mut restored, &serialize_snapshot(&interpreter)?),
+        Err(SnapshotError::InvalidFormat)
+    );
+    assert!(serialize_checkpoint(&interpreter, 3)?.ends_with(&[3, 0, 0, 0, 0, 0, 0, 0]));
+
+    Ok(())
+}
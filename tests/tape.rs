@@ -0,0 +1,132 @@
+use std::{cell::RefCell, io, io::Write, rc::Rc};
+
+use brainfuck::{
+    interpreter_init, run_on_tape, run_program, run_with_sparse_tape,
+    tape::{MmapTape, SparseTape, Tape},
+};
+
+mod common;
+use common::parse;
+
+// Output shared with the test after the interpreter took ownership of it
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn sparse_tape_stores_only_nonzero_cells() {
+    let mut tape = SparseTape::new(30000);
+    tape.set(7, 3);
+    tape.set(29999, 1);
+    assert_eq!(tape.get(7), 3);
+    assert_eq!(tape.get(8), 0);
+    assert_eq!(tape.stored(), 2);
+    tape.set(7, 0);
+    assert_eq!(tape.stored(), 1);
+    assert_eq!(tape.len(), 30000);
+
+    tape.grow_front(10);
+    assert_eq!(tape.get(30009), 1);
+    assert_eq!(tape.rfind_zero_until(30009), Some(30008));
+    assert_eq!(tape.find_zero_from(30009), None);
+    tape.resize(100);
+    assert_eq!((tape.len(), tape.stored()), (100, 0));
+}
+
+#[test]
+fn program_runs_the_same_on_a_sparse_tape() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse(&std::fs::read_to_string("./test_files/hello-world.txt")?)?;
+    let mut outputs = Vec::new();
+    for sparse in [false, true].iter() {
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let mut interpreter = interpreter_init(30000);
+        interpreter.output = Box::new(SharedOutput(Rc::clone(&output)));
+        let cells: Vec<(usize, u8)> = if *sparse {
+            let (tape, result) = run_with_sparse_tape(&mut interpreter, &ast);
+            result?;
+            (0..tape.len())
+                .map(|index| (index, tape.get(index)))
+                .filter(|&(_, value)| value != 0)
+                .collect()
+        } else {
+            run_program(&mut interpreter, &ast)?;
+            interpreter.nonzero_cells().collect()
+        };
+        outputs.push((output.take(), cells, interpreter.pointer));
+    }
+    assert_eq!(outputs[0], outputs[1]);
+
+    Ok(())
+}
+
+#[test]
+fn sparse_tape_keeps_only_the_cells_used() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = interpreter_init(0);
+    interpreter.tape_size = 1 << 40;
+    let (tape, result) = run_with_sparse_tape(&mut interpreter, &parse("+>>>>>+<<")?);
+    result?;
+    assert_eq!(tape.stored(), 2);
+    assert_eq!((tape.get(0), tape.get(5)), (1, 1));
+    assert!(interpreter.memory.is_empty());
+    assert_eq!(interpreter.pointer, 3);
+    assert_eq!(interpreter.tape_size, 1 << 40);
+
+    // the last cell of the tape is never copied out of it
+    let (tape, result) = run_with_sparse_tape(&mut interpreter, &parse("<<<<+")?);
+    result?;
+    assert_eq!(tape.stored(), 1);
+    assert_eq!(tape.get((1 << 40) - 1), 1);
+    assert!(interpreter.memory.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn mmap_tape_starts_from_the_cells_of_its_file() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("tape-{}.tape", std::process::id()));
+    std::fs::write(&path, [0, 2, 0])?;
+    let mut interpreter = interpreter_init(0);
+    run_on_tape(
+        &mut interpreter,
+        &parse(">[->+<]")?,
+        MmapTape::open(&path, 4)?,
+    )
+    .1?;
+    assert_eq!(interpreter.tape_size, 4);
+    assert_eq!(std::fs::read(&path)?, [0, 0, 2, 0]);
+    std::fs::remove_file(&path)?;
+
+    Ok(())
+}
+
+#[test]
+fn mmap_tape_never_cuts_its_file() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("tape-larger-{}.tape", std::process::id()));
+    std::fs::write(&path, [1, 2, 3, 4, 5])?;
+    let error = MmapTape::open(&path, 3).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(std::fs::read(&path)?, [1, 2, 3, 4, 5]);
+    std::fs::remove_file(&path)?;
+
+    Ok(())
+}
+
+#[test]
+fn persistent_tape_needs_a_file_of_the_tape_size() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("persistent-{}.tape", std::process::id()));
+    MmapTape::open_persistent(&path, 3)?.set(1, 7);
+    let tape = MmapTape::open_persistent(&path, 3)?;
+    assert_eq!((tape.len(), tape.get(1)), (3, 7));
+    assert!(MmapTape::open_persistent(&path, 4).is_err());
+    std::fs::remove_file(&path)?;
+
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use brainfuck::{
+    interpreter_init,
+    optimizer::optimize,
+    trace::{trace_run_program, TraceContext},
+};
+
+mod common;
+use common::parse;
+
+#[test]
+fn trace_writes_one_record_per_instruction() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("+[->+<]")?;
+    let mut interpreter = interpreter_init(8);
+    let mut out = Vec::new();
+    let mut trace = TraceContext {
+        step: 0,
+        out: &mut out,
+    };
+    trace_run_program(&mut interpreter, &ast, &mut trace)?;
+    assert_eq!(trace.step, 5);
+    assert_eq!(
+        String::from_utf8(out)?,
+        "TRACE step=1 cmd=IncByte pointer=0 cell[0]=0\n\
+         TRACE step=2 cmd=DecByte pointer=0 cell[0]=1\n\
+         TRACE step=3 cmd=IncDP pointer=0 cell[0]=0\n\
+         TRACE step=4 cmd=IncByte pointer=1 cell[1]=0\n\
+         TRACE step=5 cmd=DecDP pointer=1 cell[1]=1\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn trace_records_are_key_value_pairs() -> Result<(), Box<dyn std::error::Error>> {
+    let mut ast = parse("+++[->>+<<]>>[<]")?;
+    optimize(&mut ast);
+    let mut interpreter = interpreter_init(8);
+    let mut out = Vec::new();
+    let mut trace = TraceContext {
+        step: 0,
+        out: &mut out,
+    };
+    trace_run_program(&mut interpreter, &ast, &mut trace)?;
+    let out = String::from_utf8(out)?;
+    assert!(out.contains("cmd=AddTo(2)"));
+    for line in out.lines() {
+        let mut fields = line.split(' ');
+        assert_eq!(fields.next(), Some("TRACE"));
+        assert!(fields.all(|field| field.split('=').count() == 2));
+    }
+
+    Ok(())
+}
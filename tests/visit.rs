@@ -0,0 +1,53 @@
+use brainfuck::{
+    visit::{print_ast, walk, Visitor},
+    Command, Node,
+};
+
+mod common;
+use common::parse;
+
+// Counts operators and the deepest loop nesting
+#[derive(Default)]
+struct Counter {
+    operators: usize,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl Visitor for Counter {
+    fn visit_loop(&mut self, node: &Node) {
+        self.depth += 1;
+        self.max_depth = self.max_depth.max(self.depth);
+        for child in node.childrens.iter() {
+            walk(child, self);
+        }
+        self.depth -= 1;
+    }
+
+    fn visit_operator(&mut self, _node: &Node, _cmd: Command) {
+        self.operators += 1;
+    }
+}
+
+#[test]
+fn default_methods_recurse() -> Result<(), Box<dyn std::error::Error>> {
+    let mut counter = Counter::default();
+    walk(&parse("+[>[-]<[[.]]]-")?, &mut counter);
+    assert_eq!(counter.operators, 6);
+    assert_eq!(counter.max_depth, 3);
+
+    Ok(())
+}
+
+#[test]
+fn printer_indents_loops() -> Result<(), Box<dyn std::error::Error>> {
+    assert_eq!(
+        print_ast(&parse("+[-].")?),
+        "Operator  ---  IncByte\n\
+         Loop  ---  JumpForward\n     \
+         Operator  ---  DecByte\n\
+         Operator  ---  OutByte\n"
+    );
+
+    Ok(())
+}
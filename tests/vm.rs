@@ -0,0 +1,216 @@
+use brainfuck::{
+    bytecode::{compile_bytecode, Bytecode, BytecodeError},
+    interpreter_init,
+    optimizer::optimize,
+    run_program,
+    vm::{build_jump_table, flatten_commands, FlatInterpreter, FlatVm, JumpTableError},
+    Command, InterpreterError, OverflowMode,
+};
+
+mod common;
+use common::parse;
+
+#[test]
+fn vm_runs_like_the_tree_walker() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "++++++[->++++++++<]>+[>+<-]>>+++[-<+<+>>]<<<[>]+[[-]>+[<]>>]";
+    for optimized in [false, true] {
+        let mut ast = parse(source)?;
+        if optimized {
+            optimize(&mut ast);
+        }
+        let mut expected = interpreter_init(16);
+        run_program(&mut expected, &ast)?;
+        let mut actual = interpreter_init(16);
+        let mut vm = FlatVm::new(compile_bytecode(&ast), &mut actual)?;
+        vm.run()?;
+        assert_eq!(vm.pc, vm.program.len());
+        assert_eq!(actual.memory, expected.memory);
+        assert_eq!(actual.pointer, expected.pointer);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn vm_skips_loops_on_zero() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("[>+<-[+]]>+")?;
+    let mut interpreter = interpreter_init(4);
+    FlatVm::new(compile_bytecode(&ast), &mut interpreter)?.run()?;
+    assert_eq!(interpreter.memory, vec![0, 1, 0, 0]);
+
+    Ok(())
+}
+
+#[test]
+fn vm_reports_pointer_overflow() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("+[>+]")?;
+    let mut interpreter = interpreter_init(4);
+    interpreter.overflow_mode = OverflowMode::Error;
+    let result = FlatVm::new(compile_bytecode(&ast), &mut interpreter)?.run();
+    assert_eq!(
+        result,
+        Err(InterpreterError::PointerOutOfBounds { pointer: 4 })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn vm_counts_steps_like_the_tree_walker() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("+++[>++[-]<-]")?;
+    let mut expected = interpreter_init(4);
+    expected.max_steps = Some(1000);
+    run_program(&mut expected, &ast)?;
+    let mut actual = interpreter_init(4);
+    actual.max_steps = Some(1000);
+    FlatVm::new(compile_bytecode(&ast), &mut actual)?.run()?;
+    assert_eq!(actual.steps, expected.steps);
+
+    let mut interpreter = interpreter_init(4);
+    interpreter.max_steps = Some(7);
+    assert_eq!(
+        FlatVm::new(compile_bytecode(&parse("+[]")?), &mut interpreter)?.run(),
+        Err(InterpreterError::StepLimitExceeded { steps: 7 })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn jump_table_pairs_brackets() -> Result<(), Box<dyn std::error::Error>> {
+    use Command::*;
+    let commands = [
+        IncByte,
+        JumpForward,
+        IncDP,
+        JumpForward,
+        DecByte,
+        JumpBackward,
+        JumpBackward,
+    ];
+    assert_eq!(build_jump_table(&commands)?, vec![0, 6, 0, 5, 0, 3, 1]);
+    assert_eq!(
+        build_jump_table(&[JumpForward, JumpBackward, JumpBackward]),
+        Err(JumpTableError::UnmatchedClose { offset: 2 })
+    );
+    let error = build_jump_table(&[JumpForward, JumpForward, JumpBackward]).unwrap_err();
+    assert_eq!(error, JumpTableError::UnmatchedOpen { offset: 0 });
+    assert_eq!(error.to_string(), "unmatched '[' at offset 0");
+
+    Ok(())
+}
+
+#[test]
+fn vm_checks_the_stored_jump_targets() -> Result<(), Box<dyn std::error::Error>> {
+    let mut program = compile_bytecode(&parse("+[>+<-]")?);
+    assert_eq!(program[1], Bytecode::JumpForward(7));
+    assert_eq!(program[6], Bytecode::JumpBackward(2));
+
+    program[6] = Bytecode::JumpBackward(3);
+    let mut interpreter = interpreter_init(4);
+    assert_eq!(
+        FlatVm::new(program.clone(), &mut interpreter).err(),
+        Some(BytecodeError::InvalidJumpTarget { position: 6 })
+    );
+    program.truncate(6);
+    assert_eq!(
+        FlatVm::new(program, &mut interpreter).err(),
+        Some(BytecodeError::InvalidJumpTarget { position: 1 })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn flat_interpreter_runs_like_the_tree_walker() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "++++++[->++++++++<]>+[>+<-]>>+++[-<+<+>>]<<<[>]+[[-]>+[<]>>]";
+    for optimized in [false, true] {
+        let mut ast = parse(source)?;
+        if optimized {
+            optimize(&mut ast);
+        }
+        let mut expected = interpreter_init(16);
+        expected.max_steps = Some(100_000);
+        run_program(&mut expected, &ast)?;
+        let mut actual = interpreter_init(16);
+        actual.max_steps = Some(100_000);
+        let mut flat = FlatInterpreter::new(flatten_commands(&ast), &mut actual)?;
+        flat.run()?;
+        assert_eq!(flat.pc, flat.commands.len());
+        assert_eq!(actual.memory, expected.memory);
+        assert_eq!(actual.pointer, expected.pointer);
+        assert_eq!(actual.steps, expected.steps);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn dispatch_table_runs_like_the_match() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "++++++[->++++++++<]>+[>+<-]>>+++[-<+<+>>]<<<[>]+[[-]>+[<]>>]";
+    for optimized in [false, true] {
+        let mut ast = parse(source)?;
+        if optimized {
+            optimize(&mut ast);
+        }
+        let mut expected = interpreter_init(16);
+        expected.max_steps = Some(100_000);
+        FlatInterpreter::new(flatten_commands(&ast), &mut expected)?.run()?;
+        let mut actual = interpreter_init(16);
+        actual.max_steps = Some(100_000);
+        let mut flat = FlatInterpreter::new(flatten_commands(&ast), &mut actual)?;
+        flat.run_dispatch()?;
+        assert_eq!(flat.pc, flat.commands.len());
+        assert_eq!(actual.memory, expected.memory);
+        assert_eq!(actual.pointer, expected.pointer);
+        assert_eq!(actual.steps, expected.steps);
+    }
+    let mut interpreter = interpreter_init(4);
+    interpreter.overflow_mode = OverflowMode::Error;
+    let result =
+        FlatInterpreter::new(flatten_commands(&parse("+[>+]")?), &mut interpreter)?.run_dispatch();
+    assert_eq!(
+        result,
+        Err(InterpreterError::PointerOutOfBounds { pointer: 4 })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn flatten_commands_turns_loops_into_brackets() -> Result<(), Box<dyn std::error::Error>> {
+    use Command::*;
+    let mut ast = parse("+[>[-]<-]")?;
+    assert_eq!(
+        flatten_commands(&ast),
+        vec![
+            IncByte,
+            JumpForward,
+            IncDP,
+            JumpForward,
+            DecByte,
+            JumpBackward,
+            DecDP,
+            DecByte,
+            JumpBackward
+        ]
+    );
+    optimize(&mut ast);
+    assert!(flatten_commands(&ast).contains(&SetZero));
+
+    Ok(())
+}
+
+#[test]
+fn flat_interpreter_reports_pointer_overflow() -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse("+[>+]")?;
+    let mut interpreter = interpreter_init(4);
+    interpreter.overflow_mode = OverflowMode::Error;
+    let result = FlatInterpreter::new(flatten_commands(&ast), &mut interpreter)?.run();
+    assert_eq!(
+        result,
+        Err(InterpreterError::PointerOutOfBounds { pointer: 4 })
+    );
+
+    Ok(())
+}